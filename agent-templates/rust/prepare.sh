@@ -0,0 +1,31 @@
+# Prepares the project template used to compile Rust (cargo) programs:
+# copies the game server SDK next to it and vendors all the dependencies, so
+# that the compilation works without network access.
+set -xe
+cd "$(dirname "$0")"
+ROOT=../..
+VERSION=$(sed -n 's/^version = "\(.*\)"/\1/p' $ROOT/Cargo.toml)
+
+rm -rf proglad vendor
+mkdir -p proglad/proglad-api proglad/proglad-game
+cp -r $ROOT/api/src proglad/proglad-api/
+cp -r $ROOT/game-sdk/src proglad/proglad-game/
+cat > proglad/proglad-api/Cargo.toml <<EOT
+[package]
+name = "proglad-api"
+version = "$VERSION"
+edition = "2021"
+
+[dependencies]
+$(sed -n '/^serde = /p; /^serde_json = /p' $ROOT/Cargo.toml)
+EOT
+cat > proglad/proglad-game/Cargo.toml <<EOT
+[package]
+name = "proglad-game"
+version = "$VERSION"
+edition = "2021"
+
+[dependencies]
+proglad-api = { path = "../proglad-api" }
+EOT
+cargo vendor --versioned-dirs vendor > /dev/null
@@ -0,0 +1,494 @@
+// A decoder for Hjson (https://hjson.github.io/syntax.html): JSON with
+// comments, optional quotes and commas, and multiline strings. It follows the
+// reference implementation and gives the same values as serde_json for JSON.
+use serde_json::{Map, Number, Value};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub msg: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} at line {} column {}",
+            self.msg, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub fn parse(text: &str) -> Result<Value, ParseError> {
+    let mut p = Parser { text, at: 0 };
+    p.white();
+    let value = match p.peek() {
+        Some(b'{' | b'[') => p.value()?,
+        _ => {
+            // The braces of the root object may be omitted. If that fails,
+            // the text may still be a single value like a number or string.
+            let mut braceless = p;
+            match braceless.members(None) {
+                Ok(members) => {
+                    p = braceless;
+                    Value::Object(members)
+                }
+                Err(e) => p.value().map_err(|_| e)?,
+            }
+        }
+    };
+    p.white();
+    if p.at < text.len() {
+        return Err(p.error("trailing characters"));
+    }
+    Ok(value)
+}
+
+#[derive(Clone, Copy)]
+struct Parser<'a> {
+    text: &'a str,
+    // Byte offset, always on a char boundary.
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.text.as_bytes().get(self.at + offset).copied()
+    }
+
+    fn error(&self, msg: &str) -> ParseError {
+        let before = &self.text[..self.at.min(self.text.len())];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        ParseError {
+            msg: msg.to_owned(),
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while self.peek().is_some_and(|c| c != b'\n') {
+            self.at += 1;
+        }
+    }
+
+    // Skips whitespace and comments.
+    fn white(&mut self) {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(c), _) if c <= b' ' => self.at += 1,
+                (Some(b'#'), _) | (Some(b'/'), Some(b'/')) => self.skip_line(),
+                (Some(b'/'), Some(b'*')) => {
+                    self.at = match self.text[self.at + 2..].find("*/") {
+                        Some(i) => self.at + 2 + i + 2,
+                        None => self.text.len(),
+                    };
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, ParseError> {
+        self.white();
+        match self.peek() {
+            Some(b'{') => {
+                self.at += 1;
+                self.members(Some(b'}')).map(Value::Object)
+            }
+            Some(b'[') => {
+                self.at += 1;
+                self.array().map(Value::Array)
+            }
+            Some(q @ (b'"' | b'\'')) => self.string(q).map(Value::String),
+            Some(_) => self.quoteless(),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    // The members of an object after its '{', or of the braceless root object
+    // when there is no closing brace.
+    fn members(&mut self, close: Option<u8>) -> Result<Map<String, Value>, ParseError> {
+        let mut res = Map::new();
+        self.white();
+        loop {
+            if close.is_some() && self.peek() == close {
+                self.at += 1;
+                return Ok(res);
+            }
+            if self.peek().is_none() {
+                return match close {
+                    None => Ok(res),
+                    Some(_) => Err(self.error("missing '}'")),
+                };
+            }
+            let key = self.key()?;
+            self.white();
+            if self.peek() != Some(b':') {
+                return Err(self.error("expected ':'"));
+            }
+            self.at += 1;
+            let value = self.value()?;
+            res.insert(key, value);
+            self.white();
+            if self.peek() == Some(b',') {
+                self.at += 1;
+                self.white();
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Vec<Value>, ParseError> {
+        let mut res = Vec::new();
+        self.white();
+        loop {
+            match self.peek() {
+                Some(b']') => {
+                    self.at += 1;
+                    return Ok(res);
+                }
+                None => return Err(self.error("missing ']'")),
+                Some(_) => {}
+            }
+            res.push(self.value()?);
+            self.white();
+            if self.peek() == Some(b',') {
+                self.at += 1;
+                self.white();
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<String, ParseError> {
+        if let Some(q @ (b'"' | b'\'')) = self.peek() {
+            return self.string(q);
+        }
+        let start = self.at;
+        // Where whitespace was first seen, which is only allowed before ':'.
+        let mut space = None;
+        loop {
+            match self.peek() {
+                Some(b':') => {
+                    if self.at == start {
+                        return Err(self.error("empty key"));
+                    }
+                    let end = space.unwrap_or(self.at);
+                    if self.text[start..end].len() != self.text[start..self.at].trim_end().len() {
+                        return Err(self.error("whitespace in key"));
+                    }
+                    return Ok(self.text[start..end].to_owned());
+                }
+                None => return Err(self.error("unexpected end of input in key")),
+                Some(c) if c <= b' ' => {
+                    space.get_or_insert(self.at);
+                }
+                Some(b'{' | b'}' | b'[' | b']' | b',') => {
+                    return Err(self.error("punctuator in key, quote it"));
+                }
+                Some(_) => {}
+            }
+            self.at += 1;
+        }
+    }
+
+    fn string(&mut self, quote: u8) -> Result<String, ParseError> {
+        if quote == b'\'' && self.peek_at(1) == Some(b'\'') && self.peek_at(2) == Some(b'\'') {
+            return self.multiline_string();
+        }
+        self.at += 1;
+        let mut res = String::new();
+        loop {
+            let rest = &self.text[self.at..];
+            let Some(i) = rest.find([quote as char, '\\', '\n', '\r']) else {
+                return Err(self.error("unterminated string"));
+            };
+            res.push_str(&rest[..i]);
+            self.at += i;
+            match self.peek() {
+                Some(b'\\') => {
+                    self.at += 1;
+                    res.push(self.escape()?);
+                }
+                Some(c) if c == quote => {
+                    self.at += 1;
+                    return Ok(res);
+                }
+                _ => return Err(self.error("newline in string")),
+            }
+        }
+    }
+
+    // The escape sequence after a backslash.
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\'') => '\'',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.at += 1;
+                let high = self.hex4()?;
+                if !(0xd800..0xdc00).contains(&high) {
+                    return char::from_u32(high).ok_or_else(|| self.error("invalid \\u escape"));
+                }
+                if !self.text[self.at..].starts_with("\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.at += 2;
+                let low = self.hex4()?;
+                if !(0xdc00..0xe000).contains(&low) {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                let c = 0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00);
+                return char::from_u32(c).ok_or_else(|| self.error("invalid \\u escape"));
+            }
+            _ => return Err(self.error("invalid escape")),
+        };
+        self.at += 1;
+        Ok(c)
+    }
+
+    fn hex4(&mut self) -> Result<u32, ParseError> {
+        let digits = self
+            .text
+            .get(self.at..self.at + 4)
+            .filter(|d| d.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.at += 4;
+        Ok(u32::from_str_radix(digits, 16).unwrap())
+    }
+
+    // A '''string''' that may span lines. The indentation of the opening
+    // quotes is removed from every line.
+    fn multiline_string(&mut self) -> Result<String, ParseError> {
+        let indent = self.at - self.text[..self.at].rfind('\n').map_or(0, |i| i + 1);
+        self.at += 3;
+        while self.peek().is_some_and(|c| c <= b' ' && c != b'\n') {
+            self.at += 1;
+        }
+        if self.peek() == Some(b'\n') {
+            self.at += 1;
+            self.skip_indent(indent);
+        }
+        let mut res = String::new();
+        loop {
+            let rest = &self.text[self.at..];
+            if rest.starts_with("'''") {
+                self.at += 3;
+                if res.ends_with('\n') {
+                    res.pop();
+                }
+                return Ok(res);
+            }
+            let Some(c) = rest.chars().next() else {
+                return Err(self.error("unterminated multiline string"));
+            };
+            self.at += c.len_utf8();
+            match c {
+                '\n' => {
+                    res.push('\n');
+                    self.skip_indent(indent);
+                }
+                '\r' => {}
+                _ => res.push(c),
+            }
+        }
+    }
+
+    fn skip_indent(&mut self, indent: usize) {
+        for _ in 0..indent {
+            if !self.peek().is_some_and(|c| c <= b' ' && c != b'\n') {
+                return;
+            }
+            self.at += 1;
+        }
+    }
+
+    // true, false, null, a number or a string without quotes, which runs to
+    // the end of the line. The others end at ',', '}', ']' or a comment too.
+    fn quoteless(&mut self) -> Result<Value, ParseError> {
+        if let Some(b'{' | b'}' | b'[' | b']' | b',' | b':') = self.peek() {
+            return Err(self.error("unexpected punctuator, quote the string"));
+        }
+        let start = self.at;
+        loop {
+            self.at += self.text[self.at..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            let c = self.peek();
+            let eol = matches!(c, None | Some(b'\n' | b'\r'));
+            let comment = c == Some(b'#')
+                || (c == Some(b'/') && matches!(self.peek_at(1), Some(b'/' | b'*')));
+            if eol || comment || matches!(c, Some(b',' | b'}' | b']')) {
+                let s = self.text[start..self.at].trim();
+                match s {
+                    "true" => return Ok(Value::Bool(true)),
+                    "false" => return Ok(Value::Bool(false)),
+                    "null" => return Ok(Value::Null),
+                    _ => {}
+                }
+                if let Some(n) = number(s) {
+                    return Ok(Value::Number(n));
+                }
+                if eol {
+                    return Ok(Value::String(s.to_owned()));
+                }
+            }
+        }
+    }
+}
+
+fn number(s: &str) -> Option<Number> {
+    let b = s.as_bytes();
+    let digits = |mut i: usize| {
+        while b.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        i
+    };
+    let int_start = usize::from(b.first() == Some(&b'-'));
+    let mut i = digits(int_start);
+    if i == int_start || (b[int_start] == b'0' && i > int_start + 1) {
+        return None;
+    }
+    let mut integer = true;
+    if b.get(i) == Some(&b'.') {
+        integer = false;
+        i = digits(i + 1);
+    }
+    if let Some(b'e' | b'E') = b.get(i) {
+        integer = false;
+        i += 1;
+        if let Some(b'+' | b'-') = b.get(i) {
+            i += 1;
+        }
+        let exp_start = i;
+        i = digits(i);
+        if i == exp_start {
+            return None;
+        }
+    }
+    if i != b.len() {
+        return None;
+    }
+    if integer {
+        if let Ok(n) = s.parse::<i64>() {
+            return Some(n.into());
+        }
+        if let Ok(n) = s.parse::<u64>() {
+            return Some(n.into());
+        }
+    }
+    s.parse::<f64>().ok().and_then(Number::from_f64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn same_as_json() {
+        for s in [
+            r#"{"a":1,"b":[true,false,null],"c":{"d":"e\"\\\/\b\f\n\r\té"}}"#,
+            r#"[1, -0.5, 2e3, -1E-2, 18446744073709551615, "", {}, []]"#,
+            r#""😀""#,
+            "12",
+        ] {
+            let expected: Value = serde_json::from_str(s).unwrap();
+            assert_eq!(parse(s), Ok(expected), "{s}");
+        }
+    }
+
+    #[test]
+    fn hjson_syntax() {
+        let text = "
+            # Comment.
+            {
+              // Comment.
+              t: 1.5 /* comment */
+              'quoted key': 'single \"quoted\"'
+              text: quoteless string, with a comma # not a comment
+              n: 3 // comment
+              list: [
+                1
+                2,
+                two words
+              ]
+              multi:
+                '''
+                first
+                  second
+                '''
+              trailing: [1, 2,],
+            }
+        ";
+        assert_eq!(
+            parse(text),
+            Ok(json!({
+                "t": 1.5,
+                "quoted key": "single \"quoted\"",
+                "text": "quoteless string, with a comma # not a comment",
+                "n": 3,
+                "list": [1, 2, "two words"],
+                "multi": "first\n  second",
+                "trailing": [1, 2],
+            }))
+        );
+    }
+
+    #[test]
+    fn braceless_root() {
+        assert_eq!(parse("a: 1\nb: x"), Ok(json!({"a": 1, "b": "x"})));
+        assert_eq!(parse(""), Ok(json!({})));
+        assert_eq!(parse("  null  "), Ok(Value::Null));
+    }
+
+    #[test]
+    fn keywords_and_numbers_only_as_whole_values() {
+        assert_eq!(
+            parse("{a: true story\nb: 01\nc: 1 2\nd: -\ne: 1e\n}"),
+            Ok(json!({"a": "true story", "b": "01", "c": "1 2", "d": "-", "e": "1e"})),
+        );
+    }
+
+    #[test]
+    fn errors() {
+        for s in [
+            "{a: 1",
+            "[1, 2",
+            "{a b: 1}",
+            "{a: ,}",
+            "{a: 'x}",
+            "{a: \"x\ny\"}",
+            "{a: 1} x",
+            "{a{: 1}",
+            "{a: '''x}",
+            r#"["\ud83d"]"#,
+            r#"["\q"]"#,
+        ] {
+            assert!(parse(s).is_err(), "{s}");
+        }
+        assert_eq!(
+            parse("{\n  a: [1\n").unwrap_err(),
+            ParseError {
+                msg: "missing ']'".to_owned(),
+                line: 3,
+                column: 1
+            },
+        );
+    }
+}
@@ -1,2 +1,4 @@
+pub mod hjson;
+pub mod render;
 pub mod textapi;
 pub mod visualize;
@@ -0,0 +1,1024 @@
+// Replay playback and SVG rendering that do not depend on the browser.
+// The visualizer turns the SVG nodes into DOM elements, the server renders
+// them to text, e.g. for match thumbnails.
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::visualize::geom::{Geom, PathSegment};
+use crate::visualize::{
+    validate_asset_name, AnimateFunction, Camera, Color, Event, Replay, Transform,
+};
+
+pub const SVG_NS: &str = "http://www.w3.org/2000/svg";
+
+// A 2D affine transform in the form of SVG "matrix(a b c d e f)":
+// x' = a*x + c*y + e, y' = b*x + d*y + f.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine {
+    pub a: f32,
+    pub b: f32,
+    pub c: f32,
+    pub d: f32,
+    pub e: f32,
+    pub f: f32,
+}
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine {
+        a: 1.,
+        b: 0.,
+        c: 0.,
+        d: 1.,
+        e: 0.,
+        f: 0.,
+    };
+
+    pub fn translation((dx, dy): (f32, f32)) -> Self {
+        Affine {
+            e: dx,
+            f: dy,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(s: f32) -> Self {
+        Affine {
+            a: s,
+            d: s,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn rotation(phi: f32) -> Self {
+        let (sin, cos) = phi.sin_cos();
+        Affine {
+            a: cos,
+            b: sin,
+            c: -sin,
+            d: cos,
+            e: 0.,
+            f: 0.,
+        }
+    }
+
+    pub fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.a * x + self.c * y + self.e,
+            self.b * x + self.d * y + self.f,
+        )
+    }
+}
+
+// `x * y` applies `y` first.
+impl std::ops::Mul for Affine {
+    type Output = Affine;
+    fn mul(self, r: Affine) -> Affine {
+        Affine {
+            a: self.a * r.a + self.c * r.b,
+            b: self.b * r.a + self.d * r.b,
+            c: self.a * r.c + self.c * r.d,
+            d: self.b * r.c + self.d * r.d,
+            e: self.a * r.e + self.c * r.f + self.e,
+            f: self.b * r.e + self.d * r.f + self.f,
+        }
+    }
+}
+
+impl std::ops::MulAssign for Affine {
+    fn mul_assign(&mut self, r: Affine) {
+        *self = *self * r;
+    }
+}
+
+impl std::fmt::Display for Affine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "matrix({} {} {} {} {} {})",
+            self.a, self.b, self.c, self.d, self.e, self.f
+        )
+    }
+}
+
+#[derive(Debug)]
+pub struct Object {
+    pub z_index: u8,
+    geometry: Vec<Geom>,
+    // Set while the colors are being animated.
+    current_geometry: Option<Vec<Geom>>,
+    base_opacity: f32,
+    pub current_opacity: f32,
+    base_position: (f32, f32),
+    current_position: (f32, f32),
+    base_transform: Affine,
+    current_transform: Affine,
+    parent: Option<u64>,
+    children: Vec<u64>,
+    // The current transform composed with the ones of all the ancestors.
+    pub world_transform: Affine,
+}
+
+impl Object {
+    pub fn current_geometry(&self) -> &[Geom] {
+        self.current_geometry.as_deref().unwrap_or(&self.geometry)
+    }
+}
+
+#[derive(Debug)]
+enum Animation {
+    Transform(Transform),
+    // Geoms in `from` and `to` are matched by index.
+    Style {
+        from: Vec<Geom>,
+        to: Vec<Geom>,
+        from_opacity: f32,
+        to_opacity: f32,
+    },
+}
+
+#[derive(Debug)]
+struct OngoingAnimation {
+    id: u64,
+    start_time: f32,
+    duration: f32,
+    animation: Animation,
+    animate_function: AnimateFunction,
+}
+
+fn map_animate_progress(f: AnimateFunction, progress: f32) -> f32 {
+    match f {
+        AnimateFunction::Step => {
+            if progress <= 0.5 {
+                0.0
+            } else {
+                1.0
+            }
+        }
+        AnimateFunction::Linear => progress,
+        AnimateFunction::EaseIn => 1.0 - (progress * std::f32::consts::PI * 0.5).cos(),
+        AnimateFunction::EaseOut => (progress * std::f32::consts::PI * 0.5).sin(),
+        AnimateFunction::EaseInOut => (1.0 + ((progress - 0.5) * std::f32::consts::PI).sin()) * 0.5,
+    }
+}
+
+impl OngoingAnimation {
+    fn apply(&self, time: f32, obj: &mut Object) -> bool {
+        let elapsed = time - self.start_time;
+        if elapsed < 0. {
+            return false;
+        }
+        let (progress, done) = if elapsed >= self.duration {
+            (1., true)
+        } else {
+            (elapsed / self.duration, false)
+        };
+        let p = map_animate_progress(self.animate_function, progress);
+
+        let transform = match &self.animation {
+            Animation::Transform(t) => *t,
+            Animation::Style {
+                from,
+                to,
+                from_opacity,
+                to_opacity,
+            } => {
+                if done {
+                    obj.geometry = to.clone();
+                    obj.base_opacity = *to_opacity;
+                    obj.current_geometry = None;
+                } else {
+                    obj.current_geometry = Some(lerp_geometry(from, to, p));
+                }
+                obj.current_opacity = from_opacity + (to_opacity - from_opacity) * p;
+                return done;
+            }
+        };
+        match transform {
+            Transform::Move((dx, dy)) => {
+                if done {
+                    obj.base_position.0 += dx;
+                    obj.base_position.1 += dy;
+                }
+                obj.current_position.0 += dx * p;
+                obj.current_position.1 += dy * p;
+            }
+            Transform::Scale(s) => {
+                if done {
+                    obj.base_transform *= Affine::scale(s);
+                }
+                obj.current_transform *= Affine::scale(s * p + 1.0 - p);
+            }
+            Transform::Rotate(phi) => {
+                if done {
+                    obj.base_transform *= Affine::rotation(phi);
+                }
+                obj.current_transform *= Affine::rotation(phi * p);
+            }
+        };
+        done
+    }
+}
+
+// Takes everything but the colors from `to`.
+fn lerp_geometry(from: &[Geom], to: &[Geom], p: f32) -> Vec<Geom> {
+    to.iter()
+        .enumerate()
+        .map(|(i, g)| {
+            let mut g = g.clone();
+            let Some(f) = from.get(i) else {
+                return g;
+            };
+            if let (Some(a), Some(b)) = (f.fill_color(), g.fill_color()) {
+                g = g.fill(a.lerp(b, p));
+            }
+            if let (Some(a), Some(b)) = (f.stroke_color(), g.stroke_color()) {
+                g = g.stroke(a.lerp(b, p));
+            }
+            g
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct CameraAnimation {
+    start_time: f32,
+    duration: f32,
+    animate_function: AnimateFunction,
+    from: Camera,
+    to: Camera,
+}
+
+// The state of the replay at some point in time.
+pub struct ReplayState {
+    pub replay: Replay,
+    camera: Camera,
+    current_camera: Camera,
+    camera_animation: Option<CameraAnimation>,
+    objects: HashMap<u64, Object>,
+    next_event_idx: usize,
+    time: f32,
+    highlighted_log: std::ops::Range<usize>,
+    animations: Vec<OngoingAnimation>,
+}
+
+// What changed since the previous update, for incremental rendering.
+#[derive(Default)]
+pub struct UpdateResult {
+    // Everything was destroyed and the replay started over.
+    pub reset: bool,
+    // Ids of the objects which world transform changed.
+    pub changed: Vec<u64>,
+    // Need to be rendered again from scratch.
+    pub restyled: Vec<u64>,
+    pub created: Vec<u64>,
+    pub deleted: Vec<u64>,
+    // Indices of the Log events.
+    pub highlighted_log: std::ops::Range<usize>,
+    pub unhighlighted_log: std::ops::Range<usize>,
+}
+
+impl ReplayState {
+    pub fn new(replay: Replay) -> Self {
+        let camera = Camera::new(replay.aspect);
+        Self {
+            replay,
+            camera,
+            current_camera: camera,
+            camera_animation: None,
+            objects: HashMap::new(),
+            next_event_idx: 0,
+            time: 0.,
+            animations: vec![],
+            highlighted_log: 0..0,
+        }
+    }
+    pub fn object(&self, id: u64) -> Option<&Object> {
+        self.objects.get(&id)
+    }
+    pub fn current_camera(&self) -> Camera {
+        self.current_camera
+    }
+    // Moves to the given time, going back to the start if needed.
+    pub fn update(&mut self, new_time: f32) -> UpdateResult {
+        if self.time > new_time {
+            let deleted: Vec<u64> = self.objects.keys().cloned().collect();
+            let unhighlight = self.highlighted_log.clone();
+            self.reset();
+            let mut upd = self.update(new_time);
+            upd.reset = true;
+            upd.deleted = deleted;
+            upd.unhighlighted_log = unhighlight;
+            if !upd.highlighted_log.is_empty() {
+                upd.highlighted_log.start = upd.highlighted_log.end - 1;
+            }
+            return upd;
+        }
+        self.time = new_time;
+        let mut res = UpdateResult::default();
+        let old_hilog = self.highlighted_log.clone();
+        while self.next_event_idx < self.replay.events.len()
+            && self.replay.events[self.next_event_idx].start_time <= new_time
+        {
+            self.process_event(self.next_event_idx, &mut res);
+            self.next_event_idx += 1;
+        }
+        if self.highlighted_log != old_hilog {
+            self.highlighted_log.start = old_hilog.end;
+            res.unhighlighted_log = old_hilog;
+            res.highlighted_log = self.highlighted_log.clone();
+        }
+        res.restyled.extend(
+            self.animations
+                .iter()
+                .filter(|a| matches!(a.animation, Animation::Style { .. }))
+                .map(|a| a.id),
+        );
+        self.animate();
+        self.update_world_transforms(&mut res);
+        res
+    }
+    pub fn get_log(&self) -> impl Iterator<Item = &str> {
+        self.replay.events.iter().filter_map(|e| match &e.event {
+            Event::Log { line } => Some(line.as_str()),
+            _ => None,
+        })
+    }
+    // The whole current frame as seen by the camera, as an SVG document.
+    // Image hrefs are `assets_url` followed by the file name.
+    pub fn to_svg(&self, assets_url: &str) -> String {
+        let camera = self.current_camera;
+        let (w, h) = camera.view_size(self.replay.aspect);
+        let mut objects = self.objects.iter().collect::<Vec<_>>();
+        objects.sort_by_key(|(id, o)| (o.z_index, **id));
+        let mut root = SvgNode::new("svg").attr("xmlns", SVG_NS).attr(
+            "viewBox",
+            format!(
+                "{} {} {w} {h}",
+                camera.center.0 - w * 0.5,
+                camera.center.1 - h * 0.5
+            ),
+        );
+        root.children = objects
+            .into_iter()
+            .map(|(_, o)| object_node(o, assets_url))
+            .collect();
+        root.to_string()
+    }
+    fn update_world_transforms(&mut self, res: &mut UpdateResult) {
+        let mut worlds = HashMap::with_capacity(self.objects.len());
+        for id in self.objects.keys() {
+            self.world_transform(*id, &mut worlds);
+        }
+        for (id, world) in worlds {
+            let Some(obj) = self.objects.get_mut(&id) else {
+                continue;
+            };
+            if obj.world_transform != world {
+                obj.world_transform = world;
+                res.changed.push(id);
+            }
+        }
+    }
+    // Iterative, so that deep hierarchies can't overflow the stack.
+    fn world_transform(&self, id: u64, worlds: &mut HashMap<u64, Affine>) -> Affine {
+        // Ancestors without a known world transform, starting from `id`.
+        let mut chain = vec![];
+        let mut visited = std::collections::HashSet::new();
+        let mut world = Affine::IDENTITY;
+        let mut next = Some(id);
+        while let Some(i) = next {
+            if let Some(w) = worlds.get(&i) {
+                world = *w;
+                break;
+            }
+            // Cycles can't be created, stop at the first repeat anyway.
+            if !visited.insert(i) {
+                break;
+            }
+            let Some(obj) = self.objects.get(&i) else {
+                break;
+            };
+            chain.push(i);
+            next = obj.parent;
+        }
+        for i in chain.into_iter().rev() {
+            let obj = &self.objects[&i];
+            world = world * Affine::translation(obj.current_position) * obj.current_transform;
+            worlds.insert(i, world);
+        }
+        world
+    }
+    fn animate(&mut self) {
+        for (_, obj) in self.objects.iter_mut() {
+            obj.current_position = obj.base_position;
+            obj.current_transform = obj.base_transform;
+            obj.current_opacity = obj.base_opacity;
+            obj.current_geometry = None;
+        }
+        self.animations.retain(|an| {
+            let t = self
+                .objects
+                .get_mut(&an.id)
+                .map(|obj| an.apply(self.time, obj));
+            t == Some(false)
+        });
+        self.current_camera = self.camera;
+        if let Some(an) = &self.camera_animation {
+            let elapsed = self.time - an.start_time;
+            if elapsed >= an.duration {
+                self.camera = an.to;
+                self.current_camera = an.to;
+                self.camera_animation = None;
+            } else if elapsed >= 0. {
+                let p = map_animate_progress(an.animate_function, elapsed / an.duration);
+                self.current_camera = an.from.lerp(an.to, p);
+            }
+        }
+    }
+    fn reset(&mut self) {
+        self.highlighted_log = 0..0;
+        self.objects.clear();
+        self.next_event_idx = 0;
+        self.time = 0.;
+        self.animations.clear();
+        self.camera = Camera::new(self.replay.aspect);
+        self.current_camera = self.camera;
+        self.camera_animation = None;
+    }
+    // Invalid events, e.g. creating an object that exists or changing one
+    // that does not, are skipped; visualize::validate reports them.
+    fn process_event(&mut self, idx: usize, res: &mut UpdateResult) {
+        let event = &self.replay.events[idx];
+        match &event.event {
+            Event::Create {
+                id,
+                geometry,
+                position,
+                z_index,
+                parent,
+            } => {
+                if self.objects.contains_key(id) {
+                    return;
+                }
+                // The new object has no descendants yet, so only an existing
+                // other object can be its parent.
+                let parent = parent.filter(|p| p != id && self.objects.contains_key(p));
+                if let Some(p) = parent.and_then(|p| self.objects.get_mut(&p)) {
+                    p.children.push(*id);
+                }
+                self.objects.insert(
+                    *id,
+                    Object {
+                        base_position: *position,
+                        base_transform: Affine::IDENTITY,
+                        current_transform: Affine::IDENTITY,
+                        current_position: *position,
+                        parent,
+                        children: vec![],
+                        world_transform: Affine::IDENTITY,
+                        geometry: geometry.clone(),
+                        current_geometry: None,
+                        base_opacity: 1.0,
+                        current_opacity: 1.0,
+                        z_index: *z_index,
+                    },
+                );
+                res.created.push(*id);
+            }
+            Event::Destroy { id } => {
+                let id = *id;
+                if let Some(p) = self
+                    .objects
+                    .get(&id)
+                    .and_then(|o| o.parent)
+                    .and_then(|p| self.objects.get_mut(&p))
+                {
+                    p.children.retain(|c| *c != id);
+                }
+                self.destroy(id, res);
+            }
+            &Event::Transform {
+                id,
+                transform,
+                duration,
+                animate_function,
+            } => {
+                self.animations.push(OngoingAnimation {
+                    id,
+                    start_time: event.start_time,
+                    duration,
+                    animate_function,
+                    animation: Animation::Transform(transform),
+                });
+            }
+            Event::Update {
+                id,
+                duration,
+                animate_function,
+                update,
+            } => {
+                let Some(obj) = self.objects.get_mut(id) else {
+                    return;
+                };
+                let to = update.apply(&obj.geometry);
+                let to_opacity = update.opacity.unwrap_or(obj.base_opacity).clamp(0., 1.);
+                if *duration > 0. {
+                    self.animations.push(OngoingAnimation {
+                        id: *id,
+                        start_time: event.start_time,
+                        duration: *duration,
+                        animate_function: *animate_function,
+                        animation: Animation::Style {
+                            from: obj.geometry.clone(),
+                            to,
+                            from_opacity: obj.base_opacity,
+                            to_opacity,
+                        },
+                    });
+                } else {
+                    obj.geometry = to;
+                    obj.base_opacity = to_opacity;
+                    obj.current_opacity = to_opacity;
+                    res.restyled.push(*id);
+                }
+            }
+            Event::Board { .. } => { /* handled by Replay::new */ }
+            &Event::Camera {
+                center,
+                zoom,
+                duration,
+                animate_function,
+            } => {
+                if !(zoom.is_finite() && zoom > 0.) {
+                    return;
+                }
+                let to = Camera { center, zoom };
+                // A new camera movement starts from wherever the camera is now.
+                let from = self.current_camera;
+                if let Some(an) = self.camera_animation.take() {
+                    self.camera = an.to;
+                }
+                if duration > 0. {
+                    self.camera_animation = Some(CameraAnimation {
+                        start_time: event.start_time,
+                        duration,
+                        animate_function,
+                        from,
+                        to,
+                    });
+                } else {
+                    self.camera = to;
+                }
+            }
+            Event::Log { .. } => {
+                self.highlighted_log.end += 1;
+            }
+            Event::TickMarker { .. } | Event::LegacyTickMarker => { /* used for navigation only */ }
+        }
+    }
+    // Destroys the object together with all its descendants.
+    fn destroy(&mut self, id: u64, res: &mut UpdateResult) {
+        let mut stack = vec![id];
+        while let Some(id) = stack.pop() {
+            let Some(o) = self.objects.remove(&id) else {
+                continue;
+            };
+            res.deleted.push(id);
+            stack.extend(o.children);
+        }
+    }
+}
+
+// Renders the replay as it is at `time`, e.g. at replay.duration for the
+// final position.
+pub fn render_svg(replay: Replay, time: f32, assets_url: &str) -> String {
+    let mut state = ReplayState::new(replay);
+    state.update(time);
+    state.to_svg(assets_url)
+}
+
+// A target-independent SVG element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgNode {
+    pub tag: &'static str,
+    pub attributes: Vec<(&'static str, String)>,
+    pub text: Option<String>,
+    pub children: Vec<SvgNode>,
+}
+
+impl SvgNode {
+    pub fn new(tag: &'static str) -> Self {
+        Self {
+            tag,
+            attributes: vec![],
+            text: None,
+            children: vec![],
+        }
+    }
+    pub fn attr(mut self, name: &'static str, value: impl std::fmt::Display) -> Self {
+        self.attributes.push((name, value.to_string()));
+        self
+    }
+    pub fn child(mut self, child: SvgNode) -> Self {
+        self.children.push(child);
+        self
+    }
+}
+
+impl std::fmt::Display for SvgNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}", self.tag)?;
+        for (name, value) in self.attributes.iter() {
+            write!(f, " {name}=\"{}\"", escape_xml(value))?;
+        }
+        if self.text.is_none() && self.children.is_empty() {
+            return write!(f, "/>");
+        }
+        write!(f, ">")?;
+        if let Some(text) = &self.text {
+            write!(f, "{}", escape_xml(text))?;
+        }
+        for c in self.children.iter() {
+            write!(f, "{c}")?;
+        }
+        write!(f, "</{}>", self.tag)
+    }
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut res = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
+// A group with all the geoms of the object, placed by its world transform.
+pub fn object_node(obj: &Object, assets_url: &str) -> SvgNode {
+    let mut group = SvgNode::new("g").attr("transform", obj.world_transform);
+    if obj.current_opacity < 1. {
+        group = group.attr("opacity", obj.current_opacity);
+    }
+    group.children = obj
+        .current_geometry()
+        .iter()
+        .filter_map(|g| geom_node(g, assets_url))
+        .collect();
+    group
+}
+
+// None for images with invalid names.
+pub fn geom_node(g: &Geom, assets_url: &str) -> Option<SvgNode> {
+    let styled = |node: SvgNode, fill: Color, stroke: Color, thickness: f32| {
+        node.attr("fill", html_color(fill))
+            .attr("stroke", html_color(stroke))
+            .attr("stroke-width", thickness)
+    };
+    Some(match g {
+        Geom::Circle {
+            center,
+            radius,
+            fill_color,
+            stroke_color,
+            thickness,
+        } => styled(
+            SvgNode::new("circle")
+                .attr("r", radius)
+                .attr("cx", center.0)
+                .attr("cy", center.1),
+            *fill_color,
+            *stroke_color,
+            *thickness,
+        ),
+        Geom::Line {
+            from,
+            to,
+            thickness,
+            color,
+        } => SvgNode::new("line")
+            .attr("x1", from.0)
+            .attr("y1", from.1)
+            .attr("x2", to.0)
+            .attr("y2", to.1)
+            .attr("stroke", html_color(*color))
+            .attr("stroke-width", thickness),
+        Geom::Text {
+            text,
+            position,
+            size,
+            color,
+        } => {
+            // Scaling a group as tiny font sizes don't work correctly.
+            let mut elt = SvgNode::new("text")
+                .attr("font-size", 12)
+                .attr("stroke", html_color(*color));
+            elt.text = Some(text.clone());
+            SvgNode::new("g")
+                .attr(
+                    "transform",
+                    format!(
+                        "translate({} {}) scale({} {})",
+                        position.0,
+                        position.1,
+                        size / 12.0,
+                        size / 12.0
+                    ),
+                )
+                .child(elt)
+        }
+        Geom::Polygon {
+            vs,
+            fill_color,
+            stroke_color,
+            thickness,
+        } => {
+            let mut points = String::new();
+            for (x, y) in vs {
+                write!(&mut points, "{x},{y} ").unwrap();
+            }
+            styled(
+                SvgNode::new("polygon").attr("points", points),
+                *fill_color,
+                *stroke_color,
+                *thickness,
+            )
+        }
+        Geom::Rect {
+            position,
+            size,
+            corner_radius,
+            fill_color,
+            stroke_color,
+            thickness,
+        } => styled(
+            SvgNode::new("rect")
+                .attr("x", position.0)
+                .attr("y", position.1)
+                .attr("width", size.0)
+                .attr("height", size.1)
+                .attr("rx", corner_radius),
+            *fill_color,
+            *stroke_color,
+            *thickness,
+        ),
+        Geom::Arc {
+            center: (cx, cy),
+            radius: r,
+            from,
+            to,
+            thickness,
+            color,
+        } => {
+            let point = |a: f32| (cx + r * a.cos(), cy + r * a.sin());
+            let sweep = if to >= from { 1 } else { 0 };
+            let (x0, y0) = point(*from);
+            let mut d = format!("M {x0} {y0}");
+            // SVG can't draw a full circle with a single arc command.
+            let span = (to - from).abs().min(2. * std::f32::consts::PI);
+            let mid = from + (to - from).signum() * span / 2.;
+            for a in [mid, from + (to - from).signum() * span] {
+                let (x, y) = point(a);
+                write!(&mut d, " A {r} {r} 0 0 {sweep} {x} {y}").unwrap();
+            }
+            styled(
+                SvgNode::new("path").attr("d", d),
+                Color { a: 0., ..*color },
+                *color,
+                *thickness,
+            )
+        }
+        Geom::Path {
+            segments,
+            closed,
+            fill_color,
+            stroke_color,
+            thickness,
+        } => {
+            let mut d = String::new();
+            for s in segments {
+                match s {
+                    PathSegment::MoveTo((x, y)) => write!(&mut d, "M {x} {y} "),
+                    PathSegment::LineTo((x, y)) => write!(&mut d, "L {x} {y} "),
+                    PathSegment::QuadTo((cx, cy), (x, y)) => {
+                        write!(&mut d, "Q {cx} {cy} {x} {y} ")
+                    }
+                    PathSegment::CubicTo((c1x, c1y), (c2x, c2y), (x, y)) => {
+                        write!(&mut d, "C {c1x} {c1y} {c2x} {c2y} {x} {y} ")
+                    }
+                }
+                .unwrap();
+            }
+            if *closed {
+                d.push('Z');
+            }
+            styled(
+                SvgNode::new("path").attr("d", d),
+                *fill_color,
+                *stroke_color,
+                *thickness,
+            )
+        }
+        Geom::Image {
+            name,
+            position,
+            size,
+        } => {
+            if validate_asset_name(name).is_err() {
+                return None;
+            }
+            SvgNode::new("image")
+                .attr("href", format!("{assets_url}{name}"))
+                .attr("x", position.0)
+                .attr("y", position.1)
+                .attr("width", size.0)
+                .attr("height", size.1)
+                .attr("preserveAspectRatio", "none")
+        }
+    })
+}
+
+pub fn html_color(c: Color) -> String {
+    format!("rgba({},{},{},{})", c.r * 255., c.g * 255., c.b * 255., c.a)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::visualize::{decode_event, Replay};
+
+    #[test]
+    fn affine_composition() {
+        let t = Affine::translation((1., 2.)) * Affine::rotation(std::f32::consts::FRAC_PI_2);
+        let (x, y) = t.apply((1., 0.));
+        assert!((x - 1.).abs() < 1e-6 && (y - 3.).abs() < 1e-6, "{x} {y}");
+        assert_eq!(Affine::scale(2.) * Affine::IDENTITY, Affine::scale(2.));
+    }
+
+    #[test]
+    fn renders_final_frame() {
+        let events = [
+            r#"{"t":0,"board":{"aspect":2}}"#,
+            r#"{"t":0,"create":{"id":1,"z":2,"p":[0.5,0.5],"geom":[{"circle":{"r":0.1,"f":"ff0000ff"}}]}}"#,
+            r#"{"t":0,"create":{"id":2,"z":1,"parent":1,"geom":[{"text":{"p":[0,0],"t":0.1,"v":"a<b"}}]}}"#,
+            r#"{"t":0,"create":{"id":3,"geom":[{"image":{"src":"../x.png","wh":[1,1]}}]}}"#,
+            r#"{"t":1,"transform":{"id":1,"d":1,"mv":[0.5,0]}}"#,
+            r#"{"t":1.5,"update":{"id":1,"d":0.5,"o":0.5}}"#,
+        ]
+        .iter()
+        .map(|l| decode_event(l).unwrap())
+        .collect();
+        let replay = Replay::new(events);
+        let svg = render_svg(replay.clone(), replay.duration, "/files/");
+        assert_eq!(
+            svg,
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 2 1">"#,
+                r#"<g transform="matrix(1 0 0 1 1 0.5)">"#,
+                r#"<g transform="translate(0 0) scale(0.008333334 0.008333334)">"#,
+                r#"<text font-size="12" stroke="rgba(0,0,0,1)">a&lt;b</text></g></g>"#,
+                r#"<g transform="matrix(1 0 0 1 0 0)"/>"#,
+                r#"<g transform="matrix(1 0 0 1 1 0.5)" opacity="0.5">"#,
+                r#"<circle r="0.1" cx="0" cy="0" fill="rgba(255,0,0,1)" stroke="rgba(0,0,0,1)" stroke-width="0.005"/></g>"#,
+                "</svg>"
+            )
+        );
+        let mut state = ReplayState::new(replay);
+        let res = state.update(1.5);
+        assert_eq!(res.created.len(), 3);
+        let (x, _) = state.object(2).unwrap().world_transform.apply((0., 0.));
+        assert!((x - 0.75).abs() < 1e-6, "{x}");
+    }
+
+    #[test]
+    fn update_restyles() {
+        let events = [
+            r#"{"t":0,"create":{"id":1,"geom":[{"circle":{"f":"000000ff"}},{"text":{"p":[0,0],"v":"1"}}]}}"#,
+            r#"{"t":1,"update":{"id":1,"v":"2","o":0.4}}"#,
+            r#"{"t":2,"update":{"id":1,"d":1,"fill":"ff0000ff","o":0.8,"f":"linear"}}"#,
+            r#"{"t":2,"update":{"id":7,"v":"3"}}"#,
+        ]
+        .iter()
+        .map(|l| decode_event(l).unwrap())
+        .collect();
+        let mut state = ReplayState::new(Replay::new(events));
+        state.update(0.);
+
+        let res = state.update(1.);
+        assert_eq!(res.restyled, vec![1]);
+        let obj = state.object(1).unwrap();
+        assert_eq!(obj.current_opacity, 0.4);
+        assert!(matches!(&obj.current_geometry()[1], Geom::Text { text, .. } if text == "2"));
+
+        state.update(2.5);
+        let obj = state.object(1).unwrap();
+        assert!(
+            (obj.current_opacity - 0.6).abs() < 1e-6,
+            "{}",
+            obj.current_opacity
+        );
+        let fill = obj.current_geometry()[0].fill_color().unwrap();
+        assert!((fill.r - 0.5).abs() < 1e-6, "{fill:?}");
+
+        state.update(3.);
+        let obj = state.object(1).unwrap();
+        assert_eq!(obj.current_opacity, 0.8);
+        assert_eq!(obj.current_geometry()[0].fill_color().unwrap().r, 1.);
+        assert!(matches!(&obj.current_geometry()[1], Geom::Text { text, .. } if text == "2"));
+        assert!(state.object(7).is_none());
+    }
+
+    #[test]
+    fn parent_transforms() {
+        let events = [
+            r#"{"t":0,"create":{"id":1,"p":[0.5,0.5],"geom":[]}}"#,
+            r#"{"t":0,"create":{"id":2,"parent":1,"p":[0.1,0],"geom":[]}}"#,
+            r#"{"t":0,"create":{"id":3,"parent":2,"p":[0.1,0],"geom":[]}}"#,
+            r#"{"t":1,"transform":{"id":1,"d":0,"rot":1.5707964}}"#,
+            r#"{"t":1,"transform":{"id":1,"d":0,"scale":2}}"#,
+            r#"{"t":2,"transform":{"id":1,"d":1,"mv":[0.1,0],"f":"linear"}}"#,
+            // Would make a cycle: 1 is already created, so this is skipped.
+            r#"{"t":2,"create":{"id":1,"parent":3,"geom":[]}}"#,
+            r#"{"t":4,"destroy":{"id":2}}"#,
+        ]
+        .iter()
+        .map(|l| decode_event(l).unwrap())
+        .collect();
+        let mut state = ReplayState::new(Replay::new(events));
+        let origin = |state: &ReplayState, id| {
+            let (x, y) = state.object(id).unwrap().world_transform.apply((0., 0.));
+            ((x * 1000.).round() / 1000., (y * 1000.).round() / 1000.)
+        };
+        state.update(0.);
+        assert_eq!(origin(&state, 2), (0.6, 0.5));
+        assert_eq!(origin(&state, 3), (0.7, 0.5));
+
+        // Rotated a quarter turn and scaled around the parent's position.
+        state.update(1.);
+        assert_eq!(origin(&state, 1), (0.5, 0.5));
+        assert_eq!(origin(&state, 2), (0.5, 0.7));
+        assert_eq!(origin(&state, 3), (0.5, 0.9));
+
+        state.update(2.5);
+        assert_eq!(origin(&state, 3), (0.55, 0.9));
+        state.update(3.);
+        assert_eq!(origin(&state, 1), (0.6, 0.5));
+        assert_eq!(origin(&state, 3), (0.6, 0.9));
+        assert_eq!(state.object(1).unwrap().parent, None);
+
+        let res = state.update(4.);
+        assert_eq!(res.deleted.len(), 2);
+        assert!(state.object(3).is_none());
+        assert_eq!(origin(&state, 1), (0.6, 0.5));
+    }
+
+    #[test]
+    fn duplicate_create_is_skipped() {
+        let events = [
+            r#"{"t":0,"create":{"id":1,"p":[0.5,0.5],"geom":[]}}"#,
+            r#"{"t":0,"create":{"id":1,"parent":1,"geom":[]}}"#,
+            r#"{"t":0,"create":{"id":2,"parent":2,"geom":[]}}"#,
+        ]
+        .iter()
+        .map(|l| decode_event(l).unwrap())
+        .collect();
+        let replay = Replay::new(events);
+        let mut state = ReplayState::new(replay.clone());
+        let res = state.update(0.);
+        assert_eq!(res.created, vec![1, 2]);
+        assert_eq!(state.object(1).unwrap().parent, None);
+        assert_eq!(state.object(2).unwrap().parent, None);
+        let (x, y) = state.object(1).unwrap().world_transform.apply((0., 0.));
+        assert_eq!((x, y), (0.5, 0.5));
+        render_svg(replay, 0., "");
+    }
+
+    #[test]
+    fn deep_hierarchy() {
+        let mut events = (1..=100_000u64)
+            .map(|id| {
+                decode_event(&format!(
+                    r#"{{"t":0,"create":{{"id":{id},"parent":{},"p":[0.001,0],"geom":[]}}}}"#,
+                    id - 1
+                ))
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+        events.push(decode_event(r#"{"t":1,"destroy":{"id":1}}"#).unwrap());
+        let mut state = ReplayState::new(Replay::new(events));
+        state.update(0.);
+        let (x, _) = state
+            .object(100_000)
+            .unwrap()
+            .world_transform
+            .apply((0., 0.));
+        assert!((x - 100.).abs() < 0.1, "{x}");
+        assert_eq!(state.update(1.).deleted.len(), 100_000);
+    }
+}
@@ -1,3 +1,7 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use std::time::Duration;
+
 pub fn split<const N: usize>(s: &str) -> [&str; N] {
     let mut res = [""; N];
     for (i, piece) in s.splitn(N, ' ').enumerate() {
@@ -5,3 +9,365 @@ pub fn split<const N: usize>(s: &str) -> [&str; N] {
     }
     res
 }
+
+// The latest protocol version the controller speaks.
+// Version 2 adds "canceltimer".
+pub const PROTOCOL_VERSION: u32 = 2;
+// The version assumed for game servers that do not negotiate one.
+pub const LEGACY_PROTOCOL_VERSION: u32 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub enum VisMode {
+    // No visualization was requested.
+    #[default]
+    None,
+    // Visualization events are expected interleaved with the game commands.
+    Inline,
+    // The game server is run as a visualizer over an existing match log.
+    Standalone,
+}
+
+// Messages sent by the controller to the game server.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControllerToGame {
+    // The latest protocol version the controller supports.
+    Proto(u32),
+    Vis(VisMode),
+    Param(String),
+    Start,
+    Recv { player: usize, msg: String },
+    Timeout(u32),
+    Dropped(usize),
+}
+
+// Messages sent by the game server to the controller.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameToController {
+    // The protocol version the game server wants to use.
+    Proto(u32),
+    Timer { id: u32, duration: Duration },
+    // Since protocol version 2.
+    CancelTimer(u32),
+    // One score per player, the rest of the line is the reason.
+    Over { scores: Vec<f64>, reason: String },
+    SendAll(String),
+    PlayerError { player: usize, msg: String },
+    Send { player: usize, msg: String },
+    // Visualization event, not interpreted by the controller.
+    Vis(String),
+}
+
+// Messages sent by a bot to the controller. Everything a bot sends after
+// "ready" is forwarded to the game server as is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BotToController {
+    Ready,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    UnknownCommand(String),
+    InvalidArgument { command: String, argument: String },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_arg<T: FromStr>(command: &str, argument: &str) -> Result<T, ParseError> {
+    argument.parse().map_err(|_| ParseError::InvalidArgument {
+        command: command.to_owned(),
+        argument: argument.to_owned(),
+    })
+}
+
+impl Display for VisMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            VisMode::None => "none",
+            VisMode::Inline => "inline",
+            VisMode::Standalone => "standalone",
+        })
+    }
+}
+
+impl FromStr for VisMode {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(VisMode::None),
+            "inline" => Ok(VisMode::Inline),
+            "standalone" => Ok(VisMode::Standalone),
+            _ => Err(ParseError::InvalidArgument {
+                command: "vis".to_owned(),
+                argument: s.to_owned(),
+            }),
+        }
+    }
+}
+
+impl Display for ControllerToGame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ControllerToGame::Proto(version) => write!(f, "proto {version}"),
+            ControllerToGame::Vis(mode) => write!(f, "vis {mode}"),
+            ControllerToGame::Param(param) => write!(f, "param {param}"),
+            ControllerToGame::Start => write!(f, "start"),
+            ControllerToGame::Recv { player, msg } => write!(f, "recv {player} {msg}"),
+            ControllerToGame::Timeout(id) => write!(f, "timeout {id}"),
+            ControllerToGame::Dropped(player) => write!(f, "dropped {player}"),
+        }
+    }
+}
+
+impl FromStr for ControllerToGame {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let [cmd, rest] = split(s);
+        Ok(match cmd {
+            "proto" => ControllerToGame::Proto(parse_arg(cmd, rest)?),
+            "vis" => ControllerToGame::Vis(rest.parse()?),
+            "param" => ControllerToGame::Param(rest.to_owned()),
+            "start" => ControllerToGame::Start,
+            "recv" => {
+                let [player, msg] = split(rest);
+                ControllerToGame::Recv {
+                    player: parse_arg(cmd, player)?,
+                    msg: msg.to_owned(),
+                }
+            }
+            "timeout" => ControllerToGame::Timeout(parse_arg(cmd, rest)?),
+            "dropped" => ControllerToGame::Dropped(parse_arg(cmd, rest)?),
+            _ => return Err(ParseError::UnknownCommand(cmd.to_owned())),
+        })
+    }
+}
+
+impl Display for GameToController {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameToController::Proto(version) => write!(f, "proto {version}"),
+            GameToController::Timer { id, duration } => {
+                write!(f, "timer {id} {}ms", duration.as_millis())
+            }
+            GameToController::CancelTimer(id) => write!(f, "canceltimer {id}"),
+            GameToController::Over { scores, reason } => {
+                write!(f, "over")?;
+                for s in scores {
+                    write!(f, " {s}")?;
+                }
+                write!(f, " {reason}")
+            }
+            GameToController::SendAll(msg) => write!(f, "sendall {msg}"),
+            GameToController::PlayerError { player, msg } => {
+                write!(f, "playererror {player} {msg}")
+            }
+            GameToController::Send { player, msg } => write!(f, "send {player} {msg}"),
+            GameToController::Vis(event) => write!(f, "vis {event}"),
+        }
+    }
+}
+
+impl GameToController {
+    // "over" has a score for each player, so their number is needed to tell
+    // the scores from the reason.
+    pub fn parse(s: &str, num_players: usize) -> Result<Self, ParseError> {
+        let [cmd, rest] = split(s);
+        Ok(match cmd {
+            "proto" => GameToController::Proto(parse_arg(cmd, rest)?),
+            "canceltimer" => GameToController::CancelTimer(parse_arg(cmd, rest)?),
+            "timer" => {
+                let [id, duration] = split(rest);
+                let millis =
+                    duration
+                        .strip_suffix("ms")
+                        .ok_or_else(|| ParseError::InvalidArgument {
+                            command: cmd.to_owned(),
+                            argument: duration.to_owned(),
+                        })?;
+                GameToController::Timer {
+                    id: parse_arg(cmd, id)?,
+                    duration: Duration::from_millis(parse_arg(cmd, millis)?),
+                }
+            }
+            "over" => {
+                let mut it = rest.splitn(num_players + 1, ' ');
+                let scores = (0..num_players)
+                    .map(|_| parse_arg(cmd, it.next().unwrap_or_default()))
+                    .collect::<Result<Vec<f64>, _>>()?;
+                GameToController::Over {
+                    scores,
+                    reason: it.next().unwrap_or_default().to_owned(),
+                }
+            }
+            "sendall" => GameToController::SendAll(rest.to_owned()),
+            "playererror" => {
+                let [player, msg] = split(rest);
+                GameToController::PlayerError {
+                    player: parse_arg(cmd, player)?,
+                    msg: msg.to_owned(),
+                }
+            }
+            "send" => {
+                let [player, msg] = split(rest);
+                GameToController::Send {
+                    player: parse_arg(cmd, player)?,
+                    msg: msg.to_owned(),
+                }
+            }
+            "vis" => GameToController::Vis(rest.to_owned()),
+            _ => return Err(ParseError::UnknownCommand(cmd.to_owned())),
+        })
+    }
+}
+
+impl Display for BotToController {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BotToController::Ready => write!(f, "ready"),
+        }
+    }
+}
+
+impl FromStr for BotToController {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ready" => Ok(BotToController::Ready),
+            _ => Err(ParseError::UnknownCommand(s.to_owned())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn round_trip<T>(msgs: &[T])
+    where
+        T: FromStr<Err = ParseError> + Display + PartialEq + std::fmt::Debug,
+    {
+        for m in msgs {
+            let s = m.to_string();
+            assert_eq!(s.parse::<T>().as_ref(), Ok(m), "round trip of '{s}'");
+        }
+    }
+
+    #[test]
+    fn controller_to_game_round_trip() {
+        round_trip(&[
+            ControllerToGame::Proto(PROTOCOL_VERSION),
+            ControllerToGame::Vis(VisMode::None),
+            ControllerToGame::Vis(VisMode::Inline),
+            ControllerToGame::Vis(VisMode::Standalone),
+            ControllerToGame::Param("".to_owned()),
+            ControllerToGame::Param("3 10 500".to_owned()),
+            ControllerToGame::Start,
+            ControllerToGame::Recv {
+                player: 2,
+                msg: "move 1 2 3 4".to_owned(),
+            },
+            ControllerToGame::Recv {
+                player: 1,
+                msg: "".to_owned(),
+            },
+            ControllerToGame::Timeout(17),
+            ControllerToGame::Dropped(3),
+        ]);
+    }
+
+    fn game_round_trip(num_players: usize, msgs: &[GameToController]) {
+        for m in msgs {
+            let s = m.to_string();
+            assert_eq!(
+                GameToController::parse(&s, num_players).as_ref(),
+                Ok(m),
+                "round trip of '{s}'"
+            );
+        }
+    }
+
+    #[test]
+    fn game_to_controller_round_trip() {
+        game_round_trip(
+            2,
+            &[
+                GameToController::Proto(1),
+                GameToController::Timer {
+                    id: 1,
+                    duration: Duration::from_millis(100),
+                },
+                GameToController::CancelTimer(1),
+                GameToController::Over {
+                    scores: vec![2.0, 0.0],
+                    reason: "all pieces home".to_owned(),
+                },
+                GameToController::SendAll("yourmove".to_owned()),
+                GameToController::PlayerError {
+                    player: 2,
+                    msg: "invalid move".to_owned(),
+                },
+                GameToController::Send {
+                    player: 1,
+                    msg: "start 1".to_owned(),
+                },
+                GameToController::Vis(r#"{"t":0,"destroy":{"id":1}}"#.to_owned()),
+            ],
+        );
+        game_round_trip(
+            3,
+            &[GameToController::Over {
+                scores: vec![0.5, 1.25, -3.0],
+                reason: "".to_owned(),
+            }],
+        );
+    }
+
+    #[test]
+    fn over_reason_is_verbatim() {
+        game_round_trip(
+            1,
+            &[
+                GameToController::Over {
+                    scores: vec![1.0],
+                    reason: "2 apples".to_owned(),
+                },
+                GameToController::Over {
+                    scores: vec![f64::INFINITY],
+                    reason: "007 1e3".to_owned(),
+                },
+            ],
+        );
+        assert_eq!(
+            GameToController::parse("over 1 2 007  1e3", 2),
+            Ok(GameToController::Over {
+                scores: vec![1.0, 2.0],
+                reason: "007  1e3".to_owned(),
+            })
+        );
+        assert!(GameToController::parse("over 1", 2).is_err());
+        assert!(GameToController::parse("over 1 x", 2).is_err());
+    }
+
+    #[test]
+    fn bot_to_controller_round_trip() {
+        round_trip(&[BotToController::Ready]);
+        assert!("ready ".parse::<BotToController>().is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            GameToController::parse("", 2),
+            Err(ParseError::UnknownCommand("".to_owned()))
+        );
+        assert!(GameToController::parse("timer 1 100", 2).is_err());
+        assert!(GameToController::parse("send x hello", 2).is_err());
+        assert!("recv".parse::<ControllerToGame>().is_err());
+        assert!("vis sideways".parse::<ControllerToGame>().is_err());
+    }
+}
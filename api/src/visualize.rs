@@ -1,5 +1,6 @@
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color {
@@ -9,19 +10,46 @@ pub struct Color {
     pub a: f32,
 }
 
-impl Serialize for Color {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
+impl std::fmt::Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let byte = |c: f32| (c.clamp(0., 1.) * 255.).round() as u8;
-        serializer.serialize_str(&format!(
+        write!(
+            f,
             "{:02x}{:02x}{:02x}{:02x}",
             byte(self.r),
             byte(self.g),
             byte(self.b),
             byte(self.a)
-        ))
+        )
+    }
+}
+
+impl Color {
+    // Whether all the components are in [0..1].
+    pub fn is_valid(&self) -> bool {
+        [self.r, self.g, self.b, self.a]
+            .iter()
+            .all(|c| (0.0..=1.0).contains(c))
+    }
+
+    // Linear interpolation, p=0 is self, p=1 is `to`.
+    pub fn lerp(self, to: Color, p: f32) -> Color {
+        let f = |a: f32, b: f32| a + (b - a) * p;
+        Color {
+            r: f(self.r, to.r),
+            g: f(self.g, to.g),
+            b: f(self.b, to.b),
+            a: f(self.a, to.a),
+        }
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
@@ -127,6 +155,79 @@ pub mod geom {
             #[serde(rename = "t")]
             thickness: f32,
         },
+        // Axis-aligned rectangle with the top left corner at `position`.
+        #[serde(rename = "rect")]
+        Rect {
+            #[serde(default = "super::zero")]
+            #[serde(rename = "p")]
+            position: (f32, f32),
+            #[serde(rename = "wh")]
+            size: (f32, f32),
+            #[serde(default)]
+            #[serde(rename = "cr")]
+            corner_radius: f32,
+            #[serde(default = "default_fill_color")]
+            #[serde(rename = "f")]
+            fill_color: Color,
+            #[serde(default = "default_stroke_color")]
+            #[serde(rename = "s")]
+            stroke_color: Color,
+            #[serde(default = "default_thickness")]
+            #[serde(rename = "t")]
+            thickness: f32,
+        },
+        // Circular arc from angle `from` to angle `to`, in radians, clockwise
+        // on the screen (the y axis points down).
+        #[serde(rename = "arc")]
+        Arc {
+            #[serde(default = "super::zero")]
+            #[serde(rename = "p")]
+            center: (f32, f32),
+            #[serde(default = "default_radius")]
+            #[serde(rename = "r")]
+            radius: f32,
+            #[serde(rename = "a1")]
+            from: f32,
+            #[serde(rename = "a2")]
+            to: f32,
+            #[serde(default = "default_thickness")]
+            #[serde(rename = "t")]
+            thickness: f32,
+            #[serde(default = "default_stroke_color")]
+            #[serde(rename = "s")]
+            color: Color,
+        },
+        // Similar to SVG path. Must start with a MoveTo.
+        #[serde(rename = "path")]
+        Path {
+            #[serde(rename = "d")]
+            segments: Vec<PathSegment>,
+            // Connect the last point to the first one.
+            #[serde(default)]
+            #[serde(rename = "z")]
+            closed: bool,
+            #[serde(default = "default_fill_color")]
+            #[serde(rename = "f")]
+            fill_color: Color,
+            #[serde(default = "default_stroke_color")]
+            #[serde(rename = "s")]
+            stroke_color: Color,
+            #[serde(default = "default_thickness")]
+            #[serde(rename = "t")]
+            thickness: f32,
+        },
+        // An image stored in the game files (see FileStore), referenced by
+        // the file name, e.g. "knight.png".
+        #[serde(rename = "image")]
+        Image {
+            #[serde(rename = "src")]
+            name: String,
+            #[serde(default = "super::zero")]
+            #[serde(rename = "p")]
+            position: (f32, f32),
+            #[serde(rename = "wh")]
+            size: (f32, f32),
+        },
         #[serde(rename = "text")]
         Text {
             #[serde(rename = "v")]
@@ -141,6 +242,229 @@ pub mod geom {
             color: Color,
         },
     }
+
+    #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
+    pub enum PathSegment {
+        #[serde(rename = "m")]
+        MoveTo((f32, f32)),
+        #[serde(rename = "l")]
+        LineTo((f32, f32)),
+        // Control point, end point.
+        #[serde(rename = "q")]
+        QuadTo((f32, f32), (f32, f32)),
+        // Two control points, end point.
+        #[serde(rename = "c")]
+        CubicTo((f32, f32), (f32, f32), (f32, f32)),
+    }
+
+    impl Geom {
+        // Constructors for the geoms with all the optional fields set to defaults.
+        pub fn line(from: (f32, f32), to: (f32, f32)) -> Self {
+            Geom::Line {
+                from,
+                to,
+                thickness: default_thickness(),
+                color: default_stroke_color(),
+            }
+        }
+
+        pub fn circle(center: (f32, f32), radius: f32) -> Self {
+            Geom::Circle {
+                center,
+                radius,
+                fill_color: default_fill_color(),
+                stroke_color: default_stroke_color(),
+                thickness: default_thickness(),
+            }
+        }
+
+        pub fn polygon(vs: Vec<(f32, f32)>) -> Self {
+            Geom::Polygon {
+                vs,
+                fill_color: default_fill_color(),
+                stroke_color: default_stroke_color(),
+                thickness: default_thickness(),
+            }
+        }
+
+        pub fn rect(position: (f32, f32), size: (f32, f32)) -> Self {
+            Geom::Rect {
+                position,
+                size,
+                corner_radius: 0.0,
+                fill_color: default_fill_color(),
+                stroke_color: default_stroke_color(),
+                thickness: default_thickness(),
+            }
+        }
+
+        pub fn rounded_rect(position: (f32, f32), size: (f32, f32), corner_radius: f32) -> Self {
+            let mut r = Self::rect(position, size);
+            if let Geom::Rect {
+                corner_radius: cr, ..
+            } = &mut r
+            {
+                *cr = corner_radius;
+            }
+            r
+        }
+
+        pub fn arc(center: (f32, f32), radius: f32, from: f32, to: f32) -> Self {
+            Geom::Arc {
+                center,
+                radius,
+                from,
+                to,
+                thickness: default_thickness(),
+                color: default_stroke_color(),
+            }
+        }
+
+        pub fn path(segments: Vec<PathSegment>, closed: bool) -> Self {
+            Geom::Path {
+                segments,
+                closed,
+                fill_color: default_fill_color(),
+                stroke_color: default_stroke_color(),
+                thickness: default_thickness(),
+            }
+        }
+
+        pub fn image(name: impl Into<String>, position: (f32, f32), size: (f32, f32)) -> Self {
+            Geom::Image {
+                name: name.into(),
+                position,
+                size,
+            }
+        }
+
+        pub fn text(position: (f32, f32), size: f32, text: impl Into<String>) -> Self {
+            Geom::Text {
+                text: text.into(),
+                position,
+                size,
+                color: default_stroke_color(),
+            }
+        }
+
+        pub fn fill_color(&self) -> Option<Color> {
+            match self {
+                Geom::Circle { fill_color, .. }
+                | Geom::Polygon { fill_color, .. }
+                | Geom::Rect { fill_color, .. }
+                | Geom::Path { fill_color, .. } => Some(*fill_color),
+                Geom::Line { .. } | Geom::Arc { .. } | Geom::Text { .. } | Geom::Image { .. } => {
+                    None
+                }
+            }
+        }
+
+        pub fn stroke_color(&self) -> Option<Color> {
+            match self {
+                Geom::Line { color, .. }
+                | Geom::Arc { color, .. }
+                | Geom::Text { color, .. }
+                | Geom::Circle {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Polygon {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Rect {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Path {
+                    stroke_color: color,
+                    ..
+                } => Some(*color),
+                Geom::Image { .. } => None,
+            }
+        }
+
+        // Ignored for geoms that are not filled.
+        pub fn fill(mut self, c: Color) -> Self {
+            match &mut self {
+                Geom::Circle { fill_color, .. }
+                | Geom::Polygon { fill_color, .. }
+                | Geom::Rect { fill_color, .. }
+                | Geom::Path { fill_color, .. } => *fill_color = c,
+                Geom::Line { .. } | Geom::Arc { .. } | Geom::Text { .. } | Geom::Image { .. } => {}
+            }
+            self
+        }
+
+        // Outline color for filled geoms, the color of lines and text.
+        pub fn stroke(mut self, c: Color) -> Self {
+            match &mut self {
+                Geom::Line { color, .. }
+                | Geom::Arc { color, .. }
+                | Geom::Text { color, .. }
+                | Geom::Circle {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Polygon {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Rect {
+                    stroke_color: color,
+                    ..
+                }
+                | Geom::Path {
+                    stroke_color: color,
+                    ..
+                } => *color = c,
+                Geom::Image { .. } => {}
+            }
+            self
+        }
+
+        // Ignored for text and images.
+        pub fn thickness(mut self, t: f32) -> Self {
+            match &mut self {
+                Geom::Line { thickness, .. }
+                | Geom::Circle { thickness, .. }
+                | Geom::Polygon { thickness, .. }
+                | Geom::Rect { thickness, .. }
+                | Geom::Arc { thickness, .. }
+                | Geom::Path { thickness, .. } => *thickness = t,
+                Geom::Text { .. } | Geom::Image { .. } => {}
+            }
+            self
+        }
+    }
+}
+
+// Game files with these names are not assets, the game settings write them.
+pub const RESERVED_ASSET_NAMES: [&str; 3] = ["icon.png", "icon.svg", "index.html"];
+
+// Names of the game files that images can refer to, e.g. "knight.png". Only
+// plain file names, so that a replay can't point outside the game files.
+pub fn validate_asset_name(name: &str) -> Result<(), String> {
+    const MAX: usize = 64;
+    if !(1..=MAX).contains(&name.len()) {
+        return Err(format!("Asset name length must be in range [1..{MAX}]"));
+    }
+    if name.starts_with('.') {
+        return Err("Asset name must not start with '.'".to_owned());
+    }
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '.' || *c == '-' || *c == '_'))
+    {
+        return Err(format!(
+            "Disallowed characters found in asset name: '{c}' code={:x}",
+            c as u32
+        ));
+    }
+    if RESERVED_ASSET_NAMES.contains(&name) {
+        return Err(format!("Asset name {name} is reserved"));
+    }
+    Ok(())
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq)]
@@ -166,11 +490,14 @@ pub enum Event {
         z_index: u8,
         #[serde(rename = "geom")]
         geometry: Vec<geom::Geom>,
+        // If set, the position and the transforms are relative to the parent
+        // object, which must already exist. Destroying the parent destroys
+        // its children too.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parent: Option<u64>,
     },
     #[serde(rename = "destroy")]
-    Destroy {
-        id: u64,
-    },
+    Destroy { id: u64 },
     #[serde(rename = "transform")]
     Transform {
         id: u64,
@@ -182,12 +509,101 @@ pub enum Event {
         #[serde(flatten)]
         transform: Transform,
     },
+    // Changes the look of an existing object. With a non-zero duration, the
+    // colors and the opacity are animated; everything else changes at once.
+    #[serde(rename = "update")]
+    Update {
+        id: u64,
+        #[serde(default)]
+        #[serde(rename = "d")]
+        duration: f32,
+        #[serde(default)]
+        #[serde(rename = "f")]
+        animate_function: AnimateFunction,
+        #[serde(flatten)]
+        update: ObjectUpdate,
+    },
+    // Board dimensions: the visible area is [0..aspect]x[0..1]. Only the
+    // first one in the replay is taken into account.
+    #[serde(rename = "board")]
+    Board { aspect: f32 },
+    // Moves the camera to look at `center`. Zoom 1 shows the whole board.
+    #[serde(rename = "camera")]
+    Camera {
+        #[serde(rename = "p")]
+        center: (f32, f32),
+        #[serde(default = "one")]
+        zoom: f32,
+        #[serde(default)]
+        #[serde(rename = "d")]
+        duration: f32,
+        #[serde(default)]
+        #[serde(rename = "f")]
+        animate_function: AnimateFunction,
+    },
     #[serde(rename = "log")]
-    Log {
-        line: String,
+    Log { line: String },
+    // Denotes moves or other points of interest, the viewer can jump between
+    // them. The label is shown on the seek bar, e.g. "Move 12".
+    #[serde(rename = "tick")]
+    TickMarker {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
     },
-    // Can be used to denote moves.
-    TickMarker,
+    // A tick without a label, as written by replays from before ticks had
+    // labels. An alias of `tick` would not do: the events are flattened into
+    // TimedEvent, and the old replays have `"TickMarker":null`.
+    #[serde(rename = "TickMarker", skip_serializing)]
+    LegacyTickMarker,
+}
+
+// The fields that are not set stay unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+pub struct ObjectUpdate {
+    // Replaces the whole geometry; applied before the other fields.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "geom")]
+    pub geometry: Option<Vec<geom::Geom>>,
+    // Set for all the geoms that have them. Not "f" as in geoms, which is the
+    // animate function of the update.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "fill")]
+    pub fill_color: Option<Color>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "s")]
+    pub stroke_color: Option<Color>,
+    // Replaces the text of all the text geoms.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "v")]
+    pub text: Option<String>,
+    // Of the whole object, in [0..1].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "o")]
+    pub opacity: Option<f32>,
+}
+
+impl ObjectUpdate {
+    // Returns the geometry with the update applied. Opacity is not a property
+    // of geoms and is left to the caller.
+    pub fn apply(&self, geometry: &[geom::Geom]) -> Vec<geom::Geom> {
+        let geometry = self.geometry.as_deref().unwrap_or(geometry);
+        geometry
+            .iter()
+            .map(|g| {
+                let mut g = g.clone();
+                if let Some(c) = self.fill_color {
+                    g = g.fill(c);
+                }
+                if let Some(c) = self.stroke_color {
+                    g = g.stroke(c);
+                }
+                if let (Some(new_text), geom::Geom::Text { text, .. }) = (&self.text, &mut g) {
+                    new_text.clone_into(text);
+                }
+                g
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Default)]
@@ -211,12 +627,120 @@ pub struct TimedEvent {
     pub start_time: f32,
     #[serde(flatten)]
     pub event: Event,
+    // Who sees the event, everyone by default.
+    #[serde(default, rename = "to", skip_serializing_if = "Visibility::is_all")]
+    pub visibility: Visibility,
+}
+
+// The set of viewers that see an event: "all", "spectator", or a list of
+// in-game player ids which may include "spectator", e.g. ["spectator",2].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Visibility {
+    #[default]
+    All,
+    Only {
+        spectator: bool,
+        players: Vec<usize>,
+    },
+}
+
+// The perspective a replay is watched from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Viewer {
+    // Sees every event, e.g. once the game author allows it.
+    Omniscient,
+    // Someone not playing in the match.
+    Spectator,
+    // A participant, controlling these in-game players.
+    Players(Vec<usize>),
+}
+
+impl Visibility {
+    pub fn is_all(&self) -> bool {
+        *self == Visibility::All
+    }
+
+    pub fn visible_to(&self, viewer: &Viewer) -> bool {
+        match (self, viewer) {
+            (Visibility::All, _) | (_, Viewer::Omniscient) => true,
+            (Visibility::Only { spectator, .. }, Viewer::Spectator) => *spectator,
+            (Visibility::Only { players, .. }, Viewer::Players(ps)) => {
+                ps.iter().any(|p| players.contains(p))
+            }
+        }
+    }
+}
+
+impl Serialize for Visibility {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeSeq;
+        match self {
+            Visibility::All => serializer.serialize_str("all"),
+            Visibility::Only {
+                spectator: true,
+                players,
+            } if players.is_empty() => serializer.serialize_str("spectator"),
+            Visibility::Only { spectator, players } => {
+                let mut seq =
+                    serializer.serialize_seq(Some(*spectator as usize + players.len()))?;
+                if *spectator {
+                    seq.serialize_element("spectator")?;
+                }
+                for p in players {
+                    seq.serialize_element(p)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Visibility {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Item {
+            Player(usize),
+            Name(String),
+        }
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Name(String),
+            Set(Vec<Item>),
+        }
+        let items = match Repr::deserialize(deserializer)? {
+            Repr::Name(name) if name == "all" => return Ok(Visibility::All),
+            Repr::Name(name) => vec![Item::Name(name)],
+            Repr::Set(items) => items,
+        };
+        let mut spectator = false;
+        let mut players = vec![];
+        for item in items {
+            match item {
+                Item::Player(p) => players.push(p),
+                Item::Name(name) if name == "spectator" => spectator = true,
+                Item::Name(name) => {
+                    return Err(D::Error::custom(format!("unknown viewer {name:?}")));
+                }
+            }
+        }
+        Ok(Visibility::Only { spectator, players })
+    }
 }
 
 impl Event {
     pub fn duration(&self) -> f32 {
         match self {
-            Event::Transform { duration, .. } => *duration,
+            Event::Transform { duration, .. }
+            | Event::Update { duration, .. }
+            | Event::Camera { duration, .. } => *duration,
             _ => 0.0,
         }
     }
@@ -228,24 +752,100 @@ impl TimedEvent {
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Camera {
+    pub center: (f32, f32),
+    pub zoom: f32,
+}
+
+impl Camera {
+    // Shows the whole board.
+    pub fn new(aspect: f32) -> Self {
+        Self {
+            center: (aspect * 0.5, 0.5),
+            zoom: 1.0,
+        }
+    }
+
+    // The size of the visible area of the board.
+    pub fn view_size(&self, aspect: f32) -> (f32, f32) {
+        (aspect / self.zoom, 1.0 / self.zoom)
+    }
+
+    // Zoom is interpolated geometrically, so that zooming feels uniform.
+    pub fn lerp(self, to: Camera, p: f32) -> Camera {
+        let f = |a: f32, b: f32| a + (b - a) * p;
+        Camera {
+            center: (f(self.center.0, to.center.0), f(self.center.1, to.center.1)),
+            zoom: self.zoom * (to.zoom / self.zoom).powf(p),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Replay {
     pub events: Vec<TimedEvent>,
     pub duration: f32,
+    // Width of the board, the height is always 1.
+    #[serde(default = "one")]
+    pub aspect: f32,
 }
 
 impl Replay {
     pub fn new(events: Vec<TimedEvent>) -> Self {
+        let aspect = events
+            .iter()
+            .find_map(|e| match e.event {
+                Event::Board { aspect } if aspect.is_finite() && aspect > 0. => Some(aspect),
+                _ => None,
+            })
+            .unwrap_or(1.0);
         Self {
             duration: Self::duration(&events),
             events,
+            aspect,
         }
     }
+    // Only the events the viewer sees.
+    pub fn for_viewer(&self, viewer: &Viewer) -> Replay {
+        Replay::new(
+            self.events
+                .iter()
+                .filter(|e| e.visibility.visible_to(viewer))
+                .cloned()
+                .collect(),
+        )
+    }
+    // In-game ids of the players that have events only they see, sorted.
+    pub fn players_with_hidden_events(&self) -> Vec<usize> {
+        let mut players = self
+            .events
+            .iter()
+            .flat_map(|e| match &e.visibility {
+                Visibility::All => &[][..],
+                Visibility::Only { players, .. } => &players[..],
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        players.sort();
+        players.dedup();
+        players
+    }
+    // Times and labels of the tick markers, in order.
+    pub fn ticks(&self) -> impl Iterator<Item = (f32, Option<&str>)> + '_ {
+        self.events.iter().filter_map(|e| match &e.event {
+            Event::TickMarker { label } => Some((e.start_time, label.as_deref())),
+            Event::LegacyTickMarker => Some((e.start_time, None)),
+            _ => None,
+        })
+    }
+    // Events at non-finite times, which validate reports, are left out.
     fn duration(events: &[TimedEvent]) -> f32 {
         events
             .iter()
             .map(|e| e.end_time())
-            .max_by(|x, y| x.partial_cmp(y).unwrap())
+            .filter(|t| t.is_finite())
+            .max_by(f32::total_cmp)
             .unwrap_or(0.0)
     }
 }
@@ -267,10 +867,911 @@ impl std::fmt::Display for DecodeError {
 
 impl std::error::Error for DecodeError {}
 
+// Extracts the event from a match log line sent by the game server,
+// "<time> < vis <event>".
+pub fn vis_payload(log_line: &str) -> Option<&str> {
+    let [_, direction, vis, rest] = crate::textapi::split(log_line);
+    (direction == "<" && vis == "vis").then_some(rest)
+}
+
+// Drops the lines of a match log the viewer does not see: hidden vis events,
+// and the messages between the game and players other than the viewer's. Whom
+// the lines that fail to decode are for is unknown, so only omniscient viewers
+// get them.
+pub fn strip_hidden_events(log: &str, viewer: &Viewer) -> String {
+    let mut res = String::with_capacity(log.len());
+    for line in log.lines() {
+        let visible = match line_visibility(line) {
+            None => true,
+            Some(Ok(v)) => v.visible_to(viewer),
+            Some(Err(_)) => *viewer == Viewer::Omniscient,
+        };
+        if visible {
+            res.push_str(line);
+            res.push('\n');
+        }
+    }
+    res
+}
+
+// Who sees a match log line, None if everyone does.
+fn line_visibility(log_line: &str) -> Option<Result<Visibility, DecodeError>> {
+    let [_, direction, cmd, rest] = crate::textapi::split(log_line);
+    match (direction, cmd) {
+        ("<", "vis") => Some(decode_event(rest).map(|e| e.visibility)),
+        ("<", "send") | (">", "recv") => {
+            let [player, _] = crate::textapi::split(rest);
+            Some(
+                player
+                    .parse()
+                    .map(|p| Visibility::Only {
+                        spectator: false,
+                        players: vec![p],
+                    })
+                    .map_err(|_| DecodeError::ParseError(format!("bad player in {log_line:?}"))),
+            )
+        }
+        _ => None,
+    }
+}
+
+// Index of the first line where two match logs differ, ignoring the times.
+pub fn first_divergence<'a>(
+    a: impl IntoIterator<Item = &'a str>,
+    b: impl IntoIterator<Item = &'a str>,
+) -> Option<usize> {
+    let untimed = |l: &'a str| {
+        l.split_once(' ')
+            .filter(|(t, _)| t.parse::<f64>().is_ok())
+            .map_or(l, |(_, rest)| rest)
+    };
+    let (mut a, mut b) = (a.into_iter(), b.into_iter());
+    let mut i = 0;
+    loop {
+        match (a.next(), b.next()) {
+            (None, None) => return None,
+            (Some(x), Some(y)) if untimed(x) == untimed(y) => i += 1,
+            _ => return Some(i),
+        }
+    }
+}
+
+// Decodes an event written in JSON or Hjson. The visualizer, the server and
+// the tools all decode with this, so that they agree on which lines are events.
+pub fn decode_event(s: &str) -> Result<TimedEvent, DecodeError> {
+    // Most games write JSON, which serde_json decodes faster.
+    serde_json::from_str(s).or_else(|_| {
+        let value = crate::hjson::parse(s).map_err(|e| DecodeError::ParseError(e.to_string()))?;
+        serde_json::from_value(value).map_err(|e| DecodeError::ParseError(e.to_string()))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IssueKind {
+    NonFiniteTime(f32),
+    // Events must come in the order of their start time.
+    TimeWentBackwards { previous: f32, current: f32 },
+    InvalidDuration(f32),
+    // Created while an object with the same id exists.
+    DuplicateId(u64),
+    // Created after an object with the same id was destroyed.
+    ReusedId(u64),
+    // Transformed, updated or destroyed, but does not exist.
+    UnknownId(u64),
+    UnknownParent(u64),
+    // All components must be in [0..1].
+    ColorOutOfRange(Color),
+    OpacityOutOfRange(f32),
+    // Paths must start with a MoveTo.
+    PathWithoutMoveTo,
+}
+
+// A problem with the event at `event_index` in Replay::events.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    pub event_index: usize,
+    pub kind: IssueKind,
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "event {}: {:?}", self.event_index, self.kind)
+    }
+}
+
+// Checks the whole replay and returns every issue found, in event order.
+// Z indices are u8, so the out of range ones are reported by decode_event.
+pub fn validate(replay: &Replay) -> Vec<Issue> {
+    let mut issues = vec![];
+    // Children of every existing object.
+    let mut alive = std::collections::HashMap::<u64, Vec<u64>>::new();
+    let mut destroyed = std::collections::HashSet::<u64>::new();
+    let mut previous_time = 0f32;
+    for (event_index, e) in replay.events.iter().enumerate() {
+        let mut report = |kind| issues.push(Issue { event_index, kind });
+        if !e.start_time.is_finite() {
+            report(IssueKind::NonFiniteTime(e.start_time));
+        } else {
+            if e.start_time < previous_time {
+                report(IssueKind::TimeWentBackwards {
+                    previous: previous_time,
+                    current: e.start_time,
+                });
+            }
+            previous_time = e.start_time;
+        }
+        let duration = e.event.duration();
+        if !duration.is_finite() || duration < 0. {
+            report(IssueKind::InvalidDuration(duration));
+        }
+        let mut check_geometry = |geometry: &[geom::Geom]| {
+            for g in geometry {
+                for c in g.fill_color().into_iter().chain(g.stroke_color()) {
+                    if !c.is_valid() {
+                        report(IssueKind::ColorOutOfRange(c));
+                    }
+                }
+                if let geom::Geom::Path { segments, .. } = g {
+                    if !matches!(segments.first(), None | Some(geom::PathSegment::MoveTo(_))) {
+                        report(IssueKind::PathWithoutMoveTo);
+                    }
+                }
+            }
+        };
+        match &e.event {
+            Event::Create {
+                id,
+                geometry,
+                parent,
+                ..
+            } => {
+                check_geometry(geometry);
+                if alive.contains_key(id) {
+                    report(IssueKind::DuplicateId(*id));
+                    continue;
+                }
+                if destroyed.contains(id) {
+                    report(IssueKind::ReusedId(*id));
+                }
+                if let Some(parent) = parent {
+                    match alive.get_mut(parent) {
+                        Some(children) => children.push(*id),
+                        None => report(IssueKind::UnknownParent(*parent)),
+                    }
+                }
+                alive.insert(*id, vec![]);
+            }
+            Event::Destroy { id } => {
+                if !alive.contains_key(id) {
+                    report(IssueKind::UnknownId(*id));
+                }
+                let mut to_destroy = vec![*id];
+                while let Some(id) = to_destroy.pop() {
+                    if let Some(children) = alive.remove(&id) {
+                        destroyed.insert(id);
+                        to_destroy.extend(children);
+                    }
+                }
+            }
+            Event::Transform { id, .. } => {
+                if !alive.contains_key(id) {
+                    report(IssueKind::UnknownId(*id));
+                }
+            }
+            Event::Update { id, update, .. } => {
+                if let Some(geometry) = &update.geometry {
+                    check_geometry(geometry);
+                }
+                for c in update.fill_color.iter().chain(&update.stroke_color) {
+                    if !c.is_valid() {
+                        report(IssueKind::ColorOutOfRange(*c));
+                    }
+                }
+                if let Some(o) = update.opacity.filter(|o| !(0.0..=1.0).contains(o)) {
+                    report(IssueKind::OpacityOutOfRange(o));
+                }
+                if !alive.contains_key(id) {
+                    report(IssueKind::UnknownId(*id));
+                }
+            }
+            Event::Board { .. }
+            | Event::Camera { .. }
+            | Event::Log { .. }
+            | Event::TickMarker { .. }
+            | Event::LegacyTickMarker => {}
+        }
+    }
+    issues
+}
+
 fn zero() -> (f32, f32) {
     (0., 0.)
 }
 
+fn one() -> f32 {
+    1.0
+}
+
 fn default_z() -> u8 {
     1
 }
+
+#[derive(Debug)]
+pub enum BuildError {
+    Io(std::io::Error),
+    Serialize(serde_json::Error),
+    // Events must be emitted in the order of their start time.
+    TimeWentBackwards { current: f32, requested: f32 },
+    NonFiniteTime(f32),
+    // Objects must be created only once, even after they are destroyed.
+    ReusedId(u64),
+}
+
+impl std::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl std::error::Error for BuildError {}
+
+impl From<std::io::Error> for BuildError {
+    fn from(e: std::io::Error) -> Self {
+        BuildError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for BuildError {
+    fn from(e: serde_json::Error) -> Self {
+        BuildError::Serialize(e)
+    }
+}
+
+// Writes "vis" lines for a game server. Keeps track of the current time, which
+// can only go forward, and allocates object ids.
+//
+//   let mut vis = Builder::new(std::io::stdout());
+//   let id = vis.create((0.5, 0.5), 1, vec![Geom::circle((0., 0.), 0.1)])?;
+//   vis.advance(0.5)?;
+//   vis.transform(id, 0.3, Transform::Move((0.1, 0.)))?;
+pub struct Builder<W: std::io::Write> {
+    out: W,
+    time: f32,
+    next_id: u64,
+    created: HashSet<u64>,
+    visibility: Visibility,
+}
+
+impl<W: std::io::Write> Builder<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            time: 0.0,
+            next_id: 1,
+            created: HashSet::new(),
+            visibility: Visibility::All,
+        }
+    }
+
+    // Sets who sees the following events.
+    pub fn set_visibility(&mut self, visibility: Visibility) {
+        self.visibility = visibility;
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // Sets the start time for the following events.
+    pub fn set_time(&mut self, t: f32) -> Result<(), BuildError> {
+        if !t.is_finite() {
+            return Err(BuildError::NonFiniteTime(t));
+        }
+        if t < self.time {
+            return Err(BuildError::TimeWentBackwards {
+                current: self.time,
+                requested: t,
+            });
+        }
+        self.time = t;
+        Ok(())
+    }
+
+    pub fn advance(&mut self, dt: f32) -> Result<(), BuildError> {
+        self.set_time(self.time + dt)
+    }
+
+    // Creates an object with a fresh id and returns the id.
+    pub fn create(
+        &mut self,
+        position: (f32, f32),
+        z_index: u8,
+        geometry: Vec<geom::Geom>,
+    ) -> Result<u64, BuildError> {
+        self.create_impl(None, position, z_index, geometry)
+    }
+
+    // Same as `create`, but the position is relative to the parent object.
+    pub fn create_child(
+        &mut self,
+        parent: u64,
+        position: (f32, f32),
+        z_index: u8,
+        geometry: Vec<geom::Geom>,
+    ) -> Result<u64, BuildError> {
+        self.create_impl(Some(parent), position, z_index, geometry)
+    }
+
+    fn create_impl(
+        &mut self,
+        parent: Option<u64>,
+        position: (f32, f32),
+        z_index: u8,
+        geometry: Vec<geom::Geom>,
+    ) -> Result<u64, BuildError> {
+        let id = self.next_id;
+        self.emit(Event::Create {
+            id,
+            position,
+            z_index,
+            geometry,
+            parent,
+        })?;
+        Ok(id)
+    }
+
+    pub fn destroy(&mut self, id: u64) -> Result<(), BuildError> {
+        self.emit(Event::Destroy { id })
+    }
+
+    pub fn transform(
+        &mut self,
+        id: u64,
+        duration: f32,
+        transform: Transform,
+    ) -> Result<(), BuildError> {
+        self.transform_with(id, duration, AnimateFunction::default(), transform)
+    }
+
+    pub fn transform_with(
+        &mut self,
+        id: u64,
+        duration: f32,
+        animate_function: AnimateFunction,
+        transform: Transform,
+    ) -> Result<(), BuildError> {
+        self.emit(Event::Transform {
+            id,
+            duration,
+            animate_function,
+            transform,
+        })
+    }
+
+    pub fn update(&mut self, id: u64, update: ObjectUpdate) -> Result<(), BuildError> {
+        self.update_with(id, 0.0, AnimateFunction::default(), update)
+    }
+
+    pub fn update_with(
+        &mut self,
+        id: u64,
+        duration: f32,
+        animate_function: AnimateFunction,
+        update: ObjectUpdate,
+    ) -> Result<(), BuildError> {
+        self.emit(Event::Update {
+            id,
+            duration,
+            animate_function,
+            update,
+        })
+    }
+
+    // Should be emitted at the start, before any other events.
+    pub fn board(&mut self, aspect: f32) -> Result<(), BuildError> {
+        self.emit(Event::Board { aspect })
+    }
+
+    pub fn camera(
+        &mut self,
+        center: (f32, f32),
+        zoom: f32,
+        duration: f32,
+    ) -> Result<(), BuildError> {
+        self.emit(Event::Camera {
+            center,
+            zoom,
+            duration,
+            animate_function: AnimateFunction::default(),
+        })
+    }
+
+    pub fn tick(&mut self, label: Option<&str>) -> Result<(), BuildError> {
+        self.emit(Event::TickMarker {
+            label: label.map(str::to_owned),
+        })
+    }
+
+    pub fn log(&mut self, line: impl Into<String>) -> Result<(), BuildError> {
+        self.emit(Event::Log { line: line.into() })
+    }
+
+    // Writes an arbitrary event at the current time. Ids of created objects
+    // are never handed out by `create` afterwards.
+    pub fn emit(&mut self, event: Event) -> Result<(), BuildError> {
+        if let Event::Create { id, .. } = &event {
+            if !self.created.insert(*id) {
+                return Err(BuildError::ReusedId(*id));
+            }
+            self.next_id = self.next_id.max(id.saturating_add(1));
+        }
+        let line = serde_json::to_string(&TimedEvent {
+            start_time: self.time,
+            event,
+            visibility: self.visibility.clone(),
+        })?;
+        writeln!(self.out, "vis {line}")?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::geom::Geom;
+    use super::*;
+
+    #[test]
+    fn builder_ids() {
+        let mut vis = Builder::new(vec![]);
+        let create = |id| Event::Create {
+            id,
+            position: (0., 0.),
+            z_index: 1,
+            geometry: vec![],
+            parent: None,
+        };
+        assert_eq!(vis.create((0., 0.), 1, vec![]).unwrap(), 1);
+        vis.emit(create(5)).unwrap();
+        assert!(matches!(vis.emit(create(5)), Err(BuildError::ReusedId(5))));
+        vis.emit(Event::Destroy { id: 1 }).unwrap();
+        assert!(matches!(vis.emit(create(1)), Err(BuildError::ReusedId(1))));
+        assert_eq!(vis.create((0., 0.), 1, vec![]).unwrap(), 6);
+        vis.emit(create(u64::MAX)).unwrap();
+        assert!(matches!(
+            vis.create((0., 0.), 1, vec![]),
+            Err(BuildError::ReusedId(u64::MAX))
+        ));
+    }
+
+    #[test]
+    fn builder_writes_parseable_lines() {
+        let mut vis = Builder::new(vec![]);
+        let red = Color {
+            r: 1.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        };
+        let id = vis
+            .create(
+                (0.5, 0.5),
+                2,
+                vec![
+                    Geom::circle((0., 0.), 0.1).fill(red),
+                    Geom::text((0., 0.), 0.05, "say \"hi\"\n"),
+                ],
+            )
+            .unwrap();
+        assert_eq!(id, 1);
+        vis.advance(0.5).unwrap();
+        vis.transform(id, 0.25, Transform::Move((0.1, 0.))).unwrap();
+        assert!(matches!(
+            vis.set_time(0.25),
+            Err(BuildError::TimeWentBackwards { .. })
+        ));
+        vis.emit(Event::Create {
+            id: 10,
+            position: (0., 0.),
+            z_index: 1,
+            geometry: vec![],
+            parent: None,
+        })
+        .unwrap();
+        assert_eq!(vis.create_child(10, (0., 0.), 1, vec![]).unwrap(), 11);
+        vis.update_with(
+            id,
+            0.5,
+            AnimateFunction::Linear,
+            ObjectUpdate {
+                fill_color: Some(red),
+                text: Some("bye".to_owned()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        vis.destroy(id).unwrap();
+
+        let out = String::from_utf8(vis.into_inner()).unwrap();
+        let events = out
+            .lines()
+            .map(|l| serde_json::from_str::<TimedEvent>(l.strip_prefix("vis ").unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(events.len(), 6);
+        assert_eq!(events[0].start_time, 0.);
+        let Event::Create { geometry, .. } = &events[0].event else {
+            panic!("unexpected {:?}", events[0]);
+        };
+        assert_eq!(geometry[0], Geom::circle((0., 0.), 0.1).fill(red));
+        assert_eq!(geometry[1], Geom::text((0., 0.), 0.05, "say \"hi\"\n"));
+        assert_eq!(
+            events[1],
+            TimedEvent {
+                start_time: 0.5,
+                event: Event::Transform {
+                    id: 1,
+                    duration: 0.25,
+                    animate_function: AnimateFunction::EaseInOut,
+                    transform: Transform::Move((0.1, 0.)),
+                },
+                visibility: Visibility::All,
+            }
+        );
+        assert!(matches!(
+            events[3].event,
+            Event::Create {
+                id: 11,
+                parent: Some(10),
+                ..
+            }
+        ));
+        assert_eq!(events[4].end_time(), 1.0);
+        let Event::Update { update, .. } = &events[4].event else {
+            panic!("unexpected {:?}", events[4]);
+        };
+        assert_eq!(
+            update.apply(geometry),
+            vec![
+                Geom::circle((0., 0.), 0.1).fill(red),
+                Geom::text((0., 0.), 0.05, "bye"),
+            ]
+        );
+        assert_eq!(events[5].event, Event::Destroy { id: 1 });
+    }
+
+    #[test]
+    fn board_and_camera() {
+        let mut vis = Builder::new(vec![]);
+        vis.board(1.5).unwrap();
+        vis.camera((0.5, 0.5), 4., 1.).unwrap();
+        vis.tick(None).unwrap();
+        vis.advance(1.).unwrap();
+        vis.tick(Some("Move 2")).unwrap();
+        let events = String::from_utf8(vis.into_inner())
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<TimedEvent>(l.strip_prefix("vis ").unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        let replay = Replay::new(events);
+        assert_eq!(replay.aspect, 1.5);
+        assert_eq!(replay.duration, 1.);
+        assert_eq!(
+            replay.ticks().collect::<Vec<_>>(),
+            vec![(0., None), (1., Some("Move 2"))]
+        );
+        assert_eq!(
+            Camera::new(replay.aspect).view_size(replay.aspect),
+            (1.5, 1.)
+        );
+
+        let from = Camera::new(1.);
+        let to = Camera {
+            center: (0., 1.),
+            zoom: 4.,
+        };
+        assert_eq!(from.lerp(to, 0.), from);
+        assert_eq!(from.lerp(to, 1.), to);
+        assert_eq!(from.lerp(to, 0.5).zoom, 2.);
+    }
+
+    #[test]
+    fn object_update_apply() {
+        let geometry: Vec<Geom> = serde_json::from_str(
+            r#"[{"circle":{"f":"ff0000ff"}},{"line":{"p1":[0,0],"p2":[1,1]}},{"text":{"p":[0,0],"v":"1"}},{"image":{"src":"x.png","wh":[1,1]}}]"#,
+        )
+        .unwrap();
+        let update: ObjectUpdate =
+            serde_json::from_str(r#"{"fill":"00ff00ff","s":"0000ffff","v":"2","o":0.5}"#).unwrap();
+        let updated = update.apply(&geometry);
+        let green = serde_json::from_str::<Color>(r#""00ff00ff""#).unwrap();
+        let blue = serde_json::from_str::<Color>(r#""0000ffff""#).unwrap();
+        assert_eq!(updated.len(), 4);
+        assert_eq!(updated[0].fill_color(), Some(green));
+        assert_eq!(updated[0].stroke_color(), Some(blue));
+        assert_eq!(updated[1].fill_color(), None);
+        assert_eq!(updated[1].stroke_color(), Some(blue));
+        assert!(matches!(&updated[2], Geom::Text { text, .. } if text == "2"));
+        assert_eq!(updated[3], geometry[3]);
+        assert_eq!(ObjectUpdate::default().apply(&geometry), geometry);
+
+        // A new geometry is set first, then the colors are applied to it.
+        let update: ObjectUpdate =
+            serde_json::from_str(r#"{"geom":[{"rect":{"wh":[1,1]}}],"fill":"00ff00ff"}"#).unwrap();
+        let updated = update.apply(&geometry);
+        assert_eq!(updated.len(), 1);
+        assert!(matches!(updated[0], Geom::Rect { .. }));
+        assert_eq!(updated[0].fill_color(), Some(green));
+    }
+
+    #[test]
+    fn duration_of_non_finite_times() {
+        let event = |t| TimedEvent {
+            start_time: t,
+            ..decode_event(r#"{"t":0,"log":{"line":"x"}}"#).unwrap()
+        };
+        let replay = Replay::new(vec![event(1.), event(f32::NAN), event(f32::INFINITY)]);
+        assert_eq!(replay.duration, 1.);
+        assert_eq!(Replay::new(vec![event(f32::NAN)]).duration, 0.);
+    }
+
+    #[test]
+    fn tick_labels() {
+        let events = [
+            r#"{"t":0,"TickMarker":null}"#,
+            r#"{"t":1,"tick":{}}"#,
+            r#"{t:2,tick:{label:"Move 3"}}"#,
+            r#"{"t":3,"log":{"line":"tick"}}"#,
+        ]
+        .into_iter()
+        .map(|l| decode_event(l).unwrap())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            serde_json::to_string(&events[2]).unwrap(),
+            r#"{"t":2.0,"tick":{"label":"Move 3"}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&events[1]).unwrap(),
+            r#"{"t":1.0,"tick":{}}"#
+        );
+        let replay = Replay::new(events);
+        assert_eq!(
+            replay.ticks().collect::<Vec<_>>(),
+            vec![(0., None), (1., None), (2., Some("Move 3"))]
+        );
+        assert!(validate(&replay).is_empty());
+    }
+
+    #[test]
+    fn new_geoms_parse() {
+        let ev: TimedEvent = serde_json::from_str(
+            r#"{"t":1,"create":{"id":3,"geom":[
+                {"rect":{"wh":[0.2,0.1],"cr":0.02}},
+                {"arc":{"r":0.3,"a1":0,"a2":1.5}},
+                {"path":{"d":[{"m":[0,0]},{"q":[[0.5,0],[0.5,0.5]]},{"c":[[0.5,1],[0,1],[0,0.5]]}],"z":true}},
+                {"image":{"src":"knight.png","p":[0.1,0.1],"wh":[0.1,0.1]}}
+            ]}}"#,
+        )
+        .unwrap();
+        let Event::Create { geometry, .. } = ev.event else {
+            panic!("unexpected {ev:?}");
+        };
+        assert_eq!(
+            geometry,
+            vec![
+                Geom::rounded_rect((0., 0.), (0.2, 0.1), 0.02),
+                Geom::arc((0., 0.), 0.3, 0., 1.5),
+                Geom::path(
+                    vec![
+                        geom::PathSegment::MoveTo((0., 0.)),
+                        geom::PathSegment::QuadTo((0.5, 0.), (0.5, 0.5)),
+                        geom::PathSegment::CubicTo((0.5, 1.), (0., 1.), (0., 0.5)),
+                    ],
+                    true
+                ),
+                Geom::image("knight.png", (0.1, 0.1), (0.1, 0.1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn asset_names() {
+        assert!(validate_asset_name("knight-2_white.png").is_ok());
+        assert!(validate_asset_name("initial.svg").is_ok());
+        for name in [
+            "",
+            ".hidden.png",
+            "../index.html",
+            "a/b.png",
+            "icon.svg",
+            "icon.png",
+        ] {
+            assert!(validate_asset_name(name).is_err(), "{name}");
+        }
+    }
+
+    #[test]
+    fn decodes_hjson_field_names() {
+        let line = r#"000.100000 < vis {t:1.5,create:{id:33,z:2,geom:[{text:{p:[0,0],t:0.1,v:"a: \"b\" c"}}]}}"#;
+        let ev = decode_event(vis_payload(line).unwrap()).unwrap();
+        let Event::Create { geometry, .. } = ev.event else {
+            panic!("unexpected {ev:?}");
+        };
+        assert_eq!(geometry, vec![Geom::text((0., 0.), 0.1, "a: \"b\" c")]);
+        let ev = decode_event("{t:2, log:{line: a, b # c\n}, to:[1,],} // private").unwrap();
+        assert_eq!(
+            ev.event,
+            Event::Log {
+                line: "a, b # c".to_owned()
+            }
+        );
+        assert_eq!(
+            ev.visibility,
+            Visibility::Only {
+                spectator: false,
+                players: vec![1]
+            }
+        );
+        assert!(vis_payload("000.100000 < send 1 x").is_none());
+        assert!(vis_payload("000.100000 > vis inline").is_none());
+        assert!(decode_event(r#"{"t":0,"create":{"id":1,"z":300,"geom":[]}}"#).is_err());
+    }
+
+    #[test]
+    fn validate_reports_every_issue() {
+        let lines = [
+            r#"{"t":0,"create":{"id":1,"geom":[]}}"#,
+            r#"{"t":0,"create":{"id":2,"parent":1,"geom":[]}}"#,
+            r#"{"t":1,"create":{"id":1,"geom":[]}}"#,
+            r#"{"t":0.5,"transform":{"id":3,"d":1,"rot":1}}"#,
+            r#"{"t":2,"destroy":{"id":1}}"#,
+            r#"{"t":2,"update":{"id":2,"o":2}}"#,
+            r#"{"t":3,"create":{"id":1,"parent":5,"geom":[]}}"#,
+            r#"{"t":3,"transform":{"id":1,"d":-1,"scale":2}}"#,
+            r#"{"t":3,"update":{"id":1,"geom":[{"path":{"d":[{"l":[1,1]}]}}]}}"#,
+        ];
+        let mut events = lines
+            .iter()
+            .map(|l| decode_event(l).unwrap())
+            .collect::<Vec<_>>();
+        let bright = Color {
+            r: 2.,
+            g: 0.,
+            b: 0.,
+            a: 1.,
+        };
+        events.push(TimedEvent {
+            start_time: 4.,
+            event: Event::Update {
+                id: 1,
+                duration: 0.,
+                animate_function: AnimateFunction::Step,
+                update: ObjectUpdate {
+                    fill_color: Some(bright),
+                    ..Default::default()
+                },
+            },
+            visibility: Visibility::All,
+        });
+        let issues = validate(&Replay::new(events))
+            .into_iter()
+            .map(|i| (i.event_index, i.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            issues,
+            vec![
+                (2, IssueKind::DuplicateId(1)),
+                (
+                    3,
+                    IssueKind::TimeWentBackwards {
+                        previous: 1.,
+                        current: 0.5
+                    }
+                ),
+                (3, IssueKind::UnknownId(3)),
+                (5, IssueKind::OpacityOutOfRange(2.)),
+                (5, IssueKind::UnknownId(2)),
+                (6, IssueKind::ReusedId(1)),
+                (6, IssueKind::UnknownParent(5)),
+                (7, IssueKind::InvalidDuration(-1.)),
+                (8, IssueKind::PathWithoutMoveTo),
+                (9, IssueKind::ColorOutOfRange(bright)),
+            ]
+        );
+    }
+
+    #[test]
+    fn log_divergence() {
+        let a = ["000.100000 > move", "000.200000 < 3 1", "score 3"];
+        let b = ["000.150000 > move", "000.210000 < 3 2", "score 3"];
+        assert_eq!(first_divergence(a, a), None);
+        assert_eq!(first_divergence(a, b), Some(1));
+        assert_eq!(first_divergence(a[..1].iter().copied(), a), Some(1));
+        assert_eq!(first_divergence(["score 3"], ["score 4"]), Some(0));
+    }
+
+    #[test]
+    fn visibility_sets() {
+        let mut vis = Builder::new(vec![]);
+        vis.create((0., 0.), 1, vec![]).unwrap();
+        vis.set_visibility(Visibility::Only {
+            spectator: true,
+            players: vec![],
+        });
+        vis.log("all hands").unwrap();
+        vis.set_visibility(Visibility::Only {
+            spectator: true,
+            players: vec![2],
+        });
+        vis.log("hand of 2").unwrap();
+        let out = String::from_utf8(vis.into_inner()).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert!(!lines[0].contains("\"to\""), "{}", lines[0]);
+        assert!(lines[1].ends_with(r#","to":"spectator"}"#), "{}", lines[1]);
+        assert!(
+            lines[2].ends_with(r#","to":["spectator",2]}"#),
+            "{}",
+            lines[2]
+        );
+        let mut events = lines
+            .iter()
+            .map(|l| decode_event(l.strip_prefix("vis ").unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        events.push(decode_event(r#"{"t":1,"destroy":{"id":1},"to":[1,3]}"#).unwrap());
+        let replay = Replay::new(events);
+        assert_eq!(replay.players_with_hidden_events(), vec![1, 2, 3]);
+        let count = |viewer| replay.for_viewer(&viewer).events.len();
+        assert_eq!(count(Viewer::Omniscient), 4);
+        assert_eq!(count(Viewer::Spectator), 3);
+        assert_eq!(count(Viewer::Players(vec![1])), 2);
+        assert_eq!(count(Viewer::Players(vec![2, 3])), 3);
+        assert_eq!(replay.for_viewer(&Viewer::Players(vec![2])).duration, 0.);
+        assert!(decode_event(r#"{"t":1,"destroy":{"id":1},"to":["nobody"]}"#).is_err());
+        let log = lines
+            .iter()
+            .map(|l| format!("000.000000 < {l}\n"))
+            .collect::<String>();
+        assert_eq!(
+            strip_hidden_events(&log, &Viewer::Players(vec![1])),
+            format!("000.000000 < {}\n", lines[0])
+        );
+        assert_eq!(strip_hidden_events(&log, &Viewer::Omniscient), log);
+    }
+
+    #[test]
+    fn private_messages_are_hidden() {
+        let log = "\
+000.000000 > start
+000.001000 < sendall start
+000.002000 < send 1 hand A K
+000.003000 < send 2 hand 7 2
+000.004000 > recv 1 raise
+000.005000 > recv 2 fold
+000.006000 < vis {\"t\":0,\"log\":{\"line\":\"1 raises\"}}
+";
+        let kept = |viewer, lines: &[usize]| {
+            let expected = lines
+                .iter()
+                .map(|&i| format!("{}\n", log.lines().nth(i).unwrap()))
+                .collect::<String>();
+            assert_eq!(strip_hidden_events(log, &viewer), expected, "{viewer:?}");
+        };
+        kept(Viewer::Spectator, &[0, 1, 6]);
+        kept(Viewer::Players(vec![1]), &[0, 1, 2, 4, 6]);
+        kept(Viewer::Players(vec![1, 2]), &[0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(strip_hidden_events(log, &Viewer::Omniscient), log);
+    }
+
+    #[test]
+    fn undecodable_events_are_hidden() {
+        let log = "0 < vis {t:0,log:{line:\"secret\"},to:[1]\n0 < sendall x\n0 < send x y\n";
+        assert!(decode_event(vis_payload(log.lines().next().unwrap()).unwrap()).is_err());
+        for viewer in [Viewer::Spectator, Viewer::Players(vec![1])] {
+            assert_eq!(strip_hidden_events(log, &viewer), "0 < sendall x\n");
+        }
+        assert_eq!(strip_hidden_events(log, &Viewer::Omniscient), log);
+    }
+}
@@ -1,6 +1,8 @@
 set -xe
 cargo build --release
 
+bash agent-templates/rust/prepare.sh
+
 cd visualizer-svg
 bash build.sh
 cd ..
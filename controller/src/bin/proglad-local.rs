@@ -0,0 +1,125 @@
+// Runs a single match locally, without the web server, the database or docker.
+// Every agent is started as `sh -c "exec <command>"` with its stdin and stdout
+// redirected to the FIFOs the match runner talks to.
+//
+// Example:
+//   proglad-local --game ./game-server --bot ./bot1 --bot "python3 bot2.py" \
+//     --param "some params" --log replay.gz
+//
+// The log is written in the same gzip-compressed format as the server stores
+// for matches; `gunzip -c replay.gz > visualizer-svg/replay.txt` makes it
+// viewable with visualizer-svg/index.html.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, Context};
+use clap::Parser;
+
+use proglad_controller::{io, match_runner};
+
+#[derive(Parser, Debug)]
+struct Config {
+    /// Game server executable (or shell command).
+    #[arg(long, short = 'g')]
+    game: String,
+    /// Bot command, in the order of in-game player ids. Repeat for every player.
+    #[arg(long, short = 'b')]
+    bot: Vec<String>,
+    /// Param string passed to the game server.
+    #[arg(long, short = 'p', default_value = "")]
+    param: String,
+    /// Where to write the gzip-compressed match log.
+    #[arg(long, short = 'l', default_value = "replay.gz")]
+    log: PathBuf,
+    /// Directory to create the FIFOs in. A temporary one is used if not set.
+    #[arg(long)]
+    work_dir: Option<PathBuf>,
+    #[arg(long, default_value_t = 1000)]
+    send_timeout_ms: u64,
+    #[arg(long, default_value_t = 3000)]
+    sender_open_timeout_ms: u64,
+    #[arg(long, default_value_t = 3000)]
+    player_ready_timeout_ms: u64,
+    #[arg(long)]
+    kick_for_errors: bool,
+    #[arg(long, default_value_t = 10)]
+    max_player_errors: usize,
+    #[arg(long, default_value_t = 4096)]
+    line_length_limit: usize,
+    /// Protocol version declared for the game server, as in the game config.
+    #[arg(long)]
+    protocol_version: Option<u32>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cfg = Config::parse();
+    if cfg.bot.is_empty() {
+        return Err(anyhow!("At least one --bot is required"));
+    }
+    let temp_dir;
+    let work_dir = match &cfg.work_dir {
+        Some(d) => d.as_path(),
+        None => {
+            temp_dir = tempfile::tempdir().context("Failed to create a temp dir")?;
+            temp_dir.path()
+        }
+    };
+    let commands = std::iter::once(&cfg.game).chain(cfg.bot.iter());
+    let mut ios = Vec::with_capacity(cfg.bot.len() + 1);
+    let mut children = Vec::with_capacity(cfg.bot.len() + 1);
+    for (i, command) in commands.enumerate() {
+        let agent_io = io::AgentIO {
+            their_stdin: work_dir.join(format!("i{i}")),
+            their_stdout: work_dir.join(format!("o{i}")),
+        };
+        io::create(&agent_io).context(format!("Failed to create io files for {agent_io:?}"))?;
+        children.push(spawn_agent(command, &agent_io)?);
+        ios.push(agent_io);
+    }
+    let file = tokio::fs::File::create(&cfg.log)
+        .await
+        .context(format!("Failed to create log file {:?}", cfg.log))?;
+    let game_log_sink = Box::new(async_compression::tokio::write::GzipEncoder::new(file));
+    let mr = match_runner::run(match_runner::MatchConfig {
+        config: match_runner::Config {
+            send_timeout: Duration::from_millis(cfg.send_timeout_ms),
+            sender_open_timeout: Duration::from_millis(cfg.sender_open_timeout_ms),
+            player_ready_timeout: Duration::from_millis(cfg.player_ready_timeout_ms),
+            kick_for_errors: cfg.kick_for_errors,
+            max_player_errors: cfg.max_player_errors,
+            line_length_limit: cfg.line_length_limit,
+        },
+        ios,
+        params: vec![cfg.param.clone()],
+        game_log_sink,
+        protocol_version: cfg.protocol_version,
+    })
+    .await;
+    for mut child in children {
+        let _ = child.start_kill();
+        let _ = child.wait().await;
+    }
+    let mr = mr.context("Match failed")?;
+    println!("{}", toml::to_string_pretty(&mr)?);
+    eprintln!("Match log written to {:?}", cfg.log);
+    Ok(())
+}
+
+fn spawn_agent(command: &str, agent_io: &io::AgentIO) -> anyhow::Result<tokio::process::Child> {
+    tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(format!(
+            "exec {command} < {} > {}",
+            shell_quote(&agent_io.their_stdin),
+            shell_quote(&agent_io.their_stdout),
+        ))
+        .kill_on_drop(true)
+        .spawn()
+        .context(format!("Failed to start '{command}'"))
+}
+
+fn shell_quote(path: &Path) -> String {
+    format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"))
+}
@@ -89,7 +89,10 @@ pub async fn read_with_limit<R: AsyncRead + Unpin>(mut r: R, limit: usize) -> St
     String::from_utf8_lossy(&buffer[..fullness]).into_owned()
 }
 
-fn make_line_sink<W: AsyncWrite + Send + Unpin + 'static>(w: W, line_limit: usize) -> LineSink {
+pub(crate) fn make_line_sink<W: AsyncWrite + Send + Unpin + 'static>(
+    w: W,
+    line_limit: usize,
+) -> LineSink {
     Box::new(
         tokio_util::codec::FramedWrite::new(
             w,
@@ -99,7 +102,10 @@ fn make_line_sink<W: AsyncWrite + Send + Unpin + 'static>(w: W, line_limit: usiz
     )
 }
 
-fn make_line_stream<R: AsyncRead + Send + Unpin + 'static>(r: R, line_limit: usize) -> LineStream {
+pub(crate) fn make_line_stream<R: AsyncRead + Send + Unpin + 'static>(
+    r: R,
+    line_limit: usize,
+) -> LineStream {
     Box::new(
         tokio_util::codec::FramedRead::new(
             r,
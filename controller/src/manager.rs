@@ -63,6 +63,7 @@ pub struct MatchConfig {
     pub config: match_runner::Config,
     pub id: MatchId,
     pub agents: Vec<Agent>,
+    pub protocol_version: Option<u32>,
 }
 
 impl MatchConfig {
@@ -167,6 +168,7 @@ async fn run_match_impl(s: Arc<Manager>, mc: MatchConfig) -> anyhow::Result<Full
         ios,
         params,
         game_log_sink,
+        protocol_version: mc.protocol_version,
     })
     .await;
     let end_time = time::OffsetDateTime::now_utc();
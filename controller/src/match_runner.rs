@@ -8,7 +8,10 @@ use std::collections::BTreeSet;
 use tokio::io::{AsyncWrite, AsyncWriteExt};
 
 use crate::io::*;
-use proglad_api::textapi;
+use proglad_api::textapi::{
+    BotToController, ControllerToGame, GameToController, VisMode, LEGACY_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
+};
 
 pub type TextLogSink = Box<dyn AsyncWrite + Unpin + Send>;
 
@@ -29,6 +32,10 @@ pub struct MatchConfig {
     pub params: Vec<String>,
     // Instead of using 'tee' which is a separate process, log here.
     pub game_log_sink: TextLogSink,
+    // Protocol version declared in the game config. If set, the controller
+    // starts by sending "proto"; otherwise the game server is assumed to speak
+    // the legacy protocol, unless it sends "proto" itself.
+    pub protocol_version: Option<u32>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -104,6 +111,11 @@ struct MatchOnServer {
     ready_deadline: Option<std::time::Instant>,
     player_errors: Vec<Vec<String>>,
     max_player_errors: usize,
+    declared_protocol_version: Option<u32>,
+    protocol_version: u32,
+    // The controller sent "proto" and the game server has not answered yet.
+    // "start" does not wait for the answer, so it is accepted after "start".
+    proto_reply_pending: bool,
 }
 
 type PinnedFuture<'a, T> = std::pin::Pin<
@@ -131,6 +143,9 @@ impl MatchOnServer {
             start_instant: std::time::Instant::now(),
             player_errors: vec![],
             max_player_errors: config.config.max_player_errors,
+            declared_protocol_version: config.protocol_version,
+            protocol_version: LEGACY_PROTOCOL_VERSION,
+            proto_reply_pending: false,
         }
     }
 
@@ -149,14 +164,15 @@ impl MatchOnServer {
     }
 
     async fn kick_player(&mut self, ingame_id: usize) -> anyhow::Result<()> {
-        self.game_send(format!("dropped {ingame_id}")).await?;
+        self.game_send(ControllerToGame::Dropped(ingame_id)).await?;
         if let Some(mp) = self.players.get_mut(ingame_id - 1) {
             *mp = None;
         }
         Ok(())
     }
 
-    async fn game_send(&mut self, msg: String) -> anyhow::Result<()> {
+    async fn game_send(&mut self, msg: ControllerToGame) -> anyhow::Result<()> {
+        let msg = msg.to_string();
         self.log_to_sink(LogDirection::In, &msg).await;
         tokio::time::timeout(self.send_timeout, self.game_server_sink.send(msg))
             .await?
@@ -174,9 +190,20 @@ impl MatchOnServer {
 
     async fn run_impl(&mut self) -> anyhow::Result<MatchResult> {
         self.ready_deadline = Some(std::time::Instant::now() + self.player_ready_timeout);
-        self.game_send("vis inline".to_owned()).await?;
+        if let Some(declared) = self.declared_protocol_version {
+            if declared < LEGACY_PROTOCOL_VERSION {
+                return Err(anyhow!("Invalid declared protocol version {declared}"));
+            }
+            self.protocol_version = declared.min(PROTOCOL_VERSION);
+            self.game_send(ControllerToGame::Proto(PROTOCOL_VERSION))
+                .await?;
+            self.proto_reply_pending = true;
+        }
+        self.game_send(ControllerToGame::Vis(VisMode::Inline))
+            .await?;
         if let Some(param_str) = self.params.first() {
-            self.game_send(format!("param {param_str}")).await?;
+            self.game_send(ControllerToGame::Param(param_str.clone()))
+                .await?;
         }
         loop {
             match &mut self.state {
@@ -234,7 +261,7 @@ impl MatchOnServer {
             }
             if self.ready_deadline.is_some() && self.all_players_ready() {
                 self.ready_deadline = None;
-                self.game_send("start".to_owned()).await?;
+                self.game_send(ControllerToGame::Start).await?;
             }
         }
     }
@@ -242,7 +269,7 @@ impl MatchOnServer {
     fn all_players_ready(&self) -> bool {
         self.players
             .iter()
-            .all(|op| op.as_ref().map_or(true, |p| p.reported_ready))
+            .all(|op| op.as_ref().is_none_or(|p| p.reported_ready))
     }
 
     fn time_from_start_micros(&self) -> u128 {
@@ -279,16 +306,49 @@ impl MatchOnServer {
 
     async fn handle_game_msg(&mut self, line: String) -> anyhow::Result<()> {
         self.log_to_sink(LogDirection::Out, &line).await;
-        let [cmd, rest] = textapi::split(&line);
-        match cmd {
-            "" => return Err(anyhow!("Empty game command")),
-            "timer" => self.set_timer(rest).await?,
-            "over" => self.over(rest).await?,
-            "sendall" => self.sendall(rest).await?,
-            "playererror" => self.playererror(rest).await?,
-            "send" => self.game_to_player_send(rest).await?,
-            "vis" => { /* intentionally ignore for now */ }
-            _ => return Err(anyhow!("Unrecognized game command '{cmd}'")),
+        let msg = GameToController::parse(&line, self.players.len())
+            .context("Failed to parse game command")?;
+        match msg {
+            GameToController::Proto(version) => self.set_protocol_version(version)?,
+            GameToController::CancelTimer(id) => {
+                self.require_protocol_version(2, "canceltimer")?;
+                self.game_timers.retain(|(_, timer_id)| *timer_id != id);
+            }
+            GameToController::Timer { id, duration } => self.set_timer(id, duration).await?,
+            GameToController::Over { scores, reason } => self.over(scores, reason).await?,
+            GameToController::SendAll(msg) => self.sendall(&msg).await?,
+            GameToController::PlayerError { player, msg } => self.playererror(player, &msg).await?,
+            GameToController::Send { player, msg } => {
+                self.game_to_player_send(player, &msg).await?
+            }
+            GameToController::Vis(_) => { /* intentionally ignore for now */ }
+        }
+        Ok(())
+    }
+
+    fn set_protocol_version(&mut self, version: u32) -> anyhow::Result<()> {
+        // The ready deadline is cleared when "start" is sent.
+        let reply = std::mem::take(&mut self.proto_reply_pending);
+        if self.ready_deadline.is_none() && !reply {
+            return Err(anyhow!(
+                "'proto' is only accepted before 'start' or as the answer to 'proto'"
+            ));
+        }
+        if !(LEGACY_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&version) {
+            return Err(anyhow!(
+                "Unsupported protocol version {version}; supported: {LEGACY_PROTOCOL_VERSION}..={PROTOCOL_VERSION}"
+            ));
+        }
+        self.protocol_version = version;
+        Ok(())
+    }
+
+    fn require_protocol_version(&self, version: u32, cmd: &str) -> anyhow::Result<()> {
+        if self.protocol_version < version {
+            return Err(anyhow!(
+                "'{cmd}' requires protocol version {version}, the game server uses {}",
+                self.protocol_version
+            ));
         }
         Ok(())
     }
@@ -298,8 +358,12 @@ impl MatchOnServer {
             None => error!("Received data from disconnected player {ingame_id}"),
             Some(player) => {
                 if player.reported_ready {
-                    self.game_send(format!("recv {ingame_id} {line}")).await?
-                } else if line == "ready" {
+                    self.game_send(ControllerToGame::Recv {
+                        player: ingame_id,
+                        msg: line,
+                    })
+                    .await?
+                } else if line.parse() == Ok(BotToController::Ready) {
                     player.reported_ready = true;
                 } else {
                     self.handle_player_dropoff(
@@ -336,12 +400,12 @@ impl MatchOnServer {
                 }
                 assert!(self.all_players_ready());
                 self.ready_deadline = None;
-                self.game_send("start".to_owned()).await?;
+                self.game_send(ControllerToGame::Start).await?;
             }
         }
         while let Some(first) = self.game_timers.first() {
             if first.0 <= now {
-                self.game_send(format!("timeout {}", first.1)).await?;
+                self.game_send(ControllerToGame::Timeout(first.1)).await?;
                 self.game_timers.pop_first();
             } else {
                 break;
@@ -363,9 +427,7 @@ impl MatchOnServer {
         self.kick_player(ingame_id).await
     }
 
-    async fn game_to_player_send(&mut self, line: &str) -> anyhow::Result<()> {
-        let [player_str, rest] = textapi::split(line);
-        let id = player_str.parse::<usize>()?;
+    async fn game_to_player_send(&mut self, id: usize, rest: &str) -> anyhow::Result<()> {
         if id == 0 {
             return Err(anyhow!(
                 "Non-existent player id: {id} in 'send' from game server"
@@ -404,23 +466,11 @@ impl MatchOnServer {
         Ok(())
     }
 
-    async fn over(&mut self, line: &str) -> anyhow::Result<()> {
-        let mut it = line.splitn(self.players.len() + 1, ' ');
-        let mut scores = Vec::with_capacity(self.players.len());
-        for _ in 0..self.players.len() {
-            let Some(score) = it.next() else {
-                return Err(anyhow!("Game server returned not enough scores"));
-            };
-            let score = score
-                .parse()
-                .context("Failed to parse score from the 'over' command")?;
-            scores.push(score);
-        }
+    async fn over(&mut self, scores: Vec<f64>, reason: String) -> anyhow::Result<()> {
         let mut errors = vec![];
         for (i, pe) in self.player_errors.iter_mut().enumerate() {
             errors.extend(std::mem::take(pe).into_iter().map(|e| (i + 1, e)));
         }
-        let reason = it.next().unwrap_or_default().to_owned();
         self.state = State::Over(MatchResult {
             scores,
             reason,
@@ -429,30 +479,16 @@ impl MatchOnServer {
         Ok(())
     }
 
-    async fn set_timer(&mut self, line: &str) -> anyhow::Result<()> {
-        let [id_str, duration_str] = textapi::split(line);
-        let id = id_str.parse::<u32>()?;
+    async fn set_timer(&mut self, id: u32, duration: std::time::Duration) -> anyhow::Result<()> {
         if id == 0 {
             return Err(anyhow!("timer id should be > 0"));
         }
-        let duration_str = duration_str
-            .strip_suffix("ms")
-            .ok_or(anyhow!("timer duration does not end with 'ms'"))?;
-        let deadline = std::time::Instant::now()
-            + std::time::Duration::from_millis(
-                duration_str
-                    .parse()
-                    .context("parsing timer duration failed")?,
-            );
+        let deadline = std::time::Instant::now() + duration;
         self.game_timers.insert((deadline, id));
         Ok(())
     }
 
-    async fn playererror(&mut self, line: &str) -> anyhow::Result<()> {
-        let [player_id_str, rest] = textapi::split(line);
-        let ingame_id = player_id_str
-            .parse::<usize>()
-            .context("Failed to parse player number in 'playererror'")?;
+    async fn playererror(&mut self, ingame_id: usize, rest: &str) -> anyhow::Result<()> {
         log::trace!("Player {ingame_id} error reported: {rest}");
         self.add_player_error(ingame_id, rest);
         if self.kick_for_errors {
@@ -486,3 +522,69 @@ impl LogDirection {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, DuplexStream};
+
+    // The controller end of an in-memory pipe, and the agent end.
+    fn pipe() -> (LineStream, LineSink, DuplexStream) {
+        let (ours, theirs) = tokio::io::duplex(4096);
+        let (r, w) = tokio::io::split(ours);
+        (make_line_stream(r, 1024), make_line_sink(w, 1024), theirs)
+    }
+
+    // Plays a match with one bot, which is ready at once, and a game server
+    // that writes `script` only once it receives "start".
+    async fn run_with_script(declared: Option<u32>, script: &'static str) -> anyhow::Result<()> {
+        let (game_stream, game_sink, game) = pipe();
+        let (bot_stream, bot_sink, mut bot) = pipe();
+        let config = MatchConfig {
+            config: Config {
+                send_timeout: std::time::Duration::from_secs(5),
+                sender_open_timeout: std::time::Duration::from_secs(5),
+                player_ready_timeout: std::time::Duration::from_secs(5),
+                kick_for_errors: false,
+                max_player_errors: 10,
+                line_length_limit: 1024,
+            },
+            ios: vec![],
+            params: vec![],
+            game_log_sink: Box::new(tokio::io::sink()),
+            protocol_version: declared,
+        };
+        let mut m = MatchOnServer::new(config, game_stream, game_sink);
+        m.add_player(bot_stream, bot_sink);
+        bot.write_all(b"ready\n").await?;
+        tokio::spawn(async move {
+            let (r, mut w) = tokio::io::split(game);
+            let mut lines = tokio::io::BufReader::new(r).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line == "start" {
+                    w.write_all(script.as_bytes()).await.unwrap();
+                }
+            }
+        });
+        let _bot = bot;
+        m.run().await.map(|_| ())
+    }
+
+    #[tokio::test]
+    async fn late_proto_reply() {
+        // Declared version 1 is used until the answer upgrades it.
+        run_with_script(Some(1), "proto 2\ncanceltimer 5\nover 1 done\n")
+            .await
+            .unwrap();
+        assert!(run_with_script(Some(1), "canceltimer 5\nover 1 done\n")
+            .await
+            .is_err());
+        // Only the answer is accepted after "start".
+        assert!(run_with_script(Some(2), "proto 2\nproto 1\nover 1 done\n")
+            .await
+            .is_err());
+        assert!(run_with_script(None, "proto 2\nover 1 done\n")
+            .await
+            .is_err());
+    }
+}
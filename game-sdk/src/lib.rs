@@ -0,0 +1,250 @@
+// Library for writing Proglad game servers in Rust.
+//
+// A game server implements GameServer and calls `run`. The library reads the
+// commands sent by the controller, dispatches them to the GameServer and
+// provides a Controller handle to send commands back.
+use std::io::{BufRead, Write};
+use std::time::Duration;
+
+pub use proglad_api as api;
+pub use proglad_api::textapi::VisMode;
+use proglad_api::textapi::{
+    ControllerToGame, GameToController, ParseError, LEGACY_PROTOCOL_VERSION, PROTOCOL_VERSION,
+};
+
+pub trait GameServer {
+    // Called once with the param string configured for the game.
+    fn on_param(&mut self, _ctl: &mut Controller, _param: &str) {}
+    // Called once all the players are ready (or timed out getting ready).
+    fn on_start(&mut self, ctl: &mut Controller);
+    // Called for every line received from a player.
+    fn on_recv(&mut self, ctl: &mut Controller, player: usize, msg: &str);
+    // Called when a timer set with Controller::timer fires.
+    fn on_timeout(&mut self, _ctl: &mut Controller, _timer_id: u32) {}
+    // Called when the player is disconnected and will not send anything else.
+    fn on_dropped(&mut self, _ctl: &mut Controller, _player: usize) {}
+    // In VisMode::Standalone, called for every line of the match log.
+    fn on_replay_line(&mut self, _ctl: &mut Controller, _line: &str) {}
+}
+
+// The handle used to send commands to the controller.
+pub struct Controller {
+    out: Box<dyn Write>,
+    vis_mode: VisMode,
+    over: bool,
+    // None until "proto" was sent to the controller.
+    protocol_version: Option<u32>,
+}
+
+impl Controller {
+    pub fn new(out: impl Write + 'static) -> Self {
+        Self {
+            out: Box::new(out),
+            vis_mode: VisMode::None,
+            over: false,
+            protocol_version: None,
+        }
+    }
+
+    // The protocol version agreed with the controller.
+    pub fn protocol_version(&self) -> u32 {
+        self.protocol_version.unwrap_or(LEGACY_PROTOCOL_VERSION)
+    }
+
+    pub fn vis_mode(&self) -> VisMode {
+        self.vis_mode
+    }
+
+    // Whether the game server is expected to produce visualization events.
+    pub fn visualize(&self) -> bool {
+        self.vis_mode != VisMode::None
+    }
+
+    // True once `over` was sent; no other commands are accepted after that.
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn send(&mut self, player: usize, msg: impl std::fmt::Display) {
+        self.write(GameToController::Send {
+            player,
+            msg: msg.to_string(),
+        });
+    }
+
+    pub fn sendall(&mut self, msg: impl std::fmt::Display) {
+        self.write(GameToController::SendAll(msg.to_string()));
+    }
+
+    // Requests a "timeout" notification after the given duration.
+    // The id must be positive.
+    pub fn timer(&mut self, id: u32, duration: Duration) {
+        assert!(id > 0, "timer id should be > 0");
+        self.write(GameToController::Timer { id, duration });
+    }
+
+    // Cancels a timer set with `timer`. With controllers that only speak the
+    // legacy protocol this does nothing and the "timeout" still arrives.
+    pub fn cancel_timer(&mut self, id: u32) {
+        if self.protocol_version() >= 2 {
+            self.write(GameToController::CancelTimer(id));
+        }
+    }
+
+    pub fn playererror(&mut self, player: usize, msg: impl std::fmt::Display) {
+        self.write(GameToController::PlayerError {
+            player,
+            msg: msg.to_string(),
+        });
+    }
+
+    // Finishes the game. Scores are for players 1..=N in order.
+    pub fn over(&mut self, scores: &[f64], reason: impl std::fmt::Display) {
+        self.write(GameToController::Over {
+            scores: scores.to_vec(),
+            reason: reason.to_string(),
+        });
+        self.over = true;
+    }
+
+    // Emits a visualization event; see visualize.rs for the format.
+    pub fn vis(&mut self, event: impl std::fmt::Display) {
+        self.write(GameToController::Vis(event.to_string()));
+    }
+
+    fn negotiate(&mut self, controller_version: u32) {
+        let version = controller_version.min(PROTOCOL_VERSION);
+        self.write(GameToController::Proto(version));
+        self.protocol_version = Some(version);
+    }
+
+    fn write(&mut self, msg: GameToController) {
+        if self.over {
+            return;
+        }
+        // If the controller is gone, there is no one to report errors to.
+        let _ = writeln!(self.out, "{msg}");
+        let _ = self.out.flush();
+    }
+}
+
+// Runs the game server on stdin/stdout until the game is over or the input ends.
+pub fn run<G: GameServer>(game: &mut G) -> std::io::Result<()> {
+    run_with(game, std::io::stdin().lock(), std::io::stdout())
+}
+
+// Fails if the input can't be read or has a malformed command.
+pub fn run_with<G: GameServer>(
+    game: &mut G,
+    input: impl BufRead,
+    output: impl Write + 'static,
+) -> std::io::Result<()> {
+    let mut ctl = Controller::new(output);
+    for line in input.lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if ctl.vis_mode == VisMode::Standalone && !line.starts_with("param ") {
+            game.on_replay_line(&mut ctl, line);
+            continue;
+        }
+        match line.parse::<ControllerToGame>() {
+            // Controllers only send "proto" if the game config declares a
+            // version, older ones don't know the command at all.
+            Ok(ControllerToGame::Proto(version)) => ctl.negotiate(version),
+            Ok(ControllerToGame::Vis(mode)) => ctl.vis_mode = mode,
+            Ok(ControllerToGame::Param(param)) => game.on_param(&mut ctl, &param),
+            Ok(ControllerToGame::Start) => game.on_start(&mut ctl),
+            Ok(ControllerToGame::Recv { player, msg }) => game.on_recv(&mut ctl, player, &msg),
+            Ok(ControllerToGame::Timeout(id)) => game.on_timeout(&mut ctl, id),
+            Ok(ControllerToGame::Dropped(player)) => game.on_dropped(&mut ctl, player),
+            // Newer controllers may send commands this version does not know about.
+            Err(ParseError::UnknownCommand(_)) => {}
+            Err(e) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Failed to parse '{line}': {e}"),
+                ))
+            }
+        }
+        if ctl.over {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct Echo {
+        param: String,
+    }
+
+    impl GameServer for Echo {
+        fn on_param(&mut self, _ctl: &mut Controller, param: &str) {
+            self.param = param.to_owned();
+        }
+        fn on_start(&mut self, ctl: &mut Controller) {
+            ctl.sendall(&self.param);
+            ctl.timer(1, Duration::from_millis(100));
+            ctl.timer(2, Duration::from_millis(200));
+            ctl.cancel_timer(2);
+        }
+        fn on_recv(&mut self, ctl: &mut Controller, player: usize, msg: &str) {
+            ctl.send(player, msg);
+        }
+        fn on_timeout(&mut self, ctl: &mut Controller, timer_id: u32) {
+            ctl.over(&[1.0, 0.5], format_args!("timer {timer_id}"));
+        }
+    }
+
+    #[test]
+    fn dispatches_commands() {
+        let input = "vis inline\nparam a b\nstart\nrecv 2 hello there\ntimeout 1\nrecv 1 late\n";
+        let out = SharedBuf::default();
+        run_with(&mut Echo::default(), input.as_bytes(), out.clone()).unwrap();
+        let out = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        // Without "proto" from the controller the legacy protocol is used.
+        assert_eq!(
+            out,
+            "sendall a b\ntimer 1 100ms\ntimer 2 200ms\nsend 2 hello there\nover 1 0.5 timer 1\n"
+        );
+    }
+
+    #[test]
+    fn negotiates_protocol_version() {
+        let input = "proto 1\nvis inline\nparam a b\nstart\ntimeout 1\n";
+        let out = SharedBuf::default();
+        run_with(&mut Echo::default(), input.as_bytes(), out.clone()).unwrap();
+        let out = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(
+            out,
+            "proto 1\nsendall a b\ntimer 1 100ms\ntimer 2 200ms\nover 1 0.5 timer 1\n"
+        );
+    }
+
+    #[test]
+    fn malformed_commands_are_errors() {
+        let input = "vis inline\nparam a b\nfrobnicate\nstart\nrecv x hello\ntimeout 1\n";
+        let out = SharedBuf::default();
+        let err = run_with(&mut Echo::default(), input.as_bytes(), out.clone()).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+        let out = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(out, "sendall a b\ntimer 1 100ms\ntimer 2 200ms\n");
+    }
+}
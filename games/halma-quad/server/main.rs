@@ -1,122 +1,67 @@
 // It is OK to panic if the format of the data supplied by controller is wrong.
-use std::io::Write;
-
-fn main() {
-    let mut stdin = std::io::stdin().lock();
-    let stdout = std::io::stdout();
-    let mut buf = String::new();
-    stdin.read_line(&mut buf).unwrap();
-    let mut parts = buf.split_ascii_whitespace();
-    let mut visualize = false;
-    let mut inlinevisualize = false;
-    match (parts.next(), parts.next()) {
-        (Some("vis"), Some("inline")) => {
-            inlinevisualize = true;
-        }
-        (Some("vis"), Some("standalone")) => {
-            visualize = true;
-        }
-        _ => {}
-    }
-    stdin.read_line(&mut buf).unwrap();  // Skip "param".
-    if visualize {
-        visualizer::visualize();
-        return;
-    }
-    let mut h = Handler::new(stdout, inlinevisualize);
-    loop {
-        buf.clear();
-        match stdin.read_line(&mut buf) {
-            Ok(0) => break,
-            Ok(_) => {
-                if !h.handle_line(&buf) {
-                    break;
-                }
-            }
-            Err(e) => panic!("Failed to read from stdin: {e}"),
-        }
-    }
+use std::time::Duration;
+
+use proglad_game::{Controller, GameServer};
+
+fn main() -> std::io::Result<()> {
+    proglad_game::run(&mut Halma::new())
 }
 
-struct Handler<W> {
-    out: W,
+struct Halma {
     game: Game,
-    max_time_per_move_ms: u32,
+    max_time_per_move: Duration,
     timer_id: u32,
     visualizer: Option<visualizer::VHandler>,
 }
 
-impl<W: Write> Handler<W> {
-    fn new(out: W, visualize: bool) -> Self {
-        let visualizer = if visualize {
-            Some(visualizer::VHandler::new())
-        } else {
-            None
-        };
+impl Halma {
+    fn new() -> Self {
         Self {
-            out,
             game: Game::default(),
-            max_time_per_move_ms: 100,
+            max_time_per_move: Duration::from_millis(100),
             timer_id: 0,
-            visualizer,
-        }
-    }
-    fn handle_line(&mut self, line: &str) -> bool {
-        let mut it = line.split_ascii_whitespace();
-        match it.next().unwrap() {
-            "start" => {
-                self.visualizer
-                    .as_mut()
-                    .map(|v| v.handle_start(&mut self.out));
-                for p in self.game.players_alive.iter() {
-                    wln!(self.out, "send {p} start {p}");
-                }
-                wln!(self.out, "send 1 yourmove");
-                self.timer_id += 1;
-                wln!(
-                    self.out,
-                    "timer {} {}ms",
-                    self.timer_id,
-                    self.max_time_per_move_ms
-                );
-            }
-            "recv" => {
-                let from = it.next().unwrap().parse::<u8>().unwrap();
-                self.handle_recv(from, it);
-            }
-            "timeout" => {
-                let id = it.next().unwrap().parse::<u32>().unwrap();
-                self.handle_timeout(id);
-            }
-            "dropped" => {
-                let player = it.next().unwrap().parse::<u8>().unwrap();
-                self.handle_dropped(player);
-            }
-            "over" => {
-                return false;
-            }
-            cmd => panic!("Unrecognized command {cmd}"),
+            visualizer: None,
         }
-        true
     }
-    fn handle_recv<'a, I: Iterator<Item = &'a str>>(&mut self, player: u8, mut it: I) {
+}
+
+impl GameServer for Halma {
+    fn on_param(&mut self, ctl: &mut Controller, _param: &str) {
+        if ctl.visualize() {
+            self.visualizer = Some(visualizer::VHandler::new());
+        }
+    }
+    fn on_start(&mut self, ctl: &mut Controller) {
+        if let Some(v) = self.visualizer.as_mut() {
+            v.handle_start(ctl);
+        }
+        for p in self.game.players_alive.iter() {
+            ctl.send(*p as usize, format_args!("start {p}"));
+        }
+        ctl.send(1, "yourmove");
+        self.timer_id += 1;
+        ctl.timer(self.timer_id, self.max_time_per_move);
+    }
+    fn on_recv(&mut self, ctl: &mut Controller, player: usize, msg: &str) {
+        let player = player as u8;
         if player != self.game.current_player {
-            self.handle_player_error(player, "not your move");
+            self.handle_player_error(ctl, player, "not your move");
             self.game.remove_player(player);
-            self.check_gameover();
+            self.check_gameover(ctl);
             return;
         }
+        let mut it = msg.split_ascii_whitespace();
         match it.next() {
-            None => wln!(self.out, "playererror {player} no command"),
+            None => ctl.playererror(player as usize, "no command"),
             Some("move") => {
                 let parts: Result<Vec<i32>, std::num::ParseIntError> =
                     it.map(|s| s.parse::<i32>()).collect();
                 let Ok(parts) = parts else {
-                    self.handle_player_error(player, "failed to parse int");
+                    self.handle_player_error(ctl, player, "failed to parse int");
                     return;
                 };
                 if parts.len() % 2 != 0 {
-                    self.handle_player_error(player, "odd number of coordinates");
+                    self.handle_player_error(ctl, player, "odd number of coordinates");
                     return;
                 }
                 let hops: Vec<Coord> = parts
@@ -126,78 +71,80 @@ impl<W: Write> Handler<W> {
                     .map(|(x, y)| Coord::new(*x, *y))
                     .collect();
                 if !self.game.try_move(&hops) {
-                    self.handle_player_error(player, "invalid move");
+                    self.handle_player_error(ctl, player, "invalid move");
                     return;
                 }
-                self.visualizer
-                    .as_mut()
-                    .map(|v| v.handle_move_hops(&mut self.out, hops.iter().cloned()));
-                w!(self.out, "sendall move");
+                if let Some(v) = self.visualizer.as_mut() {
+                    v.handle_move_hops(ctl, hops.iter().cloned());
+                }
+                let mut msg = "move".to_owned();
                 for hop in hops {
-                    w!(self.out, " {} {}", hop.x, hop.y);
+                    msg.push_str(&format!(" {} {}", hop.x, hop.y));
                 }
-                wln!(self.out);
+                ctl.sendall(msg);
                 let blockers = self.game.check_and_remove_blockers();
                 for b in blockers {
-                    wln!(self.out, "playererror {b} blocking another players home");
+                    ctl.playererror(b as usize, "blocking another players home");
                 }
-                if self.check_gameover() {
+                if self.check_gameover(ctl) {
                     return;
                 }
                 self.timer_id += 1;
-                wln!(
-                    self.out,
-                    "timer {} {}ms",
-                    self.timer_id,
-                    self.max_time_per_move_ms
-                );
-                wln!(self.out, "send {} yourmove", self.game.current_player);
+                ctl.timer(self.timer_id, self.max_time_per_move);
+                ctl.send(self.game.current_player as usize, "yourmove");
             }
-            Some(_) => self.handle_player_error(player, "invalid command"),
+            Some(_) => self.handle_player_error(ctl, player, "invalid command"),
         }
     }
-    fn handle_player_error(&mut self, player: u8, error: &str) {
-        wln!(self.out, "playererror {player} {error}");
-        self.game.remove_player(player);
-        self.check_gameover();
-    }
-    fn handle_timeout(&mut self, id: u32) {
+    fn on_timeout(&mut self, ctl: &mut Controller, id: u32) {
         if id == self.timer_id {
             self.game.remove_player(self.game.current_player);
-            self.check_gameover();
+            self.check_gameover(ctl);
         }
     }
-    fn handle_dropped(&mut self, player: u8) {
+    fn on_dropped(&mut self, ctl: &mut Controller, player: usize) {
+        self.game.remove_player(player as u8);
+        self.check_gameover(ctl);
+    }
+    fn on_replay_line(&mut self, ctl: &mut Controller, line: &str) {
+        if let Some(v) = self.visualizer.as_mut() {
+            v.handle_line(ctl, line);
+        }
+    }
+}
+
+impl Halma {
+    fn handle_player_error(&mut self, ctl: &mut Controller, player: u8, error: &str) {
+        ctl.playererror(player as usize, error);
         self.game.remove_player(player);
-        self.check_gameover();
+        self.check_gameover(ctl);
     }
-    fn check_gameover(&mut self) -> bool {
+    fn check_gameover(&mut self, ctl: &mut Controller) -> bool {
         match &self.game.status {
             GameStatus::Ongoing => false,
             GameStatus::Won(p, reason) => {
                 if *p == 1 {
-                    wln!(self.out, "over 2 0 {reason}");
+                    ctl.over(&[2., 0.], reason);
                 } else {
-                    wln!(self.out, "over 0 2 {reason}");
+                    ctl.over(&[0., 2.], reason);
                 }
                 true
             }
             GameStatus::Drawn(reason) => {
-                wln!(self.out, "over 1 1 {reason}");
+                ctl.over(&[1., 1.], reason);
                 true
             }
         }
     }
 }
 
-use std::{collections::HashMap, io::BufRead};
+use std::collections::HashMap;
 
 type Map = HashMap<Coord, Cell>;
 
 fn create_map() -> Map {
     // TODO: multiplayer
     let side_size = 16;
-    let zero = Coord::new(0, 0);
     let mut m = Map::new();
     for x in 1..=side_size {
         for y in 1..=side_size {
@@ -437,7 +384,7 @@ impl Game {
             let Some(player) = c.get_player() else {
                 continue;
             };
-            if c.get_block_zone().map_or(false, |h| h != player) && !blockers.contains(&player) {
+            if c.get_block_zone().is_some_and(|h| h != player) && !blockers.contains(&player) {
                 blockers.push(player);
             }
         }
@@ -451,31 +398,10 @@ impl Game {
     }
 
     fn cell_free(&self, pos: Coord) -> bool {
-        self.map
-            .get(&pos)
-            .map_or(false, |h| h.get_player().is_none())
+        self.map.get(&pos).is_some_and(|h| h.get_player().is_none())
     }
 }
 
-#[macro_export]
-macro_rules! wln {
-    ($o:expr) => {{
-        let _ = writeln!($o);
-        let _ = $o.flush();
-    }};
-    ($o:expr, $($es:expr),+) => {{
-        let _ = writeln!($o, $($es),+);
-        let _ = $o.flush();
-    }}
-}
-
-#[macro_export]
-macro_rules! w {
-    ($($es:expr),+) => {{
-        let _ = write!($($es),+);
-    }}
-}
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Ord, PartialOrd)]
 struct Coord {
     x: i32,
@@ -489,7 +415,7 @@ impl Coord {
     fn distance(&self, c: Coord) -> i32 {
         (self.x - c.x).abs().max((self.y - c.y).abs())
     }
-    pub fn to_pixel(&self, s: f32) -> (f32, f32) {
+    pub fn to_pixel(self, s: f32) -> (f32, f32) {
         (
             0.5 + (-8.5 + self.x as f32) * s,
             0.5 + (-8.5 + self.y as f32) * s,
@@ -526,15 +452,15 @@ impl std::ops::Mul<i32> for Coord {
 }
 
 mod visualizer {
-    use super::{create_map, w, wln, Coord};
+    use super::{create_map, Coord};
+    use proglad_game::api::visualize::Color;
+    use proglad_game::Controller;
     use std::collections::HashMap;
-    use std::io::{BufRead, Write};
     pub struct VHandler {
         quad_size: f32,
-        map: Vec<Coord>,
         piece_id: HashMap<Coord, u64>,
         piece_player: HashMap<u64, u8>,
-        player_color: Vec<(f32, f32, f32, f32)>,
+        player_color: Vec<Color>,
         hop_duration: f32,
         time: f32,
     }
@@ -546,17 +472,16 @@ mod visualizer {
                 .filter_map(|(&h, &c)| c.get_player().map(|p| (h, p)))
                 .enumerate()
                 .map(|(i, x)| (1 + i as u64, x));
-            let map = map.iter().map(|(h, _)| *h).collect::<Vec<_>>();
             let piece_id =
                 HashMap::<Coord, u64>::from_iter(pieces.clone().map(|(i, (h, _))| (h, i)));
             let piece_player = HashMap::<u64, u8>::from_iter(pieces.map(|(i, (_, p))| (i, p)));
+            let color = |r, g, b, a| Color { r, g, b, a };
             let player_color = vec![
-                (0.5, 0.5, 0.5, 0.5),
-                (0.8, 0.2, 0.2, 1.),
-                (0.2, 0.8, 0.2, 1.),
+                color(0.5, 0.5, 0.5, 0.5),
+                color(0.8, 0.2, 0.2, 1.),
+                color(0.2, 0.8, 0.2, 1.),
             ];
             Self {
-                map,
                 piece_id,
                 piece_player,
                 player_color,
@@ -565,49 +490,43 @@ mod visualizer {
                 time: 0.0,
             }
         }
-        fn handle_line<W: Write>(&mut self, out: &mut W, line: &str) -> bool {
+        pub fn handle_line(&mut self, ctl: &mut Controller, line: &str) {
             let parts = line.split_ascii_whitespace().collect::<Vec<_>>();
             if parts.len() < 3 {
-                return true;
+                return;
             }
             match (parts[1], parts[2]) {
-                (">", "start") => self.handle_start(out),
-                ("<", "sendall") => {
-                    if parts[3] == "move" {
-                        self.handle_move_str(out, parts);
-                    }
+                (">", "start") => self.handle_start(ctl),
+                ("<", "sendall") if parts.get(3) == Some(&"move") => {
+                    self.handle_move_str(ctl, parts);
                 }
                 _ => {}
             }
-            true
         }
-        pub fn handle_move_hops<W: Write, I: Iterator<Item = Coord>>(
+        pub fn handle_move_hops<I: Iterator<Item = Coord>>(
             &mut self,
-            out: &mut W,
+            ctl: &mut Controller,
             mut hops: I,
         ) {
             let first = hops.next().unwrap();
-            let id = self.piece_id.get(&first).unwrap();
+            let id = *self.piece_id.get(&first).unwrap();
             let mut prev = first.to_pixel(self.quad_size);
             let mut last = first;
             for h in hops {
                 last = h;
                 let cur = h.to_pixel(self.quad_size);
                 let (dx, dy) = (cur.0 - prev.0, cur.1 - prev.1);
-
-                wln!(
-                    out,
-                    r#"vis {{"t":{},"transform":{{"id":{id},"d":{},"mv":[{dx},{dy}]}}}}"#,
-                    self.time,
-                    self.hop_duration
-                );
+                ctl.vis(format_args!(
+                    r#"{{"t":{},"transform":{{"id":{id},"d":{},"mv":[{dx},{dy}]}}}}"#,
+                    self.time, self.hop_duration
+                ));
                 self.time += self.hop_duration;
                 prev = cur;
             }
-            self.piece_id.insert(last, *id);
+            self.piece_id.insert(last, id);
             self.piece_id.remove(&first);
         }
-        fn handle_move_str<W: Write>(&mut self, out: &mut W, parts: Vec<&str>) {
+        fn handle_move_str(&mut self, ctl: &mut Controller, parts: Vec<&str>) {
             let coords = parts[4..]
                 .iter()
                 .map(|p| p.parse().unwrap())
@@ -617,28 +536,19 @@ mod visualizer {
                 .step_by(2)
                 .zip(coords.iter().skip(1).step_by(2))
                 .map(|(x, y)| Coord::new(*x, *y));
-            self.handle_move_hops(out, hops);
-        }
-        fn format_color((r, g, b, a): (f32, f32, f32, f32)) -> String {
-            let u = |c: f32| -> u8 {
-                (c.clamp(0., 1.) * 255.) as u8
-            };
-            format!("{:02x}{:02x}{:02x}{:02x}", u(r), u(g), u(b), u(a))
+            self.handle_move_hops(ctl, hops);
         }
-        pub fn handle_start<W: Write>(&mut self, out: &mut W) {
+        pub fn handle_start(&mut self, ctl: &mut Controller) {
             for (h, id) in self.piece_id.iter() {
                 let p = self.piece_player.get(id).unwrap();
-                let position = h.to_pixel(self.quad_size);
-                let (px, py) = (position.0, position.1);
-                let color = self.player_color.get(*p as usize).unwrap().clone();
-                w!(out, r#"vis {{"t":{},"create":{{"id":{id},"z":2,"p":[{px},{py}],"geom":["#, self.time);
-                wln!(
-                    out,
-                    r#"{{"circle":{{"r":{},"f":"{}","t":{}}}}}]}}}}"#,
+                let (px, py) = h.to_pixel(self.quad_size);
+                let color = self.player_color[*p as usize];
+                ctl.vis(format_args!(
+                    r#"{{"t":{},"create":{{"id":{id},"z":2,"p":[{px},{py}],"geom":[{{"circle":{{"r":{},"f":"{color}","t":{}}}}}]}}}}"#,
+                    self.time,
                     self.quad_size * 0.4,
-                    Self::format_color(color),
                     self.quad_size * 0.05
-                );
+                ));
             }
             let mut lines = vec![];
 
@@ -652,7 +562,7 @@ mod visualizer {
                 lines.push(((x0, c), (x1, c)));
                 lines.push(((c, y0), (c, y1)));
             }
-            write_batched_lines(out, 10000, lines, 0.002, "000000ff", self.time);
+            write_batched_lines(ctl, 10000, lines, 0.002, "000000ff", self.time);
 
             let mut lines = vec![];
             lines.push(((x0, y0), (x0 + 5. * self.quad_size, y0)));
@@ -669,9 +579,9 @@ mod visualizer {
                 lines.push(((cur.0, prev.1), cur));
                 prev = cur;
             }
-            let inv_lines = lines.iter().copied().map(|((x0, y0), (x1, y1))| ((1.0-x0, 1.0-y0), (1.0-x1, 1.0-y1))).collect::<Vec<_>>();
-            write_batched_lines(out, 20000, lines, 0.008, "00ff007f", self.time);
-            write_batched_lines(out, 30000, inv_lines, 0.008, "ff0000ff", self.time);
+            let inv_lines = invert(&lines);
+            write_batched_lines(ctl, 20000, lines, 0.008, "00ff007f", self.time);
+            write_batched_lines(ctl, 30000, inv_lines, 0.008, "ff0000ff", self.time);
 
             let mut lines = vec![];
             lines.push(((x0, y0), (x0 + 7. * self.quad_size, y0)));
@@ -688,49 +598,42 @@ mod visualizer {
                 lines.push(((cur.0, prev.1), cur));
                 prev = cur;
             }
-            let inv_lines = lines.iter().copied().map(|((x0, y0), (x1, y1))| ((1.0-x0, 1.0-y0), (1.0-x1, 1.0-y1))).collect::<Vec<_>>();
-            write_batched_lines(out, 40000, lines, 0.008, "0000007f", self.time);
-            write_batched_lines(out, 50000, inv_lines, 0.008, "0000007f", self.time);
-        }
-    }
-    pub fn visualize() {
-        let mut stdin = std::io::stdin().lock();
-        let mut stdout = std::io::stdout();
-        let mut buf = String::new();
-        let mut h = VHandler::new();
-        loop {
-            buf.clear();
-            match stdin.read_line(&mut buf) {
-                Ok(0) => break,
-                Ok(_) => {
-                    if !h.handle_line(&mut stdout, &buf) {
-                        break;
-                    }
-                }
-                Err(e) => panic!("Failed to read from stdin: {e}"),
-            }
+            let inv_lines = invert(&lines);
+            write_batched_lines(ctl, 40000, lines, 0.008, "0000007f", self.time);
+            write_batched_lines(ctl, 50000, inv_lines, 0.008, "0000007f", self.time);
         }
     }
-    fn write_batched_lines<W: Write>(
-        out: &mut W,
+    type Line = ((f32, f32), (f32, f32));
+    fn invert(lines: &[Line]) -> Vec<Line> {
+        lines
+            .iter()
+            .copied()
+            .map(|((x0, y0), (x1, y1))| ((1.0 - x0, 1.0 - y0), (1.0 - x1, 1.0 - y1)))
+            .collect()
+    }
+    fn write_batched_lines(
+        ctl: &mut Controller,
         base_id: usize,
-        lines: Vec<((f32, f32), (f32, f32))>,
+        lines: Vec<Line>,
         thickness: f32,
         color: &str,
         time: f32,
     ) {
         const LINES_PER_BATCH: usize = 10;
-        for batch in 0..(lines.len() + LINES_PER_BATCH - 1) / LINES_PER_BATCH {
-            let batch_start = batch * LINES_PER_BATCH;
-            let batch_end = ((batch + 1) * LINES_PER_BATCH).min(lines.len());
-            w!(out, r#"vis {{"t":{time},"create":{{"id":{},"geom":["#, base_id + batch);
-            for (idx, ((x1, y1), (x2, y2))) in lines[batch_start..batch_end].iter().enumerate() {
-                if idx > 0 {
-                    w!(out, ",");
-                }
-                w!(out, r#"{{"line":{{"p1":[{x1},{y1}],"p2":[{x2},{y2}],"t":{thickness},"s":"{color}"}}}}"#);
-            }
-            wln!(out, "]}}}}");
+        for (batch, chunk) in lines.chunks(LINES_PER_BATCH).enumerate() {
+            let geom = chunk
+                .iter()
+                .map(|((x1, y1), (x2, y2))| {
+                    format!(
+                        r#"{{"line":{{"p1":[{x1},{y1}],"p2":[{x2},{y2}],"t":{thickness},"s":"{color}"}}}}"#
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+            ctl.vis(format_args!(
+                r#"{{"t":{time},"create":{{"id":{},"geom":[{geom}]}}}}"#,
+                base_id + batch
+            ));
         }
     }
 }
@@ -1,163 +1,136 @@
 use std::collections::HashMap;
-use std::io::{BufRead, Write};
+use std::time::Duration;
 
-struct Handler<W> {
-    out: W,
+use proglad_game::{Controller, GameServer};
+
+struct LowestUnique {
     num_players: usize,
     num_options: usize,
-    want_visualize: bool,
     visualizer: Option<visualizer::Visualizer>,
     players_alive: Vec<usize>,
     scores: Vec<f64>,
     has_moved: Vec<Option<usize>>,
-    max_time_per_move_ms: i64,
-    timer_id: usize,
+    max_time_per_move: Duration,
+    timer_id: u32,
     turn: usize,
     max_turn: usize,
 }
 
-impl<W: Write> Handler<W> {
-    fn new(out: W) -> Self {
+impl LowestUnique {
+    fn new() -> Self {
         Self {
-            out,
             num_players: 0,
             num_options: 0,
             visualizer: None,
             players_alive: vec![],
             scores: vec![],
             has_moved: vec![],
-            max_time_per_move_ms: 1000,
+            max_time_per_move: Duration::from_millis(1000),
             timer_id: 0,
             turn: 0,
             max_turn: 0,
-            want_visualize: false,
         }
     }
-    fn handle_line(&mut self, line: &str) -> bool {
-        let mut it = line.split_ascii_whitespace();
-        match it.next().unwrap() {
-            "vis" => {
-                match it.next() {
-                    Some("inline") => self.want_visualize = true,
-                    _ => {}
-                }
-            }
-            "param" => {
-                self.num_players = it.next().unwrap().parse().unwrap();
-                self.num_options = it.next().map_or(5, |x| x.parse().unwrap());
-                self.max_turn = it.next().map_or(500, |x| x.parse().unwrap());
-                self.players_alive = (1..=self.num_players).collect();
-                self.has_moved = vec![None; 1 + self.num_players];
-                self.scores = vec![0.; 1 + self.num_players];
-                if self.want_visualize {
-                    self.visualizer = Some(visualizer::Visualizer::new(
-                        self.num_players,
-                        self.num_options,
-                    ));
-                }
-            }
-            "start" => {
-                self.visualizer
-                    .as_mut()
-                    .map(|v| v.handle_start(&mut self.out));
-                for p in self.players_alive.iter() {
-                    wln!(
-                        self.out,
-                        "send {p} start {} {p} {} {}",
-                        self.num_players,
-                        self.num_options,
-                        self.max_turn
-                    );
-                }
-                self.start_move();
-            }
-            "recv" => {
-                let from = it.next().unwrap().parse::<usize>().unwrap();
-                self.handle_recv(from, it);
-            }
-            "timeout" => {
-                let id = it.next().unwrap().parse::<usize>().unwrap();
-                self.handle_timeout(id);
-            }
-            "dropped" => {
-                let player = it.next().unwrap().parse::<usize>().unwrap();
-                self.handle_dropped(player);
-            }
-            "over" => {
-                return false;
-            }
-            cmd => panic!("Unrecognized command {cmd}"),
+}
+
+impl GameServer for LowestUnique {
+    fn on_param(&mut self, ctl: &mut Controller, param: &str) {
+        let mut it = param.split_ascii_whitespace();
+        self.num_players = it.next().unwrap().parse().unwrap();
+        self.num_options = it.next().map_or(5, |x| x.parse().unwrap());
+        self.max_turn = it.next().map_or(500, |x| x.parse().unwrap());
+        self.players_alive = (1..=self.num_players).collect();
+        self.has_moved = vec![None; 1 + self.num_players];
+        self.scores = vec![0.; 1 + self.num_players];
+        if ctl.visualize() {
+            self.visualizer = Some(visualizer::Visualizer::new(
+                self.num_players,
+                self.num_options,
+            ));
         }
-        true
     }
-    fn start_move(&mut self) {
-        wln!(self.out, "sendall yourmove");
-        self.timer_id += 1;
-        wln!(
-            self.out,
-            "timer {} {}ms",
-            self.timer_id,
-            self.max_time_per_move_ms
-        );
+    fn on_start(&mut self, ctl: &mut Controller) {
+        if let Some(v) = self.visualizer.as_mut() {
+            v.handle_start(ctl);
+        }
+        for p in self.players_alive.iter() {
+            ctl.send(
+                *p,
+                format_args!(
+                    "start {} {p} {} {}",
+                    self.num_players, self.num_options, self.max_turn
+                ),
+            );
+        }
+        self.start_move(ctl);
     }
-    fn handle_recv<'a>(&mut self, from: usize, mut rest: impl Iterator<Item = &'a str>) {
+    fn on_recv(&mut self, ctl: &mut Controller, from: usize, msg: &str) {
         if !self.players_alive.contains(&from) {
             return;
         }
         if let Some(Some(_)) = self.has_moved.get(from) {
-            self.remove_player(from, "already moved this turn");
+            self.remove_player(ctl, from, "already moved this turn");
             return;
         }
+        let mut rest = msg.split_ascii_whitespace();
         let (Some(mv), None) = (rest.next(), rest.next()) else {
-            self.remove_player(from, "trailing data received");
+            self.remove_player(ctl, from, "trailing data received");
             return;
         };
         match mv.parse::<usize>() {
             Err(_) => {
-                self.remove_player(from, "failed to parse move");
+                self.remove_player(ctl, from, "failed to parse move");
             }
             Ok(mv) => {
                 if !(1..=self.num_options).contains(&mv) {
-                    self.remove_player(from, "move value out of range");
+                    self.remove_player(ctl, from, "move value out of range");
                 } else {
                     self.has_moved[from] = Some(mv);
                 }
             }
         }
-        self.check_turn();
+        self.check_turn(ctl);
     }
-    fn handle_dropped(&mut self, player: usize) {
+    fn on_dropped(&mut self, ctl: &mut Controller, player: usize) {
         if !self.players_alive.contains(&player) {
             return;
         }
         self.players_alive.retain(|x| *x != player);
-        self.check_turn();
+        self.check_turn(ctl);
     }
-    fn handle_timeout(&mut self, id: usize) {
+    fn on_timeout(&mut self, ctl: &mut Controller, id: u32) {
         if self.timer_id != id {
             return;
         }
         let mut remove = vec![];
         for &p in self.players_alive.iter() {
-            if !self.has_moved[p].is_some() {
+            if self.has_moved[p].is_none() {
                 remove.push(p);
             }
         }
         for p in remove {
-            self.remove_player(p, "move timeout");
+            self.remove_player(ctl, p, "move timeout");
         }
-        self.check_turn();
+        self.check_turn(ctl);
     }
-    fn remove_player(&mut self, player: usize, reason: &str) {
+}
+
+impl LowestUnique {
+    fn start_move(&mut self, ctl: &mut Controller) {
+        ctl.sendall("yourmove");
+        self.timer_id += 1;
+        ctl.timer(self.timer_id, self.max_time_per_move);
+    }
+    fn remove_player(&mut self, ctl: &mut Controller, player: usize, reason: &str) {
         if !self.players_alive.contains(&player) {
             return;
         }
         self.players_alive.retain(|x| *x != player);
-        wln!(self.out, "playererror {player} {reason}");
+        ctl.playererror(player, reason);
     }
-    fn check_turn(&mut self) {
+    fn check_turn(&mut self, ctl: &mut Controller) {
         if self.players_alive.is_empty() {
-            self.game_over("no players alive");
+            self.game_over(ctl, "no players alive");
             return;
         }
         if !self
@@ -172,12 +145,7 @@ impl<W: Write> Handler<W> {
             let Some(mv) = mv else { continue };
             *mvs.entry(*mv).or_default() += 1;
         }
-        let winning_len = mvs
-            .iter()
-            .map(|(_, players)| players)
-            .cloned()
-            .min()
-            .unwrap();
+        let winning_len = mvs.values().cloned().min().unwrap();
         let (winning_num, winners_count) = mvs
             .iter()
             .filter(|(_, players)| **players == winning_len)
@@ -196,65 +164,43 @@ impl<W: Write> Handler<W> {
                 }
             })
             .collect::<Vec<_>>();
-        self.visualizer
-            .as_mut()
-            .map(|v| v.handle_move(&mut self.out, &self.has_moved, Some(*winning_num), &winners));
+        if let Some(v) = self.visualizer.as_mut() {
+            v.handle_move(ctl, &self.has_moved, &winners);
+        }
 
         let delta = 1.0 / (*winners_count as f64);
         for w in winners {
             self.scores[w] += delta;
         }
-        w!(&mut self.out, "sendall move {winning_num}");
+        let mut msg = format!("move {winning_num}");
         for i in 1..=self.num_players {
-            match self.has_moved[i] {
-                Some(mv) => w!(&mut self.out, " {mv}"),
-                None => w!(&mut self.out, " 0"),
-            }
+            msg.push_str(&format!(" {}", self.has_moved[i].unwrap_or(0)));
         }
-        wln!(&mut self.out);
-        self.next_turn();
+        ctl.sendall(msg);
+        self.next_turn(ctl);
     }
-    fn next_turn(&mut self) {
+    fn next_turn(&mut self, ctl: &mut Controller) {
         self.has_moved.iter_mut().for_each(|x| *x = None);
         self.turn += 1;
         if self.turn == self.max_turn {
-            self.game_over("turn limit reached");
+            self.game_over(ctl, "turn limit reached");
         } else {
-            self.start_move();
+            self.start_move(ctl);
         }
     }
-    fn game_over(&mut self, reason: &str) {
-        w!(&mut self.out, "over");
-        for score in self.scores[1..].iter() {
-            w!(&mut self.out, " {}", score);
-        }
-        wln!(&mut self.out, " {reason}");
+    fn game_over(&mut self, ctl: &mut Controller, reason: &str) {
+        ctl.over(&self.scores[1..], reason);
     }
 }
 
-fn main() {
-    let args = std::env::args().collect::<Vec<_>>();
-    let mut stdin = std::io::stdin().lock();
-    let stdout = std::io::stdout();
-    let mut buf = String::new();
-    let mut h = Handler::new(stdout);
-    loop {
-        buf.clear();
-        match stdin.read_line(&mut buf) {
-            Ok(0) => break,
-            Ok(_) => {
-                if !h.handle_line(&buf) {
-                    break;
-                }
-            }
-            Err(e) => panic!("Failed to read from stdin: {e}"),
-        }
-    }
+fn main() -> std::io::Result<()> {
+    proglad_game::run(&mut LowestUnique::new())
 }
 
 mod visualizer {
-    use crate::{w, wln};
-    use std::io::Write;
+    use proglad_game::api::visualize::Color;
+    use proglad_game::Controller;
+
     pub struct Visualizer {
         num_players: usize,
         num_options: usize,
@@ -262,7 +208,7 @@ mod visualizer {
         next_id: u64,
         ids: Vec<u64>,
         positions: Vec<(f32, f32)>,
-        player_color: Vec<(f32, f32, f32, f32)>,
+        player_color: Vec<Color>,
         delta_x: f32,
         delta_y: f32,
         radius: f32,
@@ -270,17 +216,18 @@ mod visualizer {
 
     impl Visualizer {
         pub fn new(num_players: usize, num_options: usize) -> Self {
+            let color = |r, g, b, a| Color { r, g, b, a };
             let player_color = vec![
-                (0.5, 0.5, 0.5, 0.5),
-                (0.8, 0.2, 0.2, 1.),
-                (0.2, 0.8, 0.2, 1.),
-                (0.2, 0.2, 0.8, 1.),
-                (0.2, 0.6, 0.6, 1.),
-                (0.6, 0.2, 0.6, 1.),
-                (0.6, 0.6, 0.2, 1.),
-                (0.7, 0.3, 0.1, 1.),
-                (0.1, 0.7, 0.1, 1.),
-                (0.1, 0.1, 0.7, 1.),
+                color(0.5, 0.5, 0.5, 0.5),
+                color(0.8, 0.2, 0.2, 1.),
+                color(0.2, 0.8, 0.2, 1.),
+                color(0.2, 0.2, 0.8, 1.),
+                color(0.2, 0.6, 0.6, 1.),
+                color(0.6, 0.2, 0.6, 1.),
+                color(0.6, 0.6, 0.2, 1.),
+                color(0.7, 0.3, 0.1, 1.),
+                color(0.1, 0.7, 0.1, 1.),
+                color(0.1, 0.1, 0.7, 1.),
             ];
             let delta_x = 0.9 / (num_options as f32);
             let delta_y = 0.45 / (num_players as f32);
@@ -298,68 +245,54 @@ mod visualizer {
                 radius,
             }
         }
-        pub fn handle_start<W: Write>(&mut self, out: &mut W) {
+        pub fn handle_start(&mut self, ctl: &mut Controller) {
             self.positions.push((0., 0.));
             self.ids.push(0);
             for i in 1..=self.num_players {
                 self.ids.push(self.next_id);
                 self.positions
                     .push((0.05 + i as f32 * self.delta_x, self.radius + 0.05));
-                let color = self
-                    .player_color
-                    .get(i as usize % self.player_color.len())
-                    .unwrap()
-                    .clone();
-                w!(
-                    out,
-                    r#"vis {{t:{},create:{{id:{},z:1,p:[{},{}],geom:[{{circle:{{r:{},f:"{}"}}}},"#,
+                let color = self.player_color[i % self.player_color.len()];
+                ctl.vis(format_args!(
+                    r#"{{t:{},create:{{id:{},z:1,p:[{},{}],geom:[{{circle:{{r:{},f:"{color}"}}}},{{text:{{p:[{},{}],t:{},v:"{i}"}}}}]}}}}"#,
                     self.time,
                     self.next_id,
                     self.positions[i].0,
                     self.positions[i].1,
                     self.radius,
-                    format_color(color)
-                );
-                wln!(
-                    out,
-                    r#"{{text:{{p:[{},{}],t:{},v:"{}"}}}}]}}}}"#,
                     -self.radius * 0.25,
                     self.radius * 0.25,
                     self.radius,
-                    i
-                );
+                ));
                 self.next_id += 1;
             }
 
-            wln!(
-                out,
-                "vis {{t:{},create:{{id:{},z:1,geom:[{{line:{{p1:[0.05,0.5],p2:[0.95,0.5],t:0.004}}}}]}}}}",
+            ctl.vis(format_args!(
+                "{{t:{},create:{{id:{},z:1,geom:[{{line:{{p1:[0.05,0.5],p2:[0.95,0.5],t:0.004}}}}]}}}}",
                 self.time,
                 self.next_id
-            );
+            ));
             self.next_id += 1;
             for i in 0..=self.num_options {
-                wln!(
-                    out,
-                    "vis {{t:{},create:{{id:{},z:1,p:[{},0.5],geom:[{{line:{{p1:[0,-0.05],p2:[0,0.05],t:0.004}}}}]}}}}",
+                ctl.vis(format_args!(
+                    "{{t:{},create:{{id:{},z:1,p:[{},0.5],geom:[{{line:{{p1:[0,-0.05],p2:[0,0.05],t:0.004}}}}]}}}}",
                     self.time,
                     self.next_id,
                     i as f32 * self.delta_x + 0.05
-                );
+                ));
                 self.next_id += 1;
             }
         }
-        pub fn handle_move<W: Write>(
+        pub fn handle_move(
             &mut self,
-            out: &mut W,
+            ctl: &mut Controller,
             moves: &[Option<usize>],
-            winning_move: Option<usize>,
             winners: &[usize],
         ) {
             self.time += 0.4;
             let mut buckets = vec![0; 1 + self.num_options];
-            for i in 1..moves.len() {
-                let Some(x) = moves[i] else {
+            for (i, mv) in moves.iter().enumerate().skip(1) {
+                let Some(x) = *mv else {
                     continue;
                 };
                 let newx = x as f32 * self.delta_x + 0.05 - 0.5 * self.delta_x;
@@ -368,57 +301,27 @@ mod visualizer {
                 let (x, y) = self.positions[i];
                 let dx = newx - x;
                 let dy = newy - y;
-                wln!(
-                    out,
-                    "vis {{t:{},transform:{{id:{},d:0.3,mv:[{dx},{dy}]}}}}",
-                    self.time,
-                    self.ids[i]
-                );
+                ctl.vis(format_args!(
+                    "{{t:{},transform:{{id:{},d:0.3,mv:[{dx},{dy}]}}}}",
+                    self.time, self.ids[i]
+                ));
                 self.positions[i] = (newx, newy);
             }
             self.time += 0.3;
             for w in winners {
-                wln!(
-                    out,
-                    "vis {{t:{},transform:{{id:{},d:0.2,scale:1.6}}}}",
-                    self.time,
-                    self.ids[*w]
-                );
+                ctl.vis(format_args!(
+                    "{{t:{},transform:{{id:{},d:0.2,scale:1.6}}}}",
+                    self.time, self.ids[*w]
+                ));
             }
             for w in winners {
-                wln!(
-                    out,
-                    "vis {{t:{},transform:{{id:{},d:0.2,scale:0.625}}}}",
+                ctl.vis(format_args!(
+                    "{{t:{},transform:{{id:{},d:0.2,scale:0.625}}}}",
                     self.time + 0.3,
                     self.ids[*w]
-                );
+                ));
             }
             self.time += 0.3;
         }
     }
-    fn format_color((r, g, b, a): (f32, f32, f32, f32)) -> String {
-        let u = |c: f32| -> u8 {
-            (c.clamp(0., 1.) * 255.) as u8
-        };
-        format!("{:02x}{:02x}{:02x}{:02x}", u(r), u(g), u(b), u(a))
-    }
-}
-
-#[macro_export]
-macro_rules! wln {
-    ($o:expr) => {{
-        let _ = writeln!($o);
-        let _ = $o.flush();
-    }};
-    ($o:expr, $($es:expr),+) => {{
-        let _ = writeln!($o, $($es),+);
-        let _ = $o.flush();
-    }}
-}
-
-#[macro_export]
-macro_rules! w {
-    ($($es:expr),+) => {{
-        let _ = write!($($es),+);
-    }}
 }
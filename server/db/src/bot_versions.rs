@@ -0,0 +1,52 @@
+use sea_orm::entity::prelude::*;
+
+// Every program a bot has had, the active one is the program of the bot.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "bot_versions")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(indexed)]
+    pub bot_id: i64,
+    #[sea_orm(indexed)]
+    pub program_id: i64,
+    // From 1, in the order of upload.
+    pub version: i32,
+    pub creation_time: TimeDateTimeWithTimeZone,
+    // Becomes the active version once it compiles.
+    pub promote_on_success: bool,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bots::Entity",
+        from = "Column::BotId",
+        to = "super::bots::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bots,
+    #[sea_orm(
+        belongs_to = "super::programs::Entity",
+        from = "Column::ProgramId",
+        to = "super::programs::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Programs,
+}
+
+impl Related<super::bots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bots.def()
+    }
+}
+
+impl Related<super::programs::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Programs.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
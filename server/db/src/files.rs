@@ -10,6 +10,7 @@ pub enum Kind {
     SourceCode = 1,
     StaticContent = 2,
     MatchReplay = 3,
+    MatchThumbnail = 4,
 }
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
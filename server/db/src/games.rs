@@ -11,6 +11,53 @@ pub enum Status {
     InDevelopment,
 }
 
+// How bot ratings are updated after each match, see proglad_server::rating.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum RatingSystem {
+    #[sea_orm(string_value = "elo")]
+    Elo,
+    #[sea_orm(string_value = "glicko2")]
+    Glicko2,
+    #[sea_orm(string_value = "trueskill")]
+    TrueSkill,
+}
+
+impl RatingSystem {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RatingSystem::Elo => "Elo",
+            RatingSystem::Glicko2 => "Glicko-2",
+            RatingSystem::TrueSkill => "TrueSkill",
+        }
+    }
+}
+
+// How the bots of each match are chosen, see proglad_server::matchmaking.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum Matchmaking {
+    #[sea_orm(string_value = "balanced")]
+    Balanced,
+    #[sea_orm(string_value = "rating-proximity")]
+    RatingProximity,
+    #[sea_orm(string_value = "challenge-leader")]
+    ChallengeLeader,
+    #[sea_orm(string_value = "new-bots-play-more")]
+    NewBotsPlayMore,
+}
+
+impl Matchmaking {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Matchmaking::Balanced => "Balanced random",
+            Matchmaking::RatingProximity => "Close ratings",
+            Matchmaking::ChallengeLeader => "Challenge the leader",
+            Matchmaking::NewBotsPlayMore => "New bots play more",
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
 #[sea_orm(table_name = "games")]
 pub struct Model {
@@ -25,6 +72,20 @@ pub struct Model {
     pub status: Status,
     // Supports %%-substitutions
     pub param: Option<String>,
+    // Textapi protocol version the game server speaks. If not set, the
+    // controller does not start with "proto" and assumes the legacy protocol.
+    pub protocol_version: Option<i32>,
+    // Serve replays with all events to everyone, including the ones the game
+    // server addressed to specific players only.
+    #[sea_orm(default_value = false)]
+    pub reveal_hidden_events: bool,
+    #[sea_orm(default_value = "trueskill")]
+    pub rating_system: RatingSystem,
+    #[sea_orm(default_value = "balanced")]
+    pub matchmaking: Matchmaking,
+    // Never match bots of the same owner against each other.
+    #[sea_orm(default_value = false)]
+    pub distinct_owners: bool,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,62 @@
+use sea_orm::entity::prelude::*;
+
+// Results of a bot against one opponent over all their matches together. Every
+// pair of bots has a row in both directions.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[sea_orm(table_name = "head_to_head")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bot_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub opponent_id: i64,
+    #[sea_orm(indexed)]
+    pub game_id: i64,
+    pub matches: i64,
+    pub wins: i64,
+    pub draws: i64,
+    pub losses: i64,
+    // Sum of the score of the bot minus the score of the opponent.
+    pub score_diff: f64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::bots::Entity",
+        from = "Column::BotId",
+        to = "super::bots::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bots,
+    #[sea_orm(
+        belongs_to = "super::bots::Entity",
+        from = "Column::OpponentId",
+        to = "super::bots::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Opponents,
+    #[sea_orm(
+        belongs_to = "super::games::Entity",
+        from = "Column::GameId",
+        to = "super::games::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Games,
+}
+
+impl Related<super::bots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bots.def()
+    }
+}
+
+impl Related<super::games::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Games.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -2,12 +2,17 @@ pub mod prelude;
 
 pub mod accounts;
 pub mod acls;
+pub mod bot_versions;
 pub mod bots;
 pub mod common;
 pub mod files;
 pub mod games;
+pub mod head_to_head;
 pub mod match_participations;
 pub mod matches;
 pub mod programs;
+pub mod seasons;
 pub mod stats_history;
+pub mod tournament_entries;
+pub mod tournaments;
 pub mod work_items;
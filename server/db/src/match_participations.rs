@@ -11,6 +11,8 @@ pub struct Model {
     pub ingame_player: u32,
     pub score: Option<f64>,
     pub system_message: Option<String>,
+    // The version of the bot that played.
+    pub program_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
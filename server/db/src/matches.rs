@@ -14,6 +14,9 @@ pub struct Model {
     #[sea_orm(indexed)]
     pub end_time: Option<TimeDateTimeWithTimeZone>,
     pub system_message: String,
+    #[sea_orm(indexed)]
+    pub tournament_id: Option<i64>,
+    pub tournament_round: Option<i32>,
 }
 
 impl Model {
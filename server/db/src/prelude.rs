@@ -1,10 +1,15 @@
 pub use super::accounts::Entity as Accounts;
 pub use super::acls::Entity as Acls;
+pub use super::bot_versions::Entity as BotVersions;
 pub use super::bots::Entity as Bots;
 pub use super::files::Entity as Files;
 pub use super::games::Entity as Games;
+pub use super::head_to_head::Entity as HeadToHead;
 pub use super::match_participations::Entity as MatchParticipations;
 pub use super::matches::Entity as Matches;
 pub use super::programs::Entity as Programs;
+pub use super::seasons::Entity as Seasons;
 pub use super::stats_history::Entity as StatsHistory;
+pub use super::tournament_entries::Entity as TournamentEntries;
+pub use super::tournaments::Entity as Tournaments;
 pub use super::work_items::Entity as WorkItems;
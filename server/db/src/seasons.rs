@@ -0,0 +1,72 @@
+use sea_orm::entity::prelude::*;
+
+// What happens to the ratings of the bots of the game when a season starts.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum RatingCarryOver {
+    #[sea_orm(string_value = "reset")]
+    Reset,
+    #[sea_orm(string_value = "decay")]
+    Decay,
+}
+
+impl RatingCarryOver {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RatingCarryOver::Reset => "Reset ratings",
+            RatingCarryOver::Decay => "Decay ratings halfway",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum Status {
+    #[sea_orm(string_value = "upcoming")]
+    Upcoming,
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "finished")]
+    Finished,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "seasons")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(indexed)]
+    pub game_id: i64,
+    pub name: String,
+    #[sea_orm(indexed)]
+    pub status: Status,
+    pub start_time: TimeDateTimeWithTimeZone,
+    // No new bots after this, the rest of the season evaluates the frozen ones.
+    pub submission_deadline: TimeDateTimeWithTimeZone,
+    pub end_time: TimeDateTimeWithTimeZone,
+    pub rating_carry_over: RatingCarryOver,
+    // Matches scheduled per scheduling round during the evaluation.
+    pub evaluation_matches: i32,
+    // JSON snapshot of the standings, set when the season finishes.
+    pub final_standings: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::games::Entity",
+        from = "Column::GameId",
+        to = "super::games::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Games,
+}
+
+impl Related<super::games::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Games.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
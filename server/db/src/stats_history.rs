@@ -1,6 +1,6 @@
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, DeriveEntityModel)]
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "stats_history")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -14,6 +14,23 @@ pub struct Model {
     pub match_id: Option<i64>,
     pub total_score: f64,
     pub total_matches: i64,
+    // Sums over the last sampled_matches matches, for confidence intervals.
+    // Stats from before these were kept sample fewer than total_matches.
+    #[sea_orm(default_value = 0.0)]
+    pub total_score_sq: f64,
+    // Shares of first places, ties split them.
+    #[sea_orm(default_value = 0.0)]
+    pub total_wins: f64,
+    #[sea_orm(default_value = 0)]
+    pub sampled_matches: i64,
+    // Skill rating after the match, set only for matches rated with the
+    // system in rating_system.
+    pub rating_system: Option<super::games::RatingSystem>,
+    pub rating: Option<f64>,
+    pub rating_deviation: Option<f64>,
+    pub rating_volatility: Option<f64>,
+    // What bots are ranked by: a rating the bot is very likely above.
+    pub conservative_rating: Option<f64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,46 @@
+use sea_orm::entity::prelude::*;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "tournament_entries")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub tournament_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub bot_id: i64,
+    // 1 is the strongest bot at the start of the tournament.
+    pub seed: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::tournaments::Entity",
+        from = "Column::TournamentId",
+        to = "super::tournaments::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Tournaments,
+    #[sea_orm(
+        belongs_to = "super::bots::Entity",
+        from = "Column::BotId",
+        to = "super::bots::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Bots,
+}
+
+impl Related<super::tournaments::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tournaments.def()
+    }
+}
+
+impl Related<super::bots::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Bots.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,81 @@
+use sea_orm::entity::prelude::*;
+
+// All tournaments are of two-player matches.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum Format {
+    #[sea_orm(string_value = "round-robin")]
+    RoundRobin,
+    #[sea_orm(string_value = "swiss")]
+    Swiss,
+    // Single elimination.
+    #[sea_orm(string_value = "knockout")]
+    Knockout,
+}
+
+impl Format {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Format::RoundRobin => "Round-robin",
+            Format::Swiss => "Swiss",
+            Format::Knockout => "Knockout",
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "String(None)")]
+pub enum Status {
+    #[sea_orm(string_value = "running")]
+    Running,
+    #[sea_orm(string_value = "finished")]
+    Finished,
+}
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
+#[sea_orm(table_name = "tournaments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(indexed)]
+    pub game_id: i64,
+    pub name: String,
+    pub format: Format,
+    #[sea_orm(indexed)]
+    pub status: Status,
+    pub rounds: i32,
+    // The latest scheduled round, 0 before the first one.
+    pub current_round: i32,
+    pub creation_time: TimeDateTimeWithTimeZone,
+    pub end_time: Option<TimeDateTimeWithTimeZone>,
+    // JSON snapshot of the standings, set when the tournament finishes.
+    pub final_standings: Option<String>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::games::Entity",
+        from = "Column::GameId",
+        to = "super::games::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Games,
+    #[sea_orm(has_many = "super::tournament_entries::Entity")]
+    TournamentEntries,
+}
+
+impl Related<super::games::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Games.def()
+    }
+}
+
+impl Related<super::tournament_entries::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TournamentEntries.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
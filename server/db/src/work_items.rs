@@ -7,6 +7,8 @@ pub enum WorkType {
     Compilation,
     #[sea_orm(string_value = "runmatch")]
     RunMatch,
+    #[sea_orm(string_value = "recalculatestats")]
+    RecalculateStats,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
@@ -40,6 +42,15 @@ pub struct Model {
     pub program_id: Option<i64>,
     pub match_id: Option<i64>,
     pub priority: i64, // Greater value is higher.
+    // Comma separated players of a RunMatch, chosen by the matchmaking of the
+    // game when not set.
+    pub bot_ids: Option<String>,
+    pub tournament_id: Option<i64>,
+    pub tournament_round: Option<i32>,
+    // The account that challenged with a RunMatch, limited in how many it
+    // can request.
+    #[sea_orm(indexed)]
+    pub requester_id: Option<i64>,
 }
 
 impl Model {
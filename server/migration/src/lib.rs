@@ -7,6 +7,17 @@ mod m20240920_235707_create_work_items;
 mod m20241001_210358_create_files_table;
 mod m20241006_193744_create_acls_table;
 mod m20241012_214559_populate_assets;
+mod m20241020_120000_add_game_protocol_version;
+mod m20241027_120000_add_game_reveal_hidden_events;
+mod m20241103_120000_add_ratings;
+mod m20241110_120000_add_game_matchmaking;
+mod m20241117_120000_create_tournaments;
+mod m20241124_120000_create_seasons;
+mod m20241201_120000_create_head_to_head;
+mod m20241208_120000_add_stats_confidence;
+mod m20241215_120000_create_bot_versions;
+mod m20241222_120000_add_work_item_requester;
+mod m20241229_120000_port_bundled_games_to_cargo;
 
 pub struct Migrator;
 
@@ -21,6 +32,17 @@ impl MigratorTrait for Migrator {
             Box::new(m20241001_210358_create_files_table::Migration),
             Box::new(m20241006_193744_create_acls_table::Migration),
             Box::new(m20241012_214559_populate_assets::Migration),
+            Box::new(m20241020_120000_add_game_protocol_version::Migration),
+            Box::new(m20241027_120000_add_game_reveal_hidden_events::Migration),
+            Box::new(m20241103_120000_add_ratings::Migration),
+            Box::new(m20241110_120000_add_game_matchmaking::Migration),
+            Box::new(m20241117_120000_create_tournaments::Migration),
+            Box::new(m20241124_120000_create_seasons::Migration),
+            Box::new(m20241201_120000_create_head_to_head::Migration),
+            Box::new(m20241208_120000_add_stats_confidence::Migration),
+            Box::new(m20241215_120000_create_bot_versions::Migration),
+            Box::new(m20241222_120000_add_work_item_requester::Migration),
+            Box::new(m20241229_120000_port_bundled_games_to_cargo::Migration),
         ]
     }
 }
@@ -118,7 +118,7 @@ async fn populate_database<'a>(m: &'a SchemaManager<'a>) -> Result<(), DbErr> {
     Ok(())
 }
 
-async fn populate_database_halma_quad<'a, C: ConnectionTrait>(
+async fn populate_database_halma_quad<C: ConnectionTrait>(
     db: &C,
     account_id: i64,
 ) -> Result<(), DbErr> {
@@ -285,7 +285,7 @@ async fn populate_database_halma_quad<'a, C: ConnectionTrait>(
 }
 
 #[allow(dead_code)]
-async fn populate_database_halma_hex<'a, C: ConnectionTrait>(
+async fn populate_database_halma_hex<C: ConnectionTrait>(
     db: &C,
     account_id: i64,
 ) -> Result<(), DbErr> {
@@ -416,7 +416,7 @@ async fn populate_database_halma_hex<'a, C: ConnectionTrait>(
     Ok(())
 }
 
-async fn populate_database_lowest_unique<'a, C: ConnectionTrait>(
+async fn populate_database_lowest_unique<C: ConnectionTrait>(
     db: &C,
     account_id: i64,
 ) -> Result<(), DbErr> {
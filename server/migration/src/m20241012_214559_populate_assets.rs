@@ -9,7 +9,7 @@ pub struct Migration;
 #[async_trait::async_trait]
 impl MigrationTrait for Migration {
     async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
-        if !std::env::var("PROGLAD_POPULATE_DATABASE").is_ok() {
+        if std::env::var("PROGLAD_POPULATE_DATABASE").is_err() {
             return Ok(());
         }
         let db = manager.get_connection();
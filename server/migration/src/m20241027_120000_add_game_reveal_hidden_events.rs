@@ -0,0 +1,27 @@
+use proglad_db::{games, prelude::*};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        // Databases created from scratch already have the column.
+        if m.has_column("games", "reveal_hidden_events").await? {
+            return Ok(());
+        }
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        m.alter_table(
+            Table::alter()
+                .table(Games)
+                .add_column(&mut s.get_column_def::<Games>(games::Column::RevealHiddenEvents))
+                .to_owned(),
+        )
+        .await
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use proglad_db::{games, prelude::*, stats_history};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        // Databases created from scratch already have the columns.
+        if !m.has_column("games", "rating_system").await? {
+            m.alter_table(
+                Table::alter()
+                    .table(Games)
+                    .add_column(&mut s.get_column_def::<Games>(games::Column::RatingSystem))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        let columns = [
+            ("rating_system", stats_history::Column::RatingSystem),
+            ("rating", stats_history::Column::Rating),
+            ("rating_deviation", stats_history::Column::RatingDeviation),
+            ("rating_volatility", stats_history::Column::RatingVolatility),
+            (
+                "conservative_rating",
+                stats_history::Column::ConservativeRating,
+            ),
+        ];
+        for (name, column) in columns {
+            if m.has_column("stats_history", name).await? {
+                continue;
+            }
+            m.alter_table(
+                Table::alter()
+                    .table(StatsHistory)
+                    .add_column(&mut s.get_column_def::<StatsHistory>(column))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
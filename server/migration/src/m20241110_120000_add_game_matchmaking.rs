@@ -0,0 +1,34 @@
+use proglad_db::{games, prelude::*};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        // Databases created from scratch already have the columns.
+        let columns = [
+            ("matchmaking", games::Column::Matchmaking),
+            ("distinct_owners", games::Column::DistinctOwners),
+        ];
+        for (name, column) in columns {
+            if m.has_column("games", name).await? {
+                continue;
+            }
+            m.alter_table(
+                Table::alter()
+                    .table(Games)
+                    .add_column(&mut s.get_column_def::<Games>(column))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
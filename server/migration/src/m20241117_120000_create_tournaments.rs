@@ -0,0 +1,72 @@
+use proglad_db::{matches, prelude::*, work_items};
+use sea_orm::{EntityTrait, IdenStatic};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn idx<E: EntityTrait>(s: &sea_orm::Schema, e: E) -> Vec<IndexCreateStatement> {
+    s.create_index_from_entity(e)
+}
+
+async fn add_column<E: EntityTrait>(
+    m: &SchemaManager<'_>,
+    s: &sea_orm::Schema,
+    e: E,
+    column: E::Column,
+) -> Result<(), DbErr> {
+    // Databases created from scratch already have the columns.
+    if m.has_column(e.table_name(), column.as_str()).await? {
+        return Ok(());
+    }
+    m.alter_table(
+        Table::alter()
+            .table(e)
+            .add_column(&mut s.get_column_def::<E>(column))
+            .to_owned(),
+    )
+    .await
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        let mut create_table = s.create_table_from_entity(Tournaments);
+        create_table.if_not_exists();
+        m.create_table(create_table).await?;
+        let mut create_table = s.create_table_from_entity(TournamentEntries);
+        create_table.if_not_exists();
+        m.create_table(create_table).await?;
+        for mut i in idx(&s, Tournaments) {
+            i.if_not_exists();
+            m.create_index(i).await?;
+        }
+        add_column(m, &s, WorkItems, work_items::Column::BotIds).await?;
+        add_column(m, &s, WorkItems, work_items::Column::TournamentId).await?;
+        add_column(m, &s, WorkItems, work_items::Column::TournamentRound).await?;
+        add_column(m, &s, Matches, matches::Column::TournamentId).await?;
+        add_column(m, &s, Matches, matches::Column::TournamentRound).await?;
+        let mut tournament_index = Index::create();
+        tournament_index
+            .name("idx-matches-tournament_id")
+            .if_not_exists()
+            .table(Matches)
+            .col(matches::Column::TournamentId);
+        m.create_index(tournament_index).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_table(
+            Table::drop()
+                .table(TournamentEntries)
+                .if_exists()
+                .to_owned(),
+        )
+        .await?;
+        m.drop_table(Table::drop().table(Tournaments).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
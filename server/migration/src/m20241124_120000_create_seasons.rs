@@ -0,0 +1,31 @@
+use proglad_db::prelude::*;
+use sea_orm::EntityTrait;
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn idx<E: EntityTrait>(s: &sea_orm::Schema, e: E) -> Vec<IndexCreateStatement> {
+    s.create_index_from_entity(e)
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        let mut create_table = s.create_table_from_entity(Seasons);
+        create_table.if_not_exists();
+        m.create_table(create_table).await?;
+        for mut i in idx(&s, Seasons) {
+            i.if_not_exists();
+            m.create_index(i).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_table(Table::drop().table(Seasons).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use proglad_db::prelude::*;
+use sea_orm::{ConnectionTrait, EntityTrait};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn idx<E: EntityTrait>(s: &sea_orm::Schema, e: E) -> Vec<IndexCreateStatement> {
+    s.create_index_from_entity(e)
+}
+
+// From here on the results are added up match by match, the matches played
+// so far are aggregated once.
+const BACKFILL: &str = "
+INSERT INTO head_to_head (bot_id, opponent_id, game_id, matches, wins, draws, losses, score_diff)
+SELECT a.bot_id, b.bot_id, m.game_id, COUNT(*),
+    SUM(CASE WHEN a.score > b.score THEN 1 ELSE 0 END),
+    SUM(CASE WHEN a.score = b.score THEN 1 ELSE 0 END),
+    SUM(CASE WHEN a.score < b.score THEN 1 ELSE 0 END),
+    SUM(a.score - b.score)
+FROM match_participations a
+JOIN match_participations b ON a.match_id = b.match_id AND a.bot_id <> b.bot_id
+JOIN matches m ON m.id = a.match_id
+WHERE a.score IS NOT NULL AND b.score IS NOT NULL
+GROUP BY a.bot_id, b.bot_id, m.game_id";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        if m.has_table("head_to_head").await? {
+            return Ok(());
+        }
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        m.create_table(s.create_table_from_entity(HeadToHead))
+            .await?;
+        for mut i in idx(&s, HeadToHead) {
+            i.if_not_exists();
+            m.create_index(i).await?;
+        }
+        m.get_connection().execute_unprepared(BACKFILL).await?;
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_table(Table::drop().table(HeadToHead).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
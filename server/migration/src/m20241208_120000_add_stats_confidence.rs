@@ -0,0 +1,34 @@
+use proglad_db::{prelude::*, stats_history};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        let columns = [
+            ("total_score_sq", stats_history::Column::TotalScoreSq),
+            ("total_wins", stats_history::Column::TotalWins),
+            ("sampled_matches", stats_history::Column::SampledMatches),
+        ];
+        for (name, column) in columns {
+            if m.has_column("stats_history", name).await? {
+                continue;
+            }
+            m.alter_table(
+                Table::alter()
+                    .table(StatsHistory)
+                    .add_column(&mut s.get_column_def::<StatsHistory>(column))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use proglad_db::{match_participations, prelude::*};
+use sea_orm::{ConnectionTrait, EntityTrait};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+fn idx<E: EntityTrait>(s: &sea_orm::Schema, e: E) -> Vec<IndexCreateStatement> {
+    s.create_index_from_entity(e)
+}
+
+// Until now every bot had one program, which played all of its matches.
+const BACKFILL_VERSIONS: &str = "
+INSERT INTO bot_versions (bot_id, program_id, version, creation_time, promote_on_success)
+SELECT id, program_id, 1, creation_time, FALSE FROM bots
+WHERE NOT EXISTS (SELECT 1 FROM bot_versions v WHERE v.bot_id = bots.id)";
+
+const BACKFILL_PARTICIPATIONS: &str = "
+UPDATE match_participations
+SET program_id = (SELECT program_id FROM bots WHERE bots.id = match_participations.bot_id)
+WHERE program_id IS NULL";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        if !m.has_table("bot_versions").await? {
+            m.create_table(s.create_table_from_entity(BotVersions))
+                .await?;
+            for mut i in idx(&s, BotVersions) {
+                i.if_not_exists();
+                m.create_index(i).await?;
+            }
+        }
+        m.get_connection()
+            .execute_unprepared(BACKFILL_VERSIONS)
+            .await?;
+        if !m.has_column("match_participations", "program_id").await? {
+            m.alter_table(
+                Table::alter()
+                    .table(MatchParticipations)
+                    .add_column(&mut s.get_column_def::<MatchParticipations>(
+                        match_participations::Column::ProgramId,
+                    ))
+                    .to_owned(),
+            )
+            .await?;
+            m.get_connection()
+                .execute_unprepared(BACKFILL_PARTICIPATIONS)
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        m.drop_table(Table::drop().table(BotVersions).if_exists().to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,33 @@
+use proglad_db::{prelude::*, work_items};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, m: &SchemaManager) -> Result<(), DbErr> {
+        let s = sea_orm::Schema::new(m.get_database_backend());
+        if !m.has_column("work_items", "requester_id").await? {
+            m.alter_table(
+                Table::alter()
+                    .table(WorkItems)
+                    .add_column(&mut s.get_column_def::<WorkItems>(work_items::Column::RequesterId))
+                    .to_owned(),
+            )
+            .await?;
+        }
+        let mut requester_index = Index::create();
+        requester_index
+            .name("idx-work_items-requester_id")
+            .if_not_exists()
+            .table(WorkItems)
+            .col(work_items::Column::RequesterId);
+        m.create_index(requester_index).await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+use proglad_db::{common, files, games, prelude::*, programs};
+use sea_orm::entity::prelude::TimeDateTimeWithTimeZone;
+use sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+// The bundled game servers use the game server SDK, so they are compiled as
+// cargo projects with their current sources.
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        if std::env::var("PROGLAD_POPULATE_DATABASE").is_err() {
+            return Ok(());
+        }
+        let db = manager.get_connection();
+        port_game(db, "halma-quad", "../games/halma-quad/server/main.rs").await?;
+        port_game(db, "lowest-unique", "../games/lowest-unique/server/main.rs").await?;
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
+
+async fn port_game<C: ConnectionTrait>(
+    db: &C,
+    game_name: &str,
+    gameserver_path: &str,
+) -> Result<(), DbErr> {
+    let Some(game) = Games::find()
+        .filter(games::Column::Name.eq(game_name))
+        .one(db)
+        .await?
+    else {
+        return Ok(());
+    };
+    let Some(program) = Programs::find_by_id(game.program_id).one(db).await? else {
+        return Ok(());
+    };
+    if program.language == programs::Language::RustCargo {
+        return Ok(());
+    }
+    let source_code = tokio::fs::read_to_string(gameserver_path)
+        .await
+        .map_err(|e| {
+            DbErr::Custom(format!(
+                "Failed to read game server file {gameserver_path} for database seeding: {e}"
+            ))
+        })?;
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    Files::update_many()
+        .col_expr(
+            files::Column::Content,
+            Expr::value(Some(source_code.into_bytes())),
+        )
+        .col_expr(
+            files::Column::Compression,
+            Expr::value(files::Compression::Uncompressed),
+        )
+        .col_expr(files::Column::LastUpdate, Expr::value(now))
+        .filter(files::Column::OwningEntity.eq(common::EntityKind::Program))
+        .filter(files::Column::OwningId.eq(program.id))
+        .filter(files::Column::Kind.eq(files::Kind::SourceCode))
+        .exec(db)
+        .await?;
+    programs::ActiveModel {
+        id: Set(program.id),
+        language: Set(programs::Language::RustCargo),
+        status: Set(programs::Status::New),
+        status_reason: Set(None),
+        status_update_time: Set(now),
+        ..Default::default()
+    }
+    .update(db)
+    .await?;
+    Ok(())
+}
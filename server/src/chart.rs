@@ -0,0 +1,115 @@
+// Line charts rendered on the server as SVG.
+
+use std::fmt::Write;
+
+const WIDTH: f64 = 600.;
+const HEIGHT: f64 = 240.;
+// Room for the axis labels.
+const MARGIN: f64 = 48.;
+
+// `points` are (x, y) by increasing x. The x axis has the labels of its ends,
+// the y axis is labeled with its range.
+pub fn line_chart(points: &[(f64, f64)], x_labels: [&str; 2]) -> String {
+    let mut svg = String::new();
+    let _ = write!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {WIDTH} {HEIGHT}" width="{WIDTH}" height="{HEIGHT}" font-family="sans-serif" font-size="12">"#
+    );
+    let _ = write!(
+        svg,
+        r##"<rect width="{WIDTH}" height="{HEIGHT}" fill="#fff"/><path d="M{MARGIN} {top} V{bottom} H{right}" fill="none" stroke="#999"/>"##,
+        top = MARGIN / 2.,
+        bottom = HEIGHT - MARGIN,
+        right = WIDTH - MARGIN / 2.,
+    );
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        let _ = write!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="middle">No matches yet</text></svg>"#,
+            WIDTH / 2.,
+            HEIGHT / 2.
+        );
+        return svg;
+    };
+    let (x_min, x_max) = (first.0, last.0);
+    let (mut y_min, mut y_max) = points
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), (_, y)| {
+            (lo.min(*y), hi.max(*y))
+        });
+    if y_max - y_min < 1e-9 {
+        y_min -= 1.;
+        y_max += 1.;
+    }
+    // A single point, or all at once, goes in the middle.
+    let x = |v: f64| {
+        let t = if x_max > x_min {
+            (v - x_min) / (x_max - x_min)
+        } else {
+            0.5
+        };
+        MARGIN + t * (WIDTH - 1.5 * MARGIN)
+    };
+    let y = |v: f64| HEIGHT - MARGIN - (v - y_min) / (y_max - y_min) * (HEIGHT - 1.5 * MARGIN);
+    let _ = write!(
+        svg,
+        r#"<text x="{lx}" y="{top}" text-anchor="end">{y_max:.2}</text><text x="{lx}" y="{bottom}" text-anchor="end">{y_min:.2}</text>"#,
+        lx = MARGIN - 4.,
+        top = y(y_max) + 4.,
+        bottom = y(y_min) + 4.,
+    );
+    let _ = write!(
+        svg,
+        r#"<text x="{MARGIN}" y="{ly}">{}</text><text x="{right}" y="{ly}" text-anchor="end">{}</text>"#,
+        x_labels[0],
+        x_labels[1],
+        ly = HEIGHT - MARGIN + 16.,
+        right = WIDTH - MARGIN / 2.,
+    );
+    if points.len() == 1 {
+        let _ = write!(
+            svg,
+            r##"<circle cx="{:.1}" cy="{:.1}" r="3" fill="#36c"/>"##,
+            x(first.0),
+            y(first.1)
+        );
+    } else {
+        svg.push_str(r##"<polyline fill="none" stroke="#36c" stroke-width="2" points=""##);
+        for (i, (px, py)) in points.iter().enumerate() {
+            let sep = if i == 0 { "" } else { " " };
+            let _ = write!(svg, "{sep}{:.1},{:.1}", x(*px), y(*py));
+        }
+        svg.push_str(r#""/>"#);
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn points_fit_the_chart() {
+        let svg = line_chart(&[(10., 1.), (20., 3.), (40., 2.)], ["a", "b"]);
+        let points = svg
+            .split(r#"points=""#)
+            .nth(1)
+            .unwrap()
+            .split('"')
+            .next()
+            .unwrap()
+            .split(' ')
+            .map(|p| {
+                let (x, y) = p.split_once(',').unwrap();
+                (x.parse::<f64>().unwrap(), y.parse::<f64>().unwrap())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0], (MARGIN, HEIGHT - MARGIN));
+        assert_eq!(points[1].1, MARGIN / 2.);
+        assert!(points.iter().all(|(x, _)| *x <= WIDTH - MARGIN / 2.));
+        assert!(line_chart(&[], ["", ""]).contains("No matches yet"));
+        assert!(line_chart(&[(1., 1.)], ["", ""]).contains("<circle"));
+    }
+}
@@ -0,0 +1,99 @@
+// How much the stats of a bot can be trusted: 95% intervals of its win rate
+// and of its mean score, and whether it played too few matches to be compared
+// with the others yet.
+
+use proglad_db as db;
+
+// Bots with fewer matches are provisional, they rank after the others and the
+// scheduler gives them more matches.
+pub const PROVISIONAL_MATCHES: i64 = 20;
+// Two-sided 95%.
+const Z: f64 = 1.96;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Interval {
+    pub low: f64,
+    pub high: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Confidence {
+    pub win_rate: Option<Interval>,
+    pub mean_score: Option<Interval>,
+    pub provisional: bool,
+}
+
+pub fn is_provisional(matches_played: i64) -> bool {
+    matches_played < PROVISIONAL_MATCHES
+}
+
+// Wilson score interval of a proportion.
+pub fn wilson(successes: f64, n: f64) -> Option<Interval> {
+    if n <= 0. {
+        return None;
+    }
+    let p = successes / n;
+    let z2 = Z * Z;
+    let denom = 1. + z2 / n;
+    let center = (p + z2 / (2. * n)) / denom;
+    let half = Z * (p * (1. - p) / n + z2 / (4. * n * n)).sqrt() / denom;
+    Some(Interval {
+        low: (center - half).max(0.),
+        high: (center + half).min(1.),
+    })
+}
+
+// Normal interval of the mean of `n` values, with the variance estimated from
+// a sample of `sample_n` of them.
+pub fn mean_interval(mean: f64, n: f64, sample_sq_sum: f64, sample_n: f64) -> Option<Interval> {
+    if sample_n < 2. || n < sample_n {
+        return None;
+    }
+    let variance = ((sample_sq_sum / sample_n - mean * mean) * sample_n / (sample_n - 1.)).max(0.);
+    let half = Z * (variance / n).sqrt();
+    Some(Interval {
+        low: mean - half,
+        high: mean + half,
+    })
+}
+
+pub fn of(st: &db::stats_history::Model) -> Confidence {
+    let n = st.total_matches as f64;
+    let sample_n = st.sampled_matches as f64;
+    Confidence {
+        win_rate: wilson(st.total_wins, sample_n),
+        mean_score: mean_interval(st.total_score / n.max(1.), n, st.total_score_sq, sample_n),
+        provisional: is_provisional(st.total_matches),
+    }
+}
+
+// Shares of the first place of each of the scores of a match.
+pub fn win_shares(scores: &[f64]) -> Vec<f64> {
+    let top = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let winners = scores.iter().filter(|s| **s == top).count();
+    scores
+        .iter()
+        .map(|s| if *s == top { 1. / winners as f64 } else { 0. })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn intervals() {
+        let w = wilson(5., 10.).unwrap();
+        assert!((w.low - 0.2366).abs() < 1e-4, "{w:?}");
+        assert!((w.high - 0.7634).abs() < 1e-4, "{w:?}");
+        let w = wilson(0., 3.).unwrap();
+        assert_eq!(w.low, 0.);
+        assert!(w.high > 0.5, "{w:?}");
+        assert_eq!(wilson(0., 0.), None);
+        // Scores 1, 2, 3: variance 1.
+        let m = mean_interval(2., 3., 14., 3.).unwrap();
+        assert!((m.high - 2. - Z / 3f64.sqrt()).abs() < 1e-9, "{m:?}");
+        assert_eq!(mean_interval(2., 1., 4., 1.), None);
+        assert_eq!(win_shares(&[1., 3., 3.]), vec![0., 0.5, 0.5]);
+    }
+}
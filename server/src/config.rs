@@ -6,6 +6,27 @@ pub struct AccessControl {
     pub insecure_default_account: Option<String>,
 }
 
+// Matches that bot owners start against opponents of their choice.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Challenges {
+    // Of their work items, above the scheduled matches.
+    pub priority: i64,
+    // Per account.
+    pub max_pending: u64,
+    pub max_per_hour: u64,
+}
+
+impl Default for Challenges {
+    fn default() -> Self {
+        Self {
+            priority: 1500,
+            max_pending: 2,
+            max_per_hour: 10,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ServerConfig {
     pub port: u16,
@@ -17,6 +38,16 @@ pub struct ServerConfig {
 
     #[serde(default)]
     pub access_control: AccessControl,
+    #[serde(default)]
+    pub challenges: Challenges,
+    // Of the work items that rebuild the stats of a game, requested by its
+    // owner.
+    #[serde(default = "default_stats_recalculation_priority")]
+    pub stats_recalculation_priority: i64,
+}
+
+fn default_stats_recalculation_priority() -> i64 {
+    2000
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
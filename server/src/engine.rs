@@ -1,5 +1,5 @@
 use anyhow::{anyhow, Context};
-use rand::{seq::SliceRandom, Rng};
+use rand::Rng;
 use sea_orm::prelude::TimeDateTimeWithTimeZone;
 use sea_orm::FromQueryResult;
 use sea_orm::{
@@ -9,11 +9,20 @@ use sea_orm::{
 use sea_query::Expr;
 use serde::{Deserialize, Serialize};
 
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
+use std::io::Read;
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::confidence;
 use crate::file_store::{self, FileStore};
+use crate::head_to_head;
+use crate::matchmaking;
+use crate::rating;
+use crate::recalculate;
+use crate::season;
+use crate::tournament;
+use proglad_api::{render, visualize};
 use proglad_controller::{manager, match_runner};
 use proglad_db as db;
 
@@ -79,6 +88,7 @@ pub async fn run_match<C: ConnectionTrait + TransactionTrait>(
     db: &C,
     file_store: &FileStore,
     bots: &[i64],
+    tournament: Option<(i64, i32)>,
     config: &match_runner::Config,
 ) -> anyhow::Result<()> {
     // If this dies, the match gets cancelled. That is OK for now. In the
@@ -88,7 +98,8 @@ pub async fn run_match<C: ConnectionTrait + TransactionTrait>(
     // For now the controller is integrated with the server, and it dying
     // also results in match being cancelled.
     let data = db_fetch_data(db, bots).await?;
-    let match_id = db_prepare_match(db, &data).await?;
+    let match_id = db_prepare_match(db, &data, tournament).await?;
+    let game_id = data.game.id;
 
     let mut agents = Vec::with_capacity(1 + bots.len());
     agents.push(manager::Agent {
@@ -114,6 +125,7 @@ pub async fn run_match<C: ConnectionTrait + TransactionTrait>(
         config: config.clone(),
         id: match_id,
         agents,
+        protocol_version: data.game.protocol_version.map(|v| v as u32),
     };
 
     log::info!("Starting match {match_id}");
@@ -121,6 +133,7 @@ pub async fn run_match<C: ConnectionTrait + TransactionTrait>(
     let match_result = manager::run_match(man.clone(), config)
         .await
         .context(format!("Match {match_id} failed to start"))?;
+    let replay = match_result.log.as_ref().ok().cloned();
     let score_deltas = match_result.result.as_ref().ok().map(|mr| {
         bots.iter()
             .copied()
@@ -154,6 +167,9 @@ pub async fn run_match<C: ConnectionTrait + TransactionTrait>(
     })
     .await
     .context("Transaction failed")?;
+    if let Some(replay) = replay {
+        store_thumbnail(db, file_store, match_id, game_id, replay).await;
+    }
     log::info!("Match {match_id} result: {ret:?}");
     ret
 }
@@ -186,6 +202,9 @@ fn pick_num_players(game: &db::games::Model, available_players: usize) -> anyhow
     Ok(num_players as usize)
 }
 
+// Matchmaking only looks at this many latest matches of the game.
+const MATCHMAKING_RECENT_MATCHES: u64 = 1000;
+
 async fn choose_match_for_game<C: ConnectionTrait>(
     db: &C,
     game_id: i64,
@@ -210,91 +229,94 @@ async fn choose_match_for_game<C: ConnectionTrait>(
         .all(db)
         .await
         .context("choose_match_for_game: Failed to find active bots")?;
-    let mut active_bot_ids: Vec<i64> = active_bots.iter().map(|b| b.id).collect();
+    let active_bot_ids: Vec<i64> = active_bots.iter().map(|b| b.id).collect();
+    let recent_match_ids = db::matches::Entity::find()
+        .select_only()
+        .column(db::matches::Column::Id)
+        .filter(db::matches::Column::GameId.eq(game_id))
+        .order_by_desc(db::matches::Column::Id)
+        .limit(MATCHMAKING_RECENT_MATCHES)
+        .into_tuple::<i64>()
+        .all(db)
+        .await
+        .context("choose_match_for_game: Failed to read recent matches")?;
     let participations = db::match_participations::Entity::find()
-        .filter(db::match_participations::Column::BotId.is_in(active_bot_ids.iter().copied()))
+        .filter(
+            Condition::all()
+                .add(db::match_participations::Column::MatchId.is_in(recent_match_ids))
+                .add(db::match_participations::Column::BotId.is_in(active_bot_ids.iter().copied())),
+        )
         .all(db)
         .await
-        .context("choose_and_run_match: Failed to read match participations")?;
-    let mut matches = HashMap::<i64, HashSet<i64>>::new();
+        .context("choose_match_for_game: Failed to read match participations")?;
+    let mut matches = HashMap::<i64, Vec<i64>>::new();
     for p in participations.iter() {
-        matches.entry(p.match_id).or_default().insert(p.bot_id);
+        matches.entry(p.match_id).or_default().push(p.bot_id);
     }
-    let mut botset_counts: HashMap<Vec<i64>, usize> = Default::default();
-    for (_, bs) in matches.into_iter() {
-        let mut bs = bs.into_iter().collect::<Vec<_>>();
-        bs.sort();
-        *botset_counts.entry(bs).or_default() += 1;
-    }
-    let num_players = pick_num_players(&game, active_bots.len())?;
-    let mut selected_players = HashSet::<i64>::new();
-    let mut rng = rand::thread_rng();
-    for _ in 0..num_players {
-        let mut bot_match_counts = HashMap::<i64, usize>::new();
-        for (botset, count) in botset_counts.iter() {
-            if botset
-                .iter()
-                .filter(|id| selected_players.contains(id))
-                .count()
-                != selected_players.len()
-            {
-                continue;
-            }
-            for id in botset.iter() {
-                if !selected_players.contains(id) {
-                    *bot_match_counts.entry(*id).or_default() += count;
-                }
+    let recent_matches = matches.into_values().collect::<Vec<_>>();
+    let stats = db::stats_history::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::stats_history::Column::Latest.eq(true))
+                .add(db::stats_history::Column::BotId.is_in(active_bot_ids.iter().copied())),
+        )
+        .all(db)
+        .await
+        .context("choose_match_for_game: Failed to read bot stats")?
+        .into_iter()
+        .map(|st| (st.bot_id, st))
+        .collect::<HashMap<_, _>>();
+    let candidates = active_bots
+        .iter()
+        .map(|b| {
+            let st = stats.get(&b.id);
+            matchmaking::Candidate {
+                bot_id: b.id,
+                owner_id: b.owner_id,
+                matches_played: st.map(|st| st.total_matches).unwrap_or_default(),
+                provisional: confidence::is_provisional(st.map_or(0, |st| st.total_matches)),
+                rating: st
+                    .filter(|st| st.rating_system == Some(game.rating_system))
+                    .and_then(|st| st.conservative_rating),
             }
-        }
-        let min_match_count: usize = active_bot_ids
-            .iter()
-            .map(|id| bot_match_counts.get(id).copied().unwrap_or_default())
-            .min()
-            .unwrap();
-        let candidates = active_bot_ids
-            .iter()
-            .filter_map(|id| {
-                if bot_match_counts.get(id).copied().unwrap_or_default() == min_match_count {
-                    Some(*id)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-        let player = candidates.choose(&mut rng).copied().unwrap();
-        active_bot_ids.retain(|id| *id != player);
-        selected_players.insert(player);
-    }
-    let mut selected_players = selected_players.into_iter().collect::<Vec<_>>();
-    selected_players.shuffle(&mut rng);
+        })
+        .collect::<Vec<_>>();
+    let pool = matchmaking::Pool {
+        candidates: &candidates,
+        recent_matches: &recent_matches,
+        constraints: matchmaking::Constraints {
+            distinct_owners: game.distinct_owners,
+        },
+    };
+    let num_players = pick_num_players(&game, pool.max_players())?;
+    let selected_players = matchmaking::strategy(game.matchmaking)
+        .choose(&pool, num_players, &mut rand::thread_rng())
+        .with_context(|| format!("choose_match_for_game: game {game_id}"))?;
     log::info!("Will run game {game_id} with players {selected_players:?}");
     Ok(selected_players)
 }
 
-pub async fn create_bot<C: ConnectionTrait>(
+// Stores the source code of a new program, the scheduler compiles it.
+async fn insert_program<C: ConnectionTrait>(
     db: &C,
     file_store: &FileStore,
-    game_id: i64,
-    owner_id: i64,
     source_path: impl AsRef<Path>,
     language: db::programs::Language,
-    name: &str,
 ) -> anyhow::Result<i64> {
     let source_code = tokio::fs::read(source_path)
         .await
         .context("Failed to read source tempfile")?;
     std::str::from_utf8(&source_code).context("Incorrect encoding of the source code file")?;
-    let now = TimeDateTimeWithTimeZone::now_utc();
     let program = db::programs::ActiveModel {
         language: Set(language),
         status: Set(db::programs::Status::New),
-        status_update_time: Set(now),
+        status_update_time: Set(TimeDateTimeWithTimeZone::now_utc()),
         ..Default::default()
     };
     let program_id = db::programs::Entity::insert(program)
         .exec(db)
         .await
-        .context("Failed to insert program by account {owner_id} for game {game_id}")?
+        .context("Failed to insert program")?
         .last_insert_id;
     let file = db::files::Model {
         owning_entity: db::common::EntityKind::Program,
@@ -309,7 +331,24 @@ pub async fn create_bot<C: ConnectionTrait>(
         .write(db, file_store::Requester::System, file)
         .await
         .context("Failed to write source code file")?;
+    Ok(program_id)
+}
 
+pub async fn create_bot<C: ConnectionTrait>(
+    db: &C,
+    file_store: &FileStore,
+    game_id: i64,
+    owner_id: i64,
+    source_path: impl AsRef<Path>,
+    language: db::programs::Language,
+    name: &str,
+) -> anyhow::Result<i64> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    let program_id = insert_program(db, file_store, source_path, language)
+        .await
+        .context(format!(
+            "Failed to create program by account {owner_id} for game {game_id}"
+        ))?;
     let bot = db::bots::ActiveModel {
         name: Set(name.to_owned()),
         owner_id: Set(owner_id),
@@ -326,9 +365,127 @@ pub async fn create_bot<C: ConnectionTrait>(
         .await
         .context("Failed to create bot by account {account_id} for game {game_id}")?
         .last_insert_id;
+    let version = db::bot_versions::ActiveModel {
+        bot_id: Set(bot_id),
+        program_id: Set(program_id),
+        version: Set(1),
+        creation_time: Set(now),
+        promote_on_success: Set(false),
+        ..Default::default()
+    };
+    db::bot_versions::Entity::insert(version)
+        .exec(db)
+        .await
+        .context(format!(
+            "Failed to create the first version of bot {bot_id}"
+        ))?;
     Ok(bot_id)
 }
 
+// Adds a version of the bot that replaces the active one once it compiles.
+// Returns the version number.
+pub async fn create_bot_version<C: ConnectionTrait>(
+    db: &C,
+    file_store: &FileStore,
+    bot_id: i64,
+    source_path: impl AsRef<Path>,
+    language: db::programs::Language,
+) -> anyhow::Result<i32> {
+    let program_id = insert_program(db, file_store, source_path, language)
+        .await
+        .context(format!("Failed to create a program for bot {bot_id}"))?;
+    let latest = db::bot_versions::Entity::find()
+        .filter(db::bot_versions::Column::BotId.eq(bot_id))
+        .order_by_desc(db::bot_versions::Column::Version)
+        .one(db)
+        .await
+        .context(format!("Failed to fetch versions of bot {bot_id}"))?;
+    // Only the latest upload gets promoted.
+    db_cancel_promotions(db, bot_id).await?;
+    let version = latest.map_or(1, |v| v.version + 1);
+    let model = db::bot_versions::ActiveModel {
+        bot_id: Set(bot_id),
+        program_id: Set(program_id),
+        version: Set(version),
+        creation_time: Set(TimeDateTimeWithTimeZone::now_utc()),
+        promote_on_success: Set(true),
+        ..Default::default()
+    };
+    db::bot_versions::Entity::insert(model)
+        .exec(db)
+        .await
+        .context(format!(
+            "Failed to create version {version} of bot {bot_id}"
+        ))?;
+    Ok(version)
+}
+
+async fn db_cancel_promotions<C: ConnectionTrait>(db: &C, bot_id: i64) -> anyhow::Result<()> {
+    db::bot_versions::Entity::update_many()
+        .set(db::bot_versions::ActiveModel {
+            promote_on_success: Set(false),
+            ..Default::default()
+        })
+        .filter(db::bot_versions::Column::BotId.eq(bot_id))
+        .exec(db)
+        .await
+        .context(format!("Failed to cancel promotions of bot {bot_id}"))?;
+    Ok(())
+}
+
+// Makes the version the one that plays, its program must have compiled.
+pub async fn activate_bot_version<C: ConnectionTrait>(
+    db: &C,
+    version: &db::bot_versions::Model,
+) -> anyhow::Result<()> {
+    db_cancel_promotions(db, version.bot_id).await?;
+    db::bots::Entity::update(db::bots::ActiveModel {
+        id: Set(version.bot_id),
+        program_id: Set(version.program_id),
+        system_status: Set(db::bots::SystemStatus::Ok),
+        system_status_reason: Set(None),
+        status_update_time: Set(TimeDateTimeWithTimeZone::now_utc()),
+        ..Default::default()
+    })
+    .exec(db)
+    .await
+    .context(format!(
+        "Failed to activate version {} of bot {}",
+        version.version, version.bot_id
+    ))?;
+    log::info!(
+        "Version {} of bot {} is active",
+        version.version,
+        version.bot_id
+    );
+    Ok(())
+}
+
+// Versions that failed to compile are left as they are, the bot keeps
+// playing with its active one.
+async fn db_promote_versions_of_program<C: ConnectionTrait>(
+    db: &C,
+    program_id: i64,
+    compiled: bool,
+) -> anyhow::Result<()> {
+    let pending = db::bot_versions::Entity::find()
+        .filter(db::bot_versions::Column::ProgramId.eq(program_id))
+        .filter(db::bot_versions::Column::PromoteOnSuccess.eq(true))
+        .all(db)
+        .await
+        .context(format!(
+            "Failed to fetch bot versions of program {program_id}"
+        ))?;
+    for version in pending {
+        if compiled {
+            activate_bot_version(db, &version).await?;
+        } else {
+            db_cancel_promotions(db, version.bot_id).await?;
+        }
+    }
+    Ok(())
+}
+
 fn from_db_language(language: db::programs::Language) -> manager::Language {
     match language {
         db::programs::Language::Cpp => manager::Language::Cpp,
@@ -406,11 +563,14 @@ async fn db_fetch_data<C: ConnectionTrait>(db: &C, bots: &[i64]) -> anyhow::Resu
 async fn db_prepare_match<C: ConnectionTrait>(
     db: &C,
     data: &DbMatchData,
+    tournament: Option<(i64, i32)>,
 ) -> anyhow::Result<manager::MatchId> {
     let m = db::matches::ActiveModel {
         game_id: Set(data.game.id),
         creation_time: Set(TimeDateTimeWithTimeZone::now_utc()),
         system_message: Set("Just created".to_owned()),
+        tournament_id: Set(tournament.map(|(id, _)| id)),
+        tournament_round: Set(tournament.map(|(_, round)| round)),
         ..Default::default()
     };
     let match_id = db::matches::Entity::insert(m)
@@ -426,6 +586,7 @@ async fn db_prepare_match<C: ConnectionTrait>(
                 bot_id: Set(b.id),
                 match_id: Set(match_id),
                 ingame_player: Set(1 + i as u32),
+                program_id: Set(Some(b.program_id)),
                 ..Default::default()
             });
     db::match_participations::Entity::insert_many(participations)
@@ -520,6 +681,21 @@ pub async fn db_update_stats_for_match<C: ConnectionTrait>(
 ) -> Result<(), MyDbError> {
     let bot_ids = score_deltas.iter().map(|(id, _)| *id);
     let now = TimeDateTimeWithTimeZone::now_utc();
+    let game = db::matches::Entity::find_by_id(match_id)
+        .find_also_related(db::games::Entity)
+        .one(db)
+        .await
+        .map_err(|db_error| MyDbError {
+            db_error,
+            context: format!("Failed to fetch the game of match {match_id}"),
+        })?
+        .and_then(|(_, g)| g)
+        .ok_or_else(|| MyDbError {
+            db_error: DbErr::RecordNotFound(format!("game of match {match_id}")),
+            context: "Failed to fetch the rating system".to_owned(),
+        })?;
+    let system = game.rating_system;
+    let rating_system = rating::rating_system(system);
     let stats = db::stats_history::Entity::find()
         .filter(
             Condition::all()
@@ -549,26 +725,67 @@ pub async fn db_update_stats_for_match<C: ConnectionTrait>(
             db_error,
             context: "Failed to update non-latest stats".to_owned(),
         })?;
-    let new_stats = score_deltas.into_iter().map(|(id, delta)| {
-        let mut new_st = db::stats_history::ActiveModel {
-            bot_id: Set(id),
-            update_time: Set(now),
-            match_id: Set(Some(match_id)),
-            latest: Set(true),
-            ..Default::default()
-        };
-        match stats.get(&id) {
-            Some(st) => {
-                new_st.total_score = Set(st.total_score + delta);
-                new_st.total_matches = Set(st.total_matches + 1);
-            }
-            None => {
-                new_st.total_score = Set(delta);
-                new_st.total_matches = Set(1);
+    // Ratings from another system, before the game switched, start over.
+    let ratings = score_deltas
+        .iter()
+        .map(|(id, score)| {
+            let r = stats
+                .get(id)
+                .filter(|st| st.rating_system == Some(system))
+                .and_then(|st| {
+                    Some(rating::Rating {
+                        mu: st.rating?,
+                        sigma: st.rating_deviation?,
+                        volatility: st.rating_volatility?,
+                    })
+                })
+                .unwrap_or_else(|| rating_system.initial());
+            (r, *score)
+        })
+        .collect::<Vec<_>>();
+    let ratings = rating_system.update(&ratings);
+    let scores = score_deltas.iter().map(|(_, s)| *s).collect::<Vec<_>>();
+    let wins = confidence::win_shares(&scores);
+    head_to_head::db_add_match(db, game.id, &score_deltas)
+        .await
+        .map_err(|db_error| MyDbError {
+            db_error,
+            context: format!("Failed to update head-to-head results of match {match_id}"),
+        })?;
+    let new_stats = score_deltas
+        .into_iter()
+        .zip(ratings.into_iter().zip(wins))
+        .map(|((id, delta), (r, win))| {
+            let mut new_st = db::stats_history::ActiveModel {
+                bot_id: Set(id),
+                update_time: Set(now),
+                match_id: Set(Some(match_id)),
+                latest: Set(true),
+                rating_system: Set(Some(system)),
+                rating: Set(Some(r.mu)),
+                rating_deviation: Set(Some(r.sigma)),
+                rating_volatility: Set(Some(r.volatility)),
+                conservative_rating: Set(Some(rating_system.conservative(&r))),
+                ..Default::default()
+            };
+            match stats.get(&id) {
+                Some(st) => {
+                    new_st.total_score = Set(st.total_score + delta);
+                    new_st.total_matches = Set(st.total_matches + 1);
+                    new_st.total_score_sq = Set(st.total_score_sq + delta * delta);
+                    new_st.total_wins = Set(st.total_wins + win);
+                    new_st.sampled_matches = Set(st.sampled_matches + 1);
+                }
+                None => {
+                    new_st.total_score = Set(delta);
+                    new_st.total_matches = Set(1);
+                    new_st.total_score_sq = Set(delta * delta);
+                    new_st.total_wins = Set(win);
+                    new_st.sampled_matches = Set(1);
+                }
             }
-        }
-        new_st
-    });
+            new_st
+        });
     db::stats_history::Entity::insert_many(new_stats)
         .exec(db)
         .await
@@ -579,6 +796,96 @@ pub async fn db_update_stats_for_match<C: ConnectionTrait>(
     Ok(())
 }
 
+pub const THUMBNAIL_FILE_NAME: &str = "thumbnail.svg";
+
+// Limits on what gets rendered into a thumbnail, replays beyond them have none.
+const MAX_THUMBNAIL_LOG_BYTES: u64 = 64 << 20;
+const MAX_THUMBNAIL_EVENTS: usize = 200_000;
+const MAX_THUMBNAIL_OBJECTS: usize = 20_000;
+
+// Renders the thumbnail off the async runtime, once the match result is
+// committed. Failing to render only means there is no thumbnail.
+async fn store_thumbnail<C: ConnectionTrait>(
+    db: &C,
+    file_store: &FileStore,
+    match_id: manager::MatchId,
+    game_id: i64,
+    replay_gz: Vec<u8>,
+) {
+    let thumbnail =
+        match tokio::task::spawn_blocking(move || render_thumbnail(&replay_gz, game_id)).await {
+            Ok(Ok(Some(thumbnail))) => thumbnail,
+            Ok(Ok(None)) => return,
+            Ok(Err(e)) => {
+                log::error!("Failed to render thumbnail for match {match_id}: {e:?}");
+                return;
+            }
+            Err(e) => {
+                log::error!("Rendering thumbnail for match {match_id} panicked: {e:?}");
+                return;
+            }
+        };
+    let _ = file_store
+        .write(
+            db,
+            file_store::Requester::System,
+            db::files::Model {
+                owning_entity: db::common::EntityKind::Match,
+                owning_id: Some(match_id),
+                name: THUMBNAIL_FILE_NAME.to_owned(),
+                kind: db::files::Kind::MatchThumbnail,
+                compression: db::files::Compression::Uncompressed,
+                content: Some(thumbnail.into_bytes()),
+                content_type: db::files::ContentType::Svg,
+                ..Default::default()
+            },
+        )
+        .await
+        .inspect_err(|e| {
+            log::error!("Failed to save thumbnail: {e:?}");
+        });
+}
+
+// Renders the final position of the match, None if the game server did not
+// emit any visualization events.
+fn render_thumbnail(replay_gz: &[u8], game_id: i64) -> anyhow::Result<Option<String>> {
+    let mut log = vec![];
+    flate2::read::GzDecoder::new(replay_gz)
+        .take(MAX_THUMBNAIL_LOG_BYTES + 1)
+        .read_to_end(&mut log)
+        .context("Failed to decompress the replay")?;
+    if log.len() as u64 > MAX_THUMBNAIL_LOG_BYTES {
+        return Err(anyhow!(
+            "The replay is over {MAX_THUMBNAIL_LOG_BYTES} bytes"
+        ));
+    }
+    let events = String::from_utf8_lossy(&log)
+        .lines()
+        .filter_map(visualize::vis_payload)
+        .filter_map(|e| visualize::decode_event(e).ok())
+        .take(MAX_THUMBNAIL_EVENTS + 1)
+        .collect::<Vec<_>>();
+    if events.is_empty() {
+        return Ok(None);
+    }
+    if events.len() > MAX_THUMBNAIL_EVENTS {
+        return Err(anyhow!("The replay has over {MAX_THUMBNAIL_EVENTS} events"));
+    }
+    let objects = events
+        .iter()
+        .filter(|e| matches!(e.event, visualize::Event::Create { .. }))
+        .count();
+    if objects > MAX_THUMBNAIL_OBJECTS {
+        return Err(anyhow!("The replay creates {objects} objects"));
+    }
+    // Thumbnails are public, show only what any spectator sees.
+    let replay = visualize::Replay::new(events).for_viewer(&visualize::Viewer::Spectator);
+    let duration = replay.duration;
+    // Relative to /files/match/{match_id}/thumbnail.svg.
+    let assets_url = format!("../../game/{game_id}/");
+    Ok(Some(render::render_svg(replay, duration, &assets_url)))
+}
+
 async fn match_update_from_result(
     match_id: manager::MatchId,
     num_players: usize,
@@ -686,6 +993,7 @@ async fn compile_impl<C: ConnectionTrait + TransactionTrait>(
         db::programs::Status::CompilationFailed => db::bots::SystemStatus::Deactivated,
         _ => db::bots::SystemStatus::Unknown,
     };
+    let compiled = compilation_status.is_ok();
     db.transaction(|txn| {
         let id = program.id;
         Box::pin(async move {
@@ -697,7 +1005,10 @@ async fn compile_impl<C: ConnectionTrait + TransactionTrait>(
                 ..Default::default()
             };
             db::programs::Entity::update(writeback).exec(txn).await?;
-            db_mark_bots_of_program(txn, program.id, bot_status).await
+            db_mark_bots_of_program(txn, program.id, bot_status).await?;
+            db_promote_versions_of_program(txn, program.id, compiled)
+                .await
+                .map_err(|e| DbErr::Custom(format!("{e:#}")))
         })
     })
     .await?;
@@ -774,7 +1085,9 @@ pub async fn cleanup_matches_batch<C: ConnectionTrait + TransactionTrait>(
             .filter(
                 Condition::all()
                     .add(db::matches::Column::EndTime.lt(threshold))
-                    .add(db::matches::Column::GameId.eq(game_id)),
+                    .add(db::matches::Column::GameId.eq(game_id))
+                    // The results of tournaments are computed from their matches.
+                    .add(db::matches::Column::TournamentId.is_null()),
             )
             .limit(config.max_delete_matches_num)
             .all(db)
@@ -818,10 +1131,19 @@ pub async fn cleanup_matches_batch<C: ConnectionTrait + TransactionTrait>(
     Ok(())
 }
 
-pub async fn scheduling_round<C: ConnectionTrait>(
+pub async fn scheduling_round<C: ConnectionTrait + TransactionTrait>(
     db: &C,
     config: &crate::scheduler::Config,
 ) -> anyhow::Result<()> {
+    let _ = season::scheduling_round(db)
+        .await
+        .inspect_err(|e| log::error!("Failed to update seasons: {e:?}"));
+    // Tournament rounds do not wait for the rest of the scheduled work.
+    if config.run_matches {
+        let _ = tournament::scheduling_round(db, config.match_run_default_priority)
+            .await
+            .inspect_err(|e| log::error!("Failed to schedule tournament rounds: {e:?}"));
+    }
     let scheduled_work = db::work_items::Entity::find()
         .filter(db::work_items::Column::Status.eq(db::work_items::Status::Scheduled))
         .all(db)
@@ -840,12 +1162,28 @@ pub async fn scheduling_round<C: ConnectionTrait>(
             .all(db)
             .await
             .context("Failed to fetch active games")?;
+        let mut capacity = config.max_scheduled_work_items - scheduled_work.len();
         for game_id in active_games {
-            let _ = schedule_match_for_game(db, game_id, config.match_run_default_priority)
+            let num_matches = season::matches_per_round(db, game_id)
                 .await
-                .inspect_err(|e| {
-                    log::error!("Failed to schedule match for game {game_id}: {e:?}");
-                });
+                .inspect_err(|e| log::error!("{e:?}"))
+                .unwrap_or(1);
+            for i in 0..num_matches {
+                // Every game gets a match, the extra ones of seasons in
+                // evaluation only fill the remaining capacity.
+                if i > 0 && capacity == 0 {
+                    break;
+                }
+                if schedule_match_for_game(db, game_id, config.match_run_default_priority)
+                    .await
+                    .inspect_err(|e| {
+                        log::error!("Failed to schedule match for game {game_id}: {e:?}");
+                    })
+                    .is_ok()
+                {
+                    capacity = capacity.saturating_sub(1);
+                }
+            }
         }
     }
 
@@ -903,6 +1241,63 @@ pub async fn schedule_match_for_game<C: ConnectionTrait>(
     Ok(())
 }
 
+// A match of exactly these bots, in this order, requested by the account.
+pub async fn schedule_challenge<C: ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+    bot_ids: &[i64],
+    requester_id: i64,
+    priority: i64,
+) -> anyhow::Result<()> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    let work_item = db::work_items::ActiveModel {
+        game_id: Set(Some(game_id)),
+        creation_time: Set(now),
+        work_type: Set(db::work_items::WorkType::RunMatch),
+        status: Set(db::work_items::Status::Scheduled),
+        priority: Set(priority),
+        bot_ids: Set(Some(
+            bot_ids
+                .iter()
+                .map(|id| id.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+        )),
+        requester_id: Set(Some(requester_id)),
+        ..Default::default()
+    };
+    db::work_items::Entity::insert(work_item)
+        .exec(db)
+        .await
+        .context(format!(
+            "Failed to insert work item for a challenge by account {requester_id}"
+        ))?;
+    Ok(())
+}
+
+pub async fn schedule_stats_recalculation<C: ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+    priority: i64,
+) -> anyhow::Result<()> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    let work_item = db::work_items::ActiveModel {
+        game_id: Set(Some(game_id)),
+        creation_time: Set(now),
+        work_type: Set(db::work_items::WorkType::RecalculateStats),
+        status: Set(db::work_items::Status::Scheduled),
+        priority: Set(priority),
+        ..Default::default()
+    };
+    db::work_items::Entity::insert(work_item)
+        .exec(db)
+        .await
+        .context(format!(
+            "Failed to insert work item for recalculating stats of game {game_id}"
+        ))?;
+    Ok(())
+}
+
 async fn schedule_compilation<C: ConnectionTrait>(
     db: &C,
     program_id: i64,
@@ -1007,9 +1402,18 @@ async fn run_work_item<C: ConnectionTrait + TransactionTrait>(
             let Some(game_id) = work_item.game_id else {
                 return Err(anyhow!("No game_id in RunMatch work item."));
             };
-            let selected_players = choose_match_for_game(db, game_id).await?;
+            let selected_players = match_players(db, game_id, work_item.bot_ids.as_deref()).await?;
+            let tournament = work_item.tournament_id.zip(work_item.tournament_round);
             // TODO: propagate the match id into work_items.
-            run_match(man, db, file_store, &selected_players, match_runner_config).await
+            run_match(
+                man,
+                db,
+                file_store,
+                &selected_players,
+                tournament,
+                match_runner_config,
+            )
+            .await
         }
         db::work_items::WorkType::Compilation => {
             let Some(program_id) = work_item.program_id else {
@@ -1017,6 +1421,36 @@ async fn run_work_item<C: ConnectionTrait + TransactionTrait>(
             };
             ensure_compiled(man.as_ref(), db, file_store, program_id).await
         }
+        db::work_items::WorkType::RecalculateStats => {
+            let Some(game_id) = work_item.game_id else {
+                return Err(anyhow!("No game_id in RecalculateStats work item."));
+            };
+            let rebuild = recalculate::recalculate(db, game_id).await?;
+            log::info!(
+                "Recalculated stats of game {game_id}, {} of {} bots changed, {} deleted matches dropped",
+                rebuild.diffs.iter().filter(|d| d.changed()).count(),
+                rebuild.diffs.len(),
+                rebuild.deleted_matches
+            );
+            Ok(())
+        }
+    }
+}
+
+// The bots picked by the work item, e.g. for a challenge, or the ones the
+// matchmaking picks.
+async fn match_players<C: ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+    bot_ids: Option<&str>,
+) -> anyhow::Result<Vec<i64>> {
+    match bot_ids {
+        Some(bot_ids) => bot_ids
+            .split(',')
+            .map(|id| id.parse::<i64>())
+            .collect::<Result<Vec<_>, _>>()
+            .context(format!("Invalid bot ids in work item: {bot_ids}")),
+        None => choose_match_for_game(db, game_id).await,
     }
 }
 
@@ -1044,3 +1478,33 @@ pub async fn read_source_code<C: ConnectionTrait>(
     ))?;
     file.content.ok_or(anyhow!("File content missing"))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_db;
+
+    #[tokio::test]
+    async fn work_item_bots() {
+        let db = test_db::new().await;
+        test_db::game(&db, 1, 2..=2, db::games::RatingSystem::Elo).await;
+        test_db::bot(&db, 1, 1, true).await;
+        test_db::bot(&db, 2, 1, true).await;
+        test_db::bot(&db, 3, 1, false).await;
+        // Exactly the bots of the work item, in order, active or not.
+        assert_eq!(
+            match_players(&db, 1, Some("3,1")).await.unwrap(),
+            vec![3, 1]
+        );
+        assert_eq!(match_players(&db, 1, Some("2")).await.unwrap(), vec![2]);
+        for malformed in ["", "1,", "1,x", "1;2", " 1,2"] {
+            assert!(
+                match_players(&db, 1, Some(malformed)).await.is_err(),
+                "{malformed:?}"
+            );
+        }
+        let mut chosen = match_players(&db, 1, None).await.unwrap();
+        chosen.sort();
+        assert_eq!(chosen, vec![1, 2]);
+    }
+}
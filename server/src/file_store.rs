@@ -8,7 +8,7 @@ use sea_orm::{
 
 pub use crate::acl::Requester;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct FileStore {}
 
 #[derive(Debug, Display, Eq, PartialEq)]
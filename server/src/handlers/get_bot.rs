@@ -0,0 +1,412 @@
+use crate::chart;
+use crate::confidence;
+use crate::engine;
+use crate::handlers::prelude::*;
+use crate::source_diff;
+
+// Unchanged lines shown around the changes of a diff.
+const DIFF_CONTEXT: usize = 3;
+
+#[derive(Deserialize)]
+struct BotQuery {
+    // Versions to compare.
+    diff_from: Option<i32>,
+    diff_to: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct BotVersionTmplData {
+    version: i32,
+    language: String,
+    uploaded: String,
+    status: String,
+    active: bool,
+    can_promote: bool,
+    matches: usize,
+    average_score: String,
+    win_rate: String,
+}
+
+#[derive(Serialize)]
+struct DiffTmplData {
+    from: i32,
+    to: i32,
+    text: String,
+}
+
+#[derive(Serialize)]
+struct OpponentTmplData {
+    bot_id: i64,
+    owner: String,
+    name: String,
+    matches: i64,
+    wins: i64,
+    draws: i64,
+    losses: i64,
+    average_score_diff: String,
+}
+
+#[derive(Serialize)]
+struct BotTmplData<'a> {
+    base_url_path: &'a str,
+    bot_id: i64,
+    title: String,
+    game_id: i64,
+    game: String,
+    owner: String,
+    language: String,
+    status: String,
+    created: String,
+    matches_played: i64,
+    average_score: String,
+    mean_score_interval: String,
+    win_rate_interval: String,
+    provisional: bool,
+    rating: String,
+    opponents: Vec<OpponentTmplData>,
+    versions: Vec<BotVersionTmplData>,
+    can_edit: bool,
+    languages: Vec<LanguageChoice>,
+    diff: Option<DiffTmplData>,
+}
+
+// The bot and its game, if the requester can see the game.
+async fn readable_bot(
+    req: &HttpRequest,
+    session: &Session,
+    bot_id: i64,
+) -> Result<(db::bots::Model, db::games::Model), AppHttpError> {
+    let state = server_state(req)?;
+    let Some((bot, Some(game))) = db::bots::Entity::find_by_id(bot_id)
+        .find_also_related(db::games::Entity)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let requester = requester(req, session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(game.id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    Ok((bot, game))
+}
+
+// Matches, scores and win shares of the matches kept, by the program that
+// played them.
+async fn db_version_results(
+    db: &DatabaseConnection,
+    bot_id: i64,
+) -> Result<HashMap<i64, Vec<(f64, f64)>>, DbErr> {
+    let own = db::match_participations::Entity::find()
+        .filter(db::match_participations::Column::BotId.eq(bot_id))
+        .filter(db::match_participations::Column::Score.is_not_null())
+        .all(db)
+        .await?;
+    let mut scores = HashMap::<i64, Vec<db::match_participations::Model>>::new();
+    for p in db::match_participations::Entity::find()
+        .filter(db::match_participations::Column::MatchId.is_in(own.iter().map(|p| p.match_id)))
+        .all(db)
+        .await?
+    {
+        scores.entry(p.match_id).or_default().push(p);
+    }
+    let mut results = HashMap::<i64, Vec<(f64, f64)>>::new();
+    for p in own {
+        let (Some(program_id), Some(score), Some(all)) =
+            (p.program_id, p.score, scores.get(&p.match_id))
+        else {
+            continue;
+        };
+        let Some(all) = all.iter().map(|p| p.score).collect::<Option<Vec<_>>>() else {
+            continue;
+        };
+        let Some(i) = scores[&p.match_id]
+            .iter()
+            .position(|o| o.ingame_player == p.ingame_player)
+        else {
+            continue;
+        };
+        let win = confidence::win_shares(&all)[i];
+        results.entry(program_id).or_default().push((score, win));
+    }
+    Ok(results)
+}
+
+async fn source_diff(
+    state: &ServerState<'_>,
+    versions: &[(db::bot_versions::Model, Option<db::programs::Model>)],
+    from: i32,
+    to: i32,
+) -> Result<Option<DiffTmplData>, AppHttpError> {
+    let program_of = |v| {
+        versions
+            .iter()
+            .find(|(bv, _)| bv.version == v)
+            .map(|(bv, _)| bv.program_id)
+    };
+    let (Some(old), Some(new)) = (program_of(from), program_of(to)) else {
+        return Ok(None);
+    };
+    let mut sources = Vec::with_capacity(2);
+    for program_id in [old, new] {
+        let source = engine::read_source_code(&state.file_store, &state.db, program_id)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to read the source of program {program_id}: {e:?}");
+                AppHttpError::Internal
+            })?;
+        sources.push(String::from_utf8_lossy(&source).into_owned());
+    }
+    Ok(Some(DiffTmplData {
+        from,
+        to,
+        text: source_diff::unified(&sources[0], &sources[1], DIFF_CONTEXT),
+    }))
+}
+
+#[get("/bot/{bot_id}")]
+async fn get_bot(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+    query: web::Query<BotQuery>,
+) -> HttpResult {
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    let (bot, game) = readable_bot(&req, &session, bot_id).await?;
+    // Only the owner sees the sources.
+    let can_edit = acl::check(
+        &state.db,
+        requester(&req, &session).await?,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Bot,
+        Some(bot_id),
+    )
+    .await
+    .is_ok();
+    let program = db::programs::Entity::find_by_id(bot.program_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch the program of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let stats = db::stats_history::Entity::find()
+        .filter(
+            sea_orm::Condition::all()
+                .add(db::stats_history::Column::Latest.eq(true))
+                .add(db::stats_history::Column::BotId.eq(bot_id)),
+        )
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch stats of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let mut head_to_head = db::head_to_head::Entity::find()
+        .filter(db::head_to_head::Column::BotId.eq(bot_id))
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch head-to-head results of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    head_to_head.sort_by_key(|h| std::cmp::Reverse(h.matches));
+    let opponents = db::bots::Entity::find()
+        .filter(db::bots::Column::Id.is_in(head_to_head.iter().map(|h| h.opponent_id)))
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch opponents of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|b| (b.id, b))
+        .collect::<HashMap<_, _>>();
+    let usernames = db_usernames(
+        &state.db,
+        opponents
+            .values()
+            .map(|b| b.owner_id)
+            .chain(std::iter::once(bot.owner_id)),
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to fetch owners of opponents of bot {bot_id}: {e:?}");
+        AppHttpError::Internal
+    })?;
+    let opponents = head_to_head
+        .into_iter()
+        .filter_map(|h| {
+            let o = opponents.get(&h.opponent_id)?;
+            Some(OpponentTmplData {
+                bot_id: o.id,
+                owner: usernames.get(&o.owner_id).cloned().unwrap_or_default(),
+                name: o.name.clone(),
+                matches: h.matches,
+                wins: h.wins,
+                draws: h.draws,
+                losses: h.losses,
+                average_score_diff: format!("{:+.2}", h.score_diff / h.matches.max(1) as f64),
+            })
+        })
+        .collect();
+    let (matches_played, average_score, rating) = match &stats {
+        Some(st) => (
+            st.total_matches,
+            format!("{:.2}", st.total_score / st.total_matches.max(1) as f64),
+            st.conservative_rating
+                .filter(|_| st.rating_system == Some(game.rating_system))
+                .map_or("unrated".to_owned(), |r| format!("{r:.1}")),
+        ),
+        None => (0, "-".to_owned(), "unrated".to_owned()),
+    };
+    let conf = stats.as_ref().map(confidence::of);
+    let mut versions = db::bot_versions::Entity::find()
+        .filter(db::bot_versions::Column::BotId.eq(bot_id))
+        .find_also_related(db::programs::Entity)
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch versions of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    versions.sort_by_key(|(v, _)| std::cmp::Reverse(v.version));
+    let results = db_version_results(&state.db, bot_id).await.map_err(|e| {
+        log::error!("Failed to fetch results of versions of bot {bot_id}: {e:?}");
+        AppHttpError::Internal
+    })?;
+    let diff = match (can_edit, query.diff_from, query.diff_to) {
+        (true, Some(from), Some(to)) => source_diff(state, &versions, from, to).await?,
+        _ => None,
+    };
+    let versions = versions
+        .iter()
+        .map(|(v, p)| {
+            let active = v.program_id == bot.program_id;
+            let compiled = p
+                .as_ref()
+                .is_some_and(|p| p.status == db::programs::Status::CompilationSucceeded);
+            let results = results.get(&v.program_id).map_or(&[][..], Vec::as_slice);
+            let n = results.len();
+            let (total, wins) = results
+                .iter()
+                .fold((0., 0.), |(s, w), (score, win)| (s + score, w + win));
+            BotVersionTmplData {
+                version: v.version,
+                language: p
+                    .as_ref()
+                    .map_or(String::new(), |p| format!("{:?}", p.language)),
+                uploaded: format_time(v.creation_time),
+                status: match (active, v.promote_on_success, p) {
+                    (true, _, _) => "Active".to_owned(),
+                    (_, true, Some(p)) => format!("{:?}, active once compiled", p.status),
+                    (_, _, Some(p)) => format!("{:?}", p.status),
+                    (_, _, None) => "No program".to_owned(),
+                },
+                active,
+                can_promote: can_edit && compiled && !active,
+                matches: n,
+                average_score: if n > 0 {
+                    format!("{:.2}", total / n as f64)
+                } else {
+                    "-".to_owned()
+                },
+                win_rate: if n > 0 {
+                    format!("{:.0}%", wins / n as f64 * 100.)
+                } else {
+                    "-".to_owned()
+                },
+            }
+        })
+        .collect();
+    let html = state
+        .tmpl
+        .render(
+            "bot",
+            &BotTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                bot_id,
+                owner: usernames.get(&bot.owner_id).cloned().unwrap_or_default(),
+                title: bot.name.clone(),
+                game_id: game.id,
+                game: game.name,
+                language: program
+                    .as_ref()
+                    .map_or(String::new(), |p| format!("{:?}", p.language)),
+                status: bot_status(&bot, program.as_ref()),
+                created: format_time(bot.creation_time),
+                matches_played,
+                average_score,
+                mean_score_interval: conf.as_ref().map_or("-".to_owned(), mean_score_text),
+                win_rate_interval: conf.as_ref().map_or("-".to_owned(), win_rate_text),
+                provisional: confidence::is_provisional(matches_played),
+                rating,
+                opponents,
+                versions,
+                can_edit,
+                languages: language_choices(program.as_ref().map(|p| p.language)),
+                diff,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'bot' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
+
+// Average score of the bot after each of its recent matches.
+#[get("/bot/{bot_id}/history.svg")]
+async fn get_bot_history_chart(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+) -> HttpResult {
+    const MAX_POINTS: u64 = 500;
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    readable_bot(&req, &session, bot_id).await?;
+    let mut history = db::stats_history::Entity::find()
+        .filter(
+            sea_orm::Condition::all()
+                .add(db::stats_history::Column::BotId.eq(bot_id))
+                .add(db::stats_history::Column::TotalMatches.gt(0)),
+        )
+        .order_by_desc(db::stats_history::Column::Id)
+        .limit(MAX_POINTS)
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch stats history of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    history.reverse();
+    let points = history
+        .iter()
+        .map(|st| (st.update_time.unix_timestamp() as f64, average_score(st)))
+        .collect::<Vec<_>>();
+    let labels = match (history.first(), history.last()) {
+        (Some(first), Some(last)) => [
+            format_time(first.update_time),
+            format_time(last.update_time),
+        ],
+        _ => Default::default(),
+    };
+    let svg = chart::line_chart(&points, [&labels[0], &labels[1]]);
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::IMAGE_SVG))
+        .body(svg))
+}
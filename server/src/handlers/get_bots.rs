@@ -2,6 +2,7 @@ use crate::handlers::prelude::*;
 
 #[derive(Clone, Serialize)]
 struct BotRowTmplData {
+    bot_id: i64,
     name: String,
     game: String,
     owner: String,
@@ -85,6 +86,7 @@ pub async fn get_bots(
                 p.status_update_time.max(b.status_update_time)
             });
             BotRowTmplData {
+                bot_id: b.id,
                 name: b.name,
                 game: game.map_or(String::new(), |g| g.name.clone()),
                 language: program.map_or(String::new(), |p| format!("{:?}", p.language)),
@@ -0,0 +1,139 @@
+use crate::handlers::prelude::*;
+
+#[derive(Serialize)]
+struct OpponentChoice {
+    bot_id: i64,
+    owner: String,
+    name: String,
+    reference: bool,
+    rating: String,
+}
+
+#[derive(Serialize)]
+struct ChallengeTmplData<'a> {
+    base_url_path: &'a str,
+    title: String,
+    bot_id: i64,
+    game_id: i64,
+    game: String,
+    max_opponents: i32,
+    min_opponents: i32,
+    challenges_left: u64,
+    opponents: Vec<OpponentChoice>,
+}
+
+// The form for the owner of the bot to start a match against opponents of
+// their choice.
+#[get("/challenge/{bot_id}")]
+async fn get_challenge(req: HttpRequest, session: Session, path: web::Path<i64>) -> HttpResult {
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Bot,
+        Some(bot_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let Requester::Account(account_id) = requester else {
+        return Err(AppHttpError::Unauthenticated);
+    };
+    let Some((bot, Some(game))) = db::bots::Entity::find_by_id(bot_id)
+        .find_also_related(db::games::Entity)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    // The opponents are public, like the rest of the bots of the game.
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(game.id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let bots = db_active_bots_of_game(&state.db, game.id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots for game {} from db: {e:?}", game.id);
+            AppHttpError::Internal
+        })?;
+    let usernames = db_usernames(&state.db, bots.iter().map(|b| b.owner_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch owners of bots of game {}: {e:?}", game.id);
+            AppHttpError::Internal
+        })?;
+    let stats = db_bot_stats(&state.db, bots.iter().map(|b| b.id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots stats for game {}: {e:?}", game.id);
+            AppHttpError::Internal
+        })?;
+    let since = time::OffsetDateTime::now_utc() - time::Duration::HOUR;
+    let (pending, recent) = db_challenges_of_account(&state.db, account_id, since)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch challenges of account {account_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let limits = &state.config.challenges;
+    let challenges_left = limits
+        .max_pending
+        .saturating_sub(pending)
+        .min(limits.max_per_hour.saturating_sub(recent));
+    let mut opponents = bots
+        .into_iter()
+        .filter(|b| b.id != bot_id)
+        .map(|b| {
+            let rating = stats
+                .get(&b.id)
+                .and_then(|st| current_rating(st, game.rating_system));
+            (b, rating)
+        })
+        .collect::<Vec<_>>();
+    opponents.sort_by(|(_, x), (_, y)| y.partial_cmp(x).unwrap_or(std::cmp::Ordering::Equal));
+    let opponents = opponents
+        .into_iter()
+        .map(|(b, rating)| OpponentChoice {
+            bot_id: b.id,
+            owner: usernames.get(&b.owner_id).cloned().unwrap_or_default(),
+            name: b.name,
+            reference: b.is_reference_bot == Some(true),
+            rating: rating.map_or("unrated".to_owned(), |r| format!("{r:.1}")),
+        })
+        .collect();
+    let html = state
+        .tmpl
+        .render(
+            "challenge",
+            &ChallengeTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                title: bot.name,
+                bot_id,
+                game_id: game.id,
+                game: game.name,
+                max_opponents: game.max_players - 1,
+                min_opponents: (game.min_players - 1).max(1),
+                challenges_left,
+                opponents,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'challenge' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
@@ -11,6 +11,13 @@ struct EditGameTmplData<'a> {
     min_players: i32,
     max_players: i32,
     param: String,
+    protocol_version: String,
+    reveal_hidden_events: bool,
+    rating_systems: Vec<RatingSystemChoice>,
+    matchmakings: Vec<MatchmakingChoice>,
+    distinct_owners: bool,
+    tournament_formats: Vec<TournamentFormatChoice>,
+    rating_carry_overs: Vec<RatingCarryOverChoice>,
     languages: Vec<LanguageChoice>,
     bots: Vec<BotOnEditGamePageTmplData>,
     program: Option<ProgramTmplData>,
@@ -105,6 +112,13 @@ pub async fn get_edit_game(
             min_players: 1,
             max_players: 1,
             param: "".to_owned(),
+            protocol_version: "".to_owned(),
+            reveal_hidden_events: false,
+            rating_systems: rating_system_choices(db::games::RatingSystem::TrueSkill),
+            matchmakings: matchmaking_choices(db::games::Matchmaking::Balanced),
+            distinct_owners: false,
+            tournament_formats: tournament_format_choices(),
+            rating_carry_overs: rating_carry_over_choices(),
             languages: language_choices(None),
             bots: vec![],
             program: None,
@@ -162,6 +176,15 @@ pub async fn get_edit_game(
                 min_players: g.min_players,
                 max_players: g.max_players,
                 param: g.param.unwrap_or_default(),
+                protocol_version: g
+                    .protocol_version
+                    .map_or_else(String::new, |v| v.to_string()),
+                reveal_hidden_events: g.reveal_hidden_events,
+                rating_systems: rating_system_choices(g.rating_system),
+                matchmakings: matchmaking_choices(g.matchmaking),
+                distinct_owners: g.distinct_owners,
+                tournament_formats: tournament_format_choices(),
+                rating_carry_overs: rating_carry_over_choices(),
                 languages: language_choices(language),
                 bots,
                 program,
@@ -1,5 +1,6 @@
 use crate::handlers::prelude::*;
 use actix_web::http::header::CONTENT_SECURITY_POLICY;
+use proglad_api::visualize;
 
 #[get("/files/{entity_kind}/{entity_id}")]
 pub async fn get_files_nameless(
@@ -43,6 +44,13 @@ async fn get_files_impl(
         .read(&state.db, requester, entity_kind, Some(entity_id), &name)
         .await
         .map_err(file_error_to_http_error)?;
+    let file = if entity_kind == db::common::EntityKind::Match
+        && file.kind == db::files::Kind::MatchReplay
+    {
+        replay_for_requester(&state.db, requester, entity_id, file).await?
+    } else {
+        file
+    };
     // TODO: the client might not be expecting compressed output.
     // Check the request headers for whether they accept compressed.
     let mime = match file.content_type {
@@ -65,3 +73,65 @@ async fn get_files_impl(
         .append_header((CONTENT_SECURITY_POLICY, "script-src 'none'"))
         .body(file.content.unwrap_or_default()))
 }
+
+// Drops the replay events addressed to other players, unless the game reveals
+// them or the requester can edit the game.
+pub async fn replay_for_requester(
+    db: &DatabaseConnection,
+    requester: Requester,
+    match_id: i64,
+    file: db::files::Model,
+) -> Result<db::files::Model, AppHttpError> {
+    let (_, game) = db::matches::Entity::find_by_id(match_id)
+        .find_also_related(db::games::Entity)
+        .one(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch match {match_id}: {e}");
+            AppHttpError::Internal
+        })?
+        .ok_or(AppHttpError::NotFound)?;
+    let Some(game) = game else {
+        log::error!("Game of match {match_id} not found");
+        return Err(AppHttpError::Internal);
+    };
+    if game.reveal_hidden_events
+        || acl::check(
+            db,
+            requester,
+            db::acls::AccessType::Write,
+            db::common::EntityKind::Game,
+            Some(game.id),
+        )
+        .await
+        .is_ok()
+    {
+        return Ok(file);
+    }
+    let players = match requester {
+        Requester::Account(account_id) => db::match_participations::Entity::find()
+            .filter(db::match_participations::Column::MatchId.eq(match_id))
+            .find_also_related(db::bots::Entity)
+            .all(db)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to fetch participants of match {match_id}: {e}");
+                AppHttpError::Internal
+            })?
+            .into_iter()
+            .filter(|(_, b)| b.as_ref().is_some_and(|b| b.owner_id == account_id))
+            .map(|(p, _)| p.ingame_player as usize)
+            .collect(),
+        _ => vec![],
+    };
+    let viewer = if players.is_empty() {
+        visualize::Viewer::Spectator
+    } else {
+        visualize::Viewer::Players(players)
+    };
+    let mut file = FileStore::decompress(file).map_err(file_error_to_http_error)?;
+    file.content = file.content.map(|c| {
+        visualize::strip_hidden_events(&String::from_utf8_lossy(&c), &viewer).into_bytes()
+    });
+    Ok(file)
+}
@@ -1,4 +1,6 @@
+use crate::confidence;
 use crate::handlers::prelude::*;
+use crate::season;
 
 #[derive(Serialize, Clone)]
 struct BotOnGamePageTmplData {
@@ -7,6 +9,17 @@ struct BotOnGamePageTmplData {
     name: String,
     matches_played: usize,
     average_score: String,
+    rating: String,
+    provisional: bool,
+}
+
+// Results of the bot against each of the top bots, in the order of the
+// columns.
+#[derive(Serialize, Clone)]
+struct HeadToHeadRowTmplData {
+    bot_id: i64,
+    name: String,
+    cells: Vec<String>,
 }
 
 #[derive(Serialize, Clone)]
@@ -15,6 +28,20 @@ struct ReferenceBotTmplData {
     source_url: String,
 }
 
+#[derive(Serialize, Clone)]
+struct TournamentOnGamePageTmplData {
+    tournament_id: i64,
+    name: String,
+    status: String,
+}
+
+#[derive(Serialize, Clone)]
+struct SeasonOnGamePageTmplData {
+    season_id: i64,
+    name: String,
+    phase: &'static str,
+}
+
 #[derive(Serialize, Clone)]
 struct GameTmplData<'a> {
     base_url_path: &'a str,
@@ -22,9 +49,13 @@ struct GameTmplData<'a> {
     title: String,
     url: String,
     bots: Vec<BotOnGamePageTmplData>,
+    head_to_head: Vec<HeadToHeadRowTmplData>,
+    rating_system: &'static str,
     active_bots_num: usize,
     reference_bots: Vec<ReferenceBotTmplData>,
     matches: Vec<BriefMatchTmplData>,
+    tournaments: Vec<TournamentOnGamePageTmplData>,
+    seasons: Vec<SeasonOnGamePageTmplData>,
     languages: Vec<LanguageChoice>,
     show_edit: bool,
 }
@@ -111,6 +142,8 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
         .collect::<Vec<_>>();
     const MAX_BOTS: usize = 10;
     const MAX_MATCHES: u64 = 10;
+    const MAX_TOURNAMENTS: u64 = 5;
+    const MAX_SEASONS: u64 = 5;
     let stats = db_bot_stats(&state.db, bots.iter().map(|b| b.id))
         .await
         .map_err(|e| {
@@ -118,11 +151,28 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
             AppHttpError::Internal
         })?;
     let bots = HashMap::<i64, db::bots::Model>::from_iter(bots.into_iter().map(|b| (b.id, b)));
+    // Provisional bots and bots not rated with the current system yet go last.
     let mut bot_scores = stats
         .iter()
-        .map(|(i, s)| (average_score(s), i))
+        .map(|(i, s)| {
+            let key = (
+                !confidence::is_provisional(s.total_matches),
+                current_rating(s, game.rating_system),
+                average_score(s),
+            );
+            (key, i)
+        })
         .collect::<Vec<_>>();
-    bot_scores.sort_by(|x, y| x.partial_cmp(y).unwrap().reverse());
+    // Best first. NaN ratings or averages are ordered by total_cmp instead of
+    // panicking.
+    bot_scores.sort_by(|((xe, xr, xa), xi), ((ye, yr, ya), yi)| {
+        let rating = |r: &Option<f64>| r.unwrap_or_default();
+        ye.cmp(xe)
+            .then(yr.is_some().cmp(&xr.is_some()))
+            .then(rating(yr).total_cmp(&rating(xr)))
+            .then(ya.total_cmp(xa))
+            .then(yi.cmp(xi))
+    });
     let bots = bot_scores
         .iter()
         .take(MAX_BOTS)
@@ -138,9 +188,34 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
                 name: b.name.clone(),
                 matches_played: st.total_matches as usize,
                 average_score: format!("{:.2}", average_score(st)),
+                rating: rating_text(st, game.rating_system),
+                provisional: confidence::is_provisional(st.total_matches),
             }
         })
         .collect::<Vec<_>>();
+    let head_to_head = db_head_to_head(&state.db, bots.iter().map(|b| b.bot_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch head-to-head results for game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let head_to_head = if head_to_head.is_empty() {
+        vec![]
+    } else {
+        bots.iter()
+            .map(|b| HeadToHeadRowTmplData {
+                bot_id: b.bot_id,
+                name: b.name.clone(),
+                cells: bots
+                    .iter()
+                    .map(|o| match head_to_head.get(&(b.bot_id, o.bot_id)) {
+                        Some(h) => format!("{}-{}-{}", h.wins, h.draws, h.losses),
+                        None => String::new(),
+                    })
+                    .collect(),
+            })
+            .collect::<Vec<_>>()
+    };
     let matches = db_recent_matches(
         &state.db,
         db::matches::Column::GameId.eq(game_id).into_condition(),
@@ -158,6 +233,44 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
             system_message: m.system_message,
         })
         .collect::<Vec<_>>();
+    let tournaments = db::tournaments::Entity::find()
+        .filter(db::tournaments::Column::GameId.eq(game_id))
+        .order_by_desc(db::tournaments::Column::Id)
+        .limit(MAX_TOURNAMENTS)
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tournaments of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|t| TournamentOnGamePageTmplData {
+            tournament_id: t.id,
+            name: t.name,
+            status: format!("{:?}", t.status),
+        })
+        .collect::<Vec<_>>();
+    let now = time::OffsetDateTime::now_utc();
+    let seasons = db::seasons::Entity::find()
+        .filter(db::seasons::Column::GameId.eq(game_id))
+        .order_by_desc(db::seasons::Column::StartTime)
+        .limit(MAX_SEASONS)
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch seasons of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|s| SeasonOnGamePageTmplData {
+            season_id: s.id,
+            phase: match s.status {
+                db::seasons::Status::Finished => season::Phase::Ended.as_str(),
+                _ => season::phase(&s, now).as_str(),
+            },
+            name: s.name,
+        })
+        .collect::<Vec<_>>();
     let html = state
         .tmpl
         .render(
@@ -169,8 +282,12 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
                 url,
                 active_bots_num,
                 bots,
+                head_to_head,
+                rating_system: game.rating_system.as_str(),
                 reference_bots,
                 matches,
+                tournaments,
+                seasons,
                 languages: language_choices(None),
                 show_edit: game.status == db::games::Status::InDevelopment,
             },
@@ -184,46 +301,21 @@ async fn get_game(req: HttpRequest, session: Session, path: web::Path<i64>) -> H
         .body(html))
 }
 
-fn average_score(st: &db::stats_history::Model) -> f64 {
-    st.total_score / (st.total_matches.max(1) as f64)
-}
-
-async fn db_bot_stats(
+// Results between the given bots, by (bot, opponent).
+async fn db_head_to_head(
     db: &DatabaseConnection,
-    ids: impl ExactSizeIterator<Item = i64>,
-) -> Result<HashMap<i64, db::stats_history::Model>, DbErr> {
-    let stats = db::stats_history::Entity::find()
+    ids: impl Iterator<Item = i64> + Clone,
+) -> Result<HashMap<(i64, i64), db::head_to_head::Model>, DbErr> {
+    let results = db::head_to_head::Entity::find()
         .filter(
             sea_orm::Condition::all()
-                .add(db::stats_history::Column::Latest.eq(true))
-                .add(db::stats_history::Column::BotId.is_in(ids)),
+                .add(db::head_to_head::Column::BotId.is_in(ids.clone()))
+                .add(db::head_to_head::Column::OpponentId.is_in(ids)),
         )
         .all(db)
         .await?;
-    Ok(stats.into_iter().map(|st| (st.bot_id, st)).collect())
-}
-
-async fn db_active_bots_of_game(
-    db: &DatabaseConnection,
-    game_id: i64,
-) -> Result<Vec<db::bots::Model>, DbErr> {
-    let condition = sea_orm::Condition::all()
-        .add(db::bots::Column::GameId.eq(game_id))
-        .add(db::bots::Column::SystemStatus.eq(db::bots::SystemStatus::Ok))
-        .add(db::bots::Column::OwnerSetStatus.eq(db::bots::OwnerSetStatus::Active));
-    db::bots::Entity::find().filter(condition).all(db).await
-}
-
-async fn db_languages_of_programs(
-    db: &DatabaseConnection,
-    ids: impl IntoIterator<Item = i64>,
-) -> Result<Vec<(i64, db::programs::Language)>, DbErr> {
-    db::programs::Entity::find()
-        .select_only()
-        .column(db::programs::Column::Id)
-        .column(db::programs::Column::Language)
-        .filter(db::programs::Column::Id.is_in(ids))
-        .into_tuple()
-        .all(db)
-        .await
+    Ok(results
+        .into_iter()
+        .map(|h| ((h.bot_id, h.opponent_id), h))
+        .collect())
 }
@@ -0,0 +1,240 @@
+use crate::confidence;
+use crate::handlers::prelude::*;
+
+const PAGE_SIZE: usize = 50;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Sort {
+    #[default]
+    Rating,
+    AverageScore,
+    Matches,
+}
+
+impl Sort {
+    fn as_str(self) -> &'static str {
+        match self {
+            Sort::Rating => "Rating",
+            Sort::AverageScore => "Average score",
+            Sort::Matches => "Matches played",
+        }
+    }
+    // As in the query.
+    fn value(self) -> &'static str {
+        match self {
+            Sort::Rating => "rating",
+            Sort::AverageScore => "average_score",
+            Sort::Matches => "matches",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct LeaderboardQuery {
+    // From 1.
+    page: Option<usize>,
+    sort: Option<Sort>,
+    language: Option<String>,
+    owner: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SortChoice {
+    name: &'static str,
+    value: &'static str,
+    selected: bool,
+}
+
+#[derive(Serialize)]
+struct LeaderboardRowTmplData {
+    rank: usize,
+    bot_id: i64,
+    owner: String,
+    name: String,
+    language: &'static str,
+    rating: String,
+    average_score: String,
+    mean_score_interval: String,
+    win_rate_interval: String,
+    matches_played: i64,
+    provisional: bool,
+}
+
+#[derive(Serialize)]
+struct LeaderboardTmplData<'a> {
+    base_url_path: &'a str,
+    title: String,
+    game_id: i64,
+    rating_system: &'static str,
+    sorts: Vec<SortChoice>,
+    languages: Vec<LanguageChoice>,
+    owner: String,
+    bots: Vec<LeaderboardRowTmplData>,
+    page: usize,
+    num_pages: usize,
+    prev_page: Option<usize>,
+    next_page: Option<usize>,
+}
+
+#[get("/leaderboard/{game_id}")]
+async fn get_leaderboard(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+    query: web::Query<LeaderboardQuery>,
+) -> HttpResult {
+    let game_id = *path;
+    let state = server_state(&req)?;
+    let Some(game) = db::games::Entity::find_by_id(game_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch game {game_id} from db: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let sort = query.sort.unwrap_or_default();
+    let language = match query.language.as_deref() {
+        None | Some("") => None,
+        Some(l) => Some(parse_language(l)?),
+    };
+    let owner = query.owner.as_deref().unwrap_or_default().trim();
+    let bots = db_active_bots_of_game(&state.db, game_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots for game {game_id} from db: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let usernames = db_usernames(&state.db, bots.iter().map(|b| b.owner_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch owners of bots of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let languages = db_languages_of_programs(&state.db, bots.iter().map(|b| b.program_id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch languages of bots of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let stats = db_bot_stats(&state.db, bots.iter().map(|b| b.id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots stats for game {game_id} from db: {e:?}");
+            AppHttpError::Internal
+        })?;
+    // Ranks are over all of the bots, the filters only pick the rows.
+    // Provisional bots rank after the others whatever the sort.
+    let mut ranked = bots
+        .iter()
+        .map(|b| {
+            let st = stats.get(&b.id);
+            let rating = st.and_then(|st| current_rating(st, game.rating_system));
+            let average = st.map(average_score);
+            let matches = st.map_or(0, |st| st.total_matches);
+            (b, st, rating, average, matches)
+        })
+        .collect::<Vec<_>>();
+    ranked.sort_by(|x, y| {
+        let key = |(_, _, rating, average, matches): &(_, _, Option<f64>, Option<f64>, i64)| {
+            let established = !confidence::is_provisional(*matches);
+            match sort {
+                Sort::Rating => (established, *rating, *average),
+                Sort::AverageScore => (established, *average, *rating),
+                Sort::Matches => (established, Some(*matches as f64), *rating),
+            }
+        };
+        // NaN ratings or averages are ordered by total_cmp instead of panicking.
+        let cmp = |x: Option<f64>, y: Option<f64>| match (x, y) {
+            (Some(x), Some(y)) => x.total_cmp(&y),
+            _ => x.is_some().cmp(&y.is_some()),
+        };
+        let (x, y) = (key(x), key(y));
+        y.0.cmp(&x.0).then(cmp(y.1, x.1)).then(cmp(y.2, x.2))
+    });
+    let rows = ranked
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (b, ..))| {
+            language.is_none_or(|l| languages.get(&b.program_id) == Some(&l))
+                && (owner.is_empty()
+                    || usernames.get(&b.owner_id).map(String::as_str) == Some(owner))
+        })
+        .collect::<Vec<_>>();
+    let num_pages = rows.len().div_ceil(PAGE_SIZE).max(1);
+    let page = query.page.unwrap_or(1).clamp(1, num_pages);
+    let rows = rows
+        .into_iter()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|(i, (b, st, _, average, matches))| {
+            let conf = st.map(confidence::of);
+            LeaderboardRowTmplData {
+                rank: i + 1,
+                bot_id: b.id,
+                owner: usernames.get(&b.owner_id).cloned().unwrap_or_default(),
+                name: b.name.clone(),
+                language: languages
+                    .get(&b.program_id)
+                    .map_or("Unknown Language", |l| l.as_str()),
+                rating: st.map_or("unrated".to_owned(), |st| {
+                    rating_text(st, game.rating_system)
+                }),
+                average_score: average.map_or("-".to_owned(), |a| format!("{a:.2}")),
+                mean_score_interval: conf.as_ref().map_or("-".to_owned(), mean_score_text),
+                win_rate_interval: conf.as_ref().map_or("-".to_owned(), win_rate_text),
+                matches_played: matches,
+                provisional: confidence::is_provisional(matches),
+            }
+        })
+        .collect();
+    let sorts = [Sort::Rating, Sort::AverageScore, Sort::Matches]
+        .into_iter()
+        .map(|s| SortChoice {
+            name: s.as_str(),
+            value: s.value(),
+            selected: s == sort,
+        })
+        .collect();
+    let html = state
+        .tmpl
+        .render(
+            "leaderboard",
+            &LeaderboardTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                title: game.name,
+                game_id,
+                rating_system: game.rating_system.as_str(),
+                sorts,
+                languages: language_choices(language),
+                owner: owner.to_owned(),
+                bots: rows,
+                page,
+                num_pages,
+                prev_page: (page > 1).then(|| page - 1),
+                next_page: (page < num_pages).then(|| page + 1),
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'leaderboard' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
@@ -0,0 +1,135 @@
+use crate::handlers::prelude::*;
+use crate::recalculate;
+
+#[derive(Serialize)]
+struct StatsDiffRowTmplData {
+    bot_id: i64,
+    name: String,
+    matches_before: String,
+    matches_after: String,
+    average_score_before: String,
+    average_score_after: String,
+    rating_before: String,
+    rating_after: String,
+}
+
+#[derive(Serialize)]
+struct RecalculateStatsTmplData<'a> {
+    base_url_path: &'a str,
+    title: String,
+    game_id: i64,
+    num_bots: usize,
+    num_changed: usize,
+    deleted_matches: usize,
+    scheduled: bool,
+    changes: Vec<StatsDiffRowTmplData>,
+}
+
+fn summary_texts(s: &Option<recalculate::Summary>) -> (String, String, String) {
+    match s {
+        Some(s) => (
+            s.matches.to_string(),
+            format!("{:.2}", s.average_score),
+            s.rating.map_or("unrated".to_owned(), |r| format!("{r:.1}")),
+        ),
+        None => ("-".to_owned(), "-".to_owned(), "-".to_owned()),
+    }
+}
+
+// What recalculating the stats of the game would change, to check before
+// scheduling it. Whoever may edit the game, its authors as well as the admins,
+// may rewrite the stats of all of its bots.
+#[get("/recalculate_stats/{game_id}")]
+async fn get_recalculate_stats(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+) -> HttpResult {
+    let game_id = *path;
+    let state = server_state(&req)?;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Game,
+        Some(game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let Some(game) = db::games::Entity::find_by_id(game_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch game {game_id} from db: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let rebuild = recalculate::preview(&state.db, game_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to preview stats recalculation of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let names = db::bots::Entity::find()
+        .filter(db::bots::Column::GameId.eq(game_id))
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots for game {game_id} from db: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|b| (b.id, b.name))
+        .collect::<HashMap<_, _>>();
+    let scheduled = db_stats_recalculation_pending(&state.db, game_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check scheduled stats recalculation of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    let num_bots = rebuild.diffs.len();
+    let changes = rebuild
+        .diffs
+        .into_iter()
+        .filter(|d| d.changed())
+        .map(|d| {
+            let (matches_before, average_score_before, rating_before) = summary_texts(&d.before);
+            let (matches_after, average_score_after, rating_after) = summary_texts(&d.after);
+            StatsDiffRowTmplData {
+                bot_id: d.bot_id,
+                name: names.get(&d.bot_id).cloned().unwrap_or_default(),
+                matches_before,
+                matches_after,
+                average_score_before,
+                average_score_after,
+                rating_before,
+                rating_after,
+            }
+        })
+        .collect::<Vec<_>>();
+    let html = state
+        .tmpl
+        .render(
+            "recalculate_stats",
+            &RecalculateStatsTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                title: game.name,
+                game_id,
+                num_bots,
+                num_changed: changes.len(),
+                deleted_matches: rebuild.deleted_matches,
+                scheduled,
+                changes,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'recalculate_stats' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
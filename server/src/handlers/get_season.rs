@@ -0,0 +1,110 @@
+use crate::handlers::prelude::*;
+use crate::season;
+
+#[derive(Serialize)]
+struct SeasonStandingTmplData {
+    rank: usize,
+    owner: String,
+    name: String,
+    rating: String,
+    matches_played: i64,
+    average_score: String,
+}
+
+#[derive(Serialize)]
+struct SeasonTmplData<'a> {
+    base_url_path: &'a str,
+    title: String,
+    game_id: i64,
+    phase: &'static str,
+    start_time: String,
+    submission_deadline: String,
+    end_time: String,
+    rating_carry_over: &'static str,
+    finished: bool,
+    standings: Vec<SeasonStandingTmplData>,
+}
+
+#[get("/season/{season_id}")]
+async fn get_season(req: HttpRequest, session: Session, path: web::Path<i64>) -> HttpResult {
+    let season_id = *path;
+    let state = server_state(&req)?;
+    let Some((s, Some(game))) = db::seasons::Entity::find_by_id(season_id)
+        .find_also_related(db::games::Entity)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch season {season_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(game.id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    // Finished seasons show what was archived at the end, upcoming ones have
+    // no standings yet.
+    let standings = match (&s.final_standings, s.status) {
+        (Some(st), _) => serde_json::from_str::<Vec<season::Standing>>(st).map_err(|e| {
+            log::error!("Invalid final standings of season {season_id}: {e}");
+            AppHttpError::Internal
+        })?,
+        (None, db::seasons::Status::Running) => {
+            season::standings(&state.db, &game).await.map_err(|e| {
+                log::error!("{e:?}");
+                AppHttpError::Internal
+            })?
+        }
+        (None, _) => vec![],
+    };
+    let standings = standings
+        .into_iter()
+        .enumerate()
+        .map(|(i, st)| SeasonStandingTmplData {
+            rank: i + 1,
+            owner: st.owner,
+            name: st.name,
+            rating: st
+                .rating
+                .map_or("unrated".to_owned(), |r| format!("{r:.1}")),
+            matches_played: st.matches_played,
+            average_score: format!("{:.2}", st.average_score),
+        })
+        .collect();
+    let phase = match s.status {
+        db::seasons::Status::Finished => season::Phase::Ended,
+        _ => season::phase(&s, time::OffsetDateTime::now_utc()),
+    };
+    let html = state
+        .tmpl
+        .render(
+            "season",
+            &SeasonTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                title: format!("{}: {}", game.name, s.name),
+                game_id: game.id,
+                phase: phase.as_str(),
+                start_time: format_time(s.start_time),
+                submission_deadline: format_time(s.submission_deadline),
+                end_time: format_time(s.end_time),
+                rating_carry_over: s.rating_carry_over.as_str(),
+                finished: s.status == db::seasons::Status::Finished,
+                standings,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'season' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
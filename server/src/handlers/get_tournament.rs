@@ -0,0 +1,161 @@
+use crate::handlers::prelude::*;
+use crate::tournament;
+
+#[derive(Serialize)]
+struct StandingTmplData {
+    rank: usize,
+    name: String,
+    seed: i32,
+    points: f64,
+    wins: i32,
+    draws: i32,
+    losses: i32,
+    eliminated_in_round: Option<i32>,
+}
+
+#[derive(Serialize)]
+struct TournamentMatchTmplData {
+    match_id: i64,
+    players: String,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct RoundTmplData {
+    round: i32,
+    matches: Vec<TournamentMatchTmplData>,
+}
+
+#[derive(Serialize)]
+struct TournamentTmplData<'a> {
+    base_url_path: &'a str,
+    title: String,
+    game_id: i64,
+    format: &'static str,
+    status: String,
+    current_round: i32,
+    rounds: i32,
+    finished: bool,
+    standings: Vec<StandingTmplData>,
+    rounds_played: Vec<RoundTmplData>,
+}
+
+#[get("/tournament/{tournament_id}")]
+async fn get_tournament(req: HttpRequest, session: Session, path: web::Path<i64>) -> HttpResult {
+    let tournament_id = *path;
+    let state = server_state(&req)?;
+    let Some(t) = db::tournaments::Entity::find_by_id(tournament_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch tournament {tournament_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(t.game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let entries = tournament::db_entries(&state.db, tournament_id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    let matches = tournament::db_match_results(&state.db, tournament_id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    // Finished tournaments show the standings as they were at the end.
+    let standings = match &t.final_standings {
+        Some(s) => serde_json::from_str::<Vec<tournament::Standing>>(s).map_err(|e| {
+            log::error!("Invalid final standings of tournament {tournament_id}: {e}");
+            AppHttpError::Internal
+        })?,
+        None => {
+            // The current round is still being played.
+            let results = matches.iter().map(|(_, r)| r.clone()).collect::<Vec<_>>();
+            tournament::standings(t.format, &entries, &results, t.current_round - 1)
+        }
+    };
+    let standings = standings
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| StandingTmplData {
+            rank: i + 1,
+            name: s.name,
+            seed: s.seed,
+            points: s.points,
+            wins: s.wins,
+            draws: s.draws,
+            losses: s.losses,
+            eliminated_in_round: s.eliminated_in_round,
+        })
+        .collect();
+    let names = entries
+        .iter()
+        .map(|e| (e.bot_id, e.name.as_str()))
+        .collect::<HashMap<_, _>>();
+    let mut rounds_played = (1..=t.current_round)
+        .map(|round| RoundTmplData {
+            round,
+            matches: vec![],
+        })
+        .collect::<Vec<_>>();
+    for (m, r) in matches.iter() {
+        let Some(round) = rounds_played.get_mut(r.round as usize - 1) else {
+            continue;
+        };
+        let players = r
+            .scores
+            .iter()
+            .map(|(bot_id, score)| {
+                let name = names.get(bot_id).copied().unwrap_or("deleted bot");
+                match score {
+                    Some(s) => format!("{name} ({s})"),
+                    None => name.to_owned(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" vs ");
+        round.matches.push(TournamentMatchTmplData {
+            match_id: m.id,
+            players,
+            status: m.system_message.clone(),
+        });
+    }
+    let html = state
+        .tmpl
+        .render(
+            "tournament",
+            &TournamentTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                title: t.name,
+                game_id: t.game_id,
+                format: t.format.as_str(),
+                status: format!("{:?}", t.status),
+                current_round: t.current_round,
+                rounds: t.rounds,
+                finished: t.status == db::tournaments::Status::Finished,
+                standings,
+                rounds_played,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render 'tournament' template: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
@@ -1,20 +1,167 @@
 use crate::handlers::prelude::*;
+use actix_web::http::header::CONTENT_DISPOSITION;
+use base64::prelude::*;
 
 #[derive(Serialize)]
 struct VisualizerTmplData<'a> {
     base_url_path: &'a str,
     match_id: i64,
     match_data: MatchTmplData,
+    embedded: Option<EmbeddedVisualizerTmplData>,
+}
+
+// Everything the exported page needs to play offline. `js`, `log` and
+// `assets` are JS string literals.
+#[derive(Serialize)]
+struct EmbeddedVisualizerTmplData {
+    css: String,
+    js: String,
+    wasm_base64: String,
+    log: String,
+    assets: String,
+}
+
+#[derive(Serialize)]
+struct CompareTmplData<'a> {
+    base_url_path: &'a str,
+    a: MatchTmplData,
+    b: MatchTmplData,
+}
+
+#[derive(Deserialize)]
+struct CompareQuery {
+    a: i64,
+    b: i64,
 }
 
 #[get("/visualizer/{match_id}")]
 pub async fn get_visualizer(req: HttpRequest, path: web::Path<i64>) -> HttpResult {
     let state = server_state(&req)?;
-    let matches = db::matches::Entity::find_by_id(*path)
+    let html = render_visualizer(state, *path, None).await?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
+
+// A single HTML file with the visualizer, the replay and the game images.
+#[get("/visualizer/{match_id}/export")]
+pub async fn get_visualizer_export(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+) -> HttpResult {
+    let requester = requester(&req, &session).await?;
+    let state = server_state(&req)?;
+    let match_id = *path;
+    let replay = state
+        .file_store
+        .read(
+            &state.db,
+            requester,
+            db::common::EntityKind::Match,
+            Some(match_id),
+            "",
+        )
+        .await
+        .map_err(file_error_to_http_error)?;
+    let replay =
+        super::get_files::replay_for_requester(&state.db, requester, match_id, replay).await?;
+    let replay = FileStore::decompress(replay).map_err(file_error_to_http_error)?;
+    let log = String::from_utf8_lossy(&replay.content.unwrap_or_default()).into_owned();
+    let game_id = db::matches::Entity::find_by_id(match_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get match {match_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .ok_or(AppHttpError::NotFound)?
+        .game_id;
+    let assets = embedded_assets(&state.db, &state.file_store, requester, game_id).await?;
+    let dir = state.config.fs_root_dir.join("static/visualizer");
+    let read = |name: &'static str| {
+        let path = dir.join(name);
+        async move {
+            tokio::fs::read(&path).await.map_err(|e| {
+                log::error!("Failed to read {path:?}: {e}");
+                AppHttpError::Internal
+            })
+        }
+    };
+    let embedded = EmbeddedVisualizerTmplData {
+        css: String::from_utf8_lossy(&read("visualizer.css").await?).into_owned(),
+        js: js_string(&String::from_utf8_lossy(
+            &read("proglad_visualizer.js").await?,
+        )),
+        wasm_base64: BASE64_STANDARD.encode(read("proglad_visualizer_bg.wasm").await?),
+        log: js_string(&log),
+        assets: js_string(&serde_json::to_string(&assets).map_err(|e| {
+            log::error!("Failed to encode assets: {e}");
+            AppHttpError::Internal
+        })?),
+    };
+    let html = render_visualizer(state, match_id, Some(embedded)).await?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .append_header((
+            CONTENT_DISPOSITION,
+            format!("attachment; filename=\"match-{match_id}.html\""),
+        ))
+        .body(html))
+}
+
+// Two matches side by side on one timeline. Images come from the game of the
+// first one.
+#[get("/compare")]
+pub async fn get_compare(req: HttpRequest, q: web::Query<CompareQuery>) -> HttpResult {
+    let state = server_state(&req)?;
+    let matches = db::matches::Entity::find()
+        .filter(db::matches::Column::Id.is_in([q.a, q.b]))
+        .all(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to get matches {} and {}: {e:?}", q.a, q.b);
+            AppHttpError::Internal
+        })?;
+    let mut match_data = match_tmpl_data(&state.db, &matches, |_| false).await?;
+    let mut take = |id: i64| {
+        let i = match_data
+            .iter()
+            .position(|m| m.match_id == id)
+            .ok_or(AppHttpError::NotFound)?;
+        Ok::<_, AppHttpError>(match_data.swap_remove(i))
+    };
+    let a = take(q.a)?;
+    let b = if q.a == q.b { a.clone() } else { take(q.b)? };
+    let html = state
+        .tmpl
+        .render(
+            "visualizer_compare",
+            &CompareTmplData {
+                base_url_path: &state.config.site_base_url_path,
+                a,
+                b,
+            },
+        )
+        .map_err(|e| {
+            log::error!("Failed to render template: {e}");
+            AppHttpError::Internal
+        })?;
+    Ok(HttpResponse::Ok()
+        .append_header(ContentType(mime::TEXT_HTML))
+        .body(html))
+}
+
+async fn render_visualizer(
+    state: &ServerState<'_>,
+    match_id: i64,
+    embedded: Option<EmbeddedVisualizerTmplData>,
+) -> Result<String, AppHttpError> {
+    let matches = db::matches::Entity::find_by_id(match_id)
         .all(&state.db)
         .await
         .map_err(|e| {
-            log::error!("Failed to get match {}: {e:?}", *path);
+            log::error!("Failed to get match {match_id}: {e:?}");
             AppHttpError::NotFound
         })?;
     let match_data = match_tmpl_data(&state.db, &matches, |_| false).await?;
@@ -25,21 +172,167 @@ pub async fn get_visualizer(req: HttpRequest, path: web::Path<i64>) -> HttpResul
         );
         return Err(AppHttpError::Internal);
     }
-    let html = state
+    state
         .tmpl
         .render(
             "visualizer",
             &VisualizerTmplData {
                 base_url_path: &state.config.site_base_url_path,
-                match_id: *path,
+                match_id,
                 match_data: match_data.into_iter().next().unwrap(),
+                embedded,
             },
         )
         .map_err(|e| {
             log::error!("Failed to render template: {e}");
             AppHttpError::Internal
-        })?;
-    Ok(HttpResponse::Ok()
-        .append_header(ContentType(mime::TEXT_HTML))
-        .body(html))
+        })
+}
+
+// Data URLs of the game images the requester can read, by file name.
+async fn embedded_assets(
+    db: &DatabaseConnection,
+    file_store: &FileStore,
+    requester: Requester,
+    game_id: i64,
+) -> Result<HashMap<String, String>, AppHttpError> {
+    let names = db::files::Entity::find()
+        .filter(db::files::Column::OwningEntity.eq(db::common::EntityKind::Game))
+        .filter(db::files::Column::OwningId.eq(Some(game_id)))
+        .filter(db::files::Column::Kind.eq(db::files::Kind::StaticContent))
+        .all(db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to list files of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|f| f.name)
+        .filter(|n| proglad_api::visualize::validate_asset_name(n).is_ok());
+    let mut assets = HashMap::new();
+    for name in names {
+        let Ok(file) = file_store
+            .read(
+                db,
+                requester,
+                db::common::EntityKind::Game,
+                Some(game_id),
+                &name,
+            )
+            .await
+        else {
+            continue;
+        };
+        let mime = match file.content_type {
+            db::files::ContentType::Png => mime::IMAGE_PNG,
+            db::files::ContentType::Svg => mime::IMAGE_SVG,
+            _ => continue,
+        };
+        let file = FileStore::decompress(file).map_err(file_error_to_http_error)?;
+        let content = BASE64_STANDARD.encode(file.content.unwrap_or_default());
+        assets.insert(name, format!("data:{mime};base64,{content}"));
+    }
+    Ok(assets)
+}
+
+// A JS string literal that can be put into a <script> element.
+fn js_string(s: &str) -> String {
+    serde_json::Value::from(s)
+        .to_string()
+        .replace('<', "\\u003c")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sea_orm_migration::MigratorTrait;
+
+    #[test]
+    fn js_string_escapes_script_end() {
+        let s = "a\"b</script><script>alert(1)</script>\n";
+        let lit = js_string(s);
+        assert!(!lit.contains('<'), "{lit}");
+        assert_eq!(serde_json::from_str::<String>(&lit).unwrap(), s);
+    }
+
+    #[test]
+    fn export_page_keeps_the_log_inside_the_script() {
+        let mut tmpl = handlebars::Handlebars::new();
+        tmpl.set_strict_mode(true);
+        tmpl.register_templates_directory("templates", Default::default())
+            .unwrap();
+        let log = "vis {\"t\":0,\"log\":{\"line\":\"</script><script>alert(1)\"}}";
+        let html = tmpl
+            .render(
+                "visualizer",
+                &VisualizerTmplData {
+                    base_url_path: "",
+                    match_id: 3,
+                    match_data: MatchTmplData {
+                        match_id: 3,
+                        creation_time: String::new(),
+                        game_id: 1,
+                        game_name: "g".to_owned(),
+                        participations: vec![],
+                        duration: String::new(),
+                        system_message: String::new(),
+                        has_thumbnail: false,
+                    },
+                    embedded: Some(EmbeddedVisualizerTmplData {
+                        css: String::new(),
+                        js: js_string("export default function() {}"),
+                        wasm_base64: String::new(),
+                        log: js_string(log),
+                        assets: js_string("{}"),
+                    }),
+                },
+            )
+            .unwrap();
+        assert!(!html.contains("<script>alert"), "{html}");
+        assert!(html.contains(&js_string(log)));
+        assert!(!html.contains("/static/visualizer/proglad_visualizer.js"));
+        assert!(!html.contains("/export"));
+    }
+
+    #[tokio::test]
+    async fn embedded_assets_are_data_urls() {
+        let db = sea_orm::Database::connect("sqlite::memory:")
+            .await
+            .expect("Failed to open in-memory sqlite DB.");
+        migration::Migrator::up(&db, None)
+            .await
+            .expect("Applying initial DB migrations failed");
+        let store = FileStore::new();
+        let file = |name: &str, content_type, kind| db::files::Model {
+            owning_entity: db::common::EntityKind::Game,
+            owning_id: Some(1),
+            name: name.to_owned(),
+            content_type,
+            kind,
+            content: Some(b"<svg/>".to_vec()),
+            ..Default::default()
+        };
+        use db::files::{ContentType, Kind};
+        for f in [
+            file("knight.svg", ContentType::Svg, Kind::StaticContent),
+            file("icon.svg", ContentType::Svg, Kind::StaticContent),
+            file("rules.html", ContentType::Html, Kind::StaticContent),
+            file("game.rs", ContentType::Svg, Kind::SourceCode),
+        ] {
+            store.write(&db, Requester::System, f).await.unwrap();
+        }
+        let assets = embedded_assets(&db, &store, Requester::System, 1)
+            .await
+            .unwrap();
+        assert_eq!(
+            assets,
+            HashMap::from([(
+                "knight.svg".to_owned(),
+                format!(
+                    "data:image/svg+xml;base64,{}",
+                    BASE64_STANDARD.encode("<svg/>")
+                )
+            )])
+        );
+    }
 }
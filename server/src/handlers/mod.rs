@@ -1,17 +1,29 @@
 pub mod prelude; // TODO: not pub
 pub mod tmpl_data; // TODO: not pub
 
+pub mod get_bot;
 pub mod get_bots;
+pub mod get_challenge;
 pub mod get_edit_game;
 pub mod get_files;
 pub mod get_game;
 pub mod get_games;
 pub mod get_index;
+pub mod get_leaderboard;
 pub mod get_logout;
 pub mod get_matches;
+pub mod get_recalculate_stats;
+pub mod get_season;
+pub mod get_tournament;
 pub mod get_visualizer;
 pub mod kratos_hooks;
+pub mod post_challenge;
 pub mod post_create_bot;
+pub mod post_create_bot_version;
+pub mod post_create_season;
+pub mod post_create_tournament;
 pub mod post_edit_bot;
 pub mod post_edit_game;
+pub mod post_promote_bot_version;
+pub mod post_recalculate_stats;
 pub mod post_schedule_match;
@@ -0,0 +1,304 @@
+use crate::handlers::prelude::*;
+
+#[post("/challenge/{bot_id}")]
+pub async fn post_challenge(
+    req: HttpRequest,
+    session: Session,
+    // The opponents are repeated "opponent" fields.
+    form: web::Form<Vec<(String, String)>>,
+    path: web::Path<i64>,
+) -> Result<HttpResponse<()>, AppHttpError> {
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Bot,
+        Some(bot_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let Requester::Account(account_id) = requester else {
+        return Err(AppHttpError::Unauthenticated);
+    };
+    let Some((_, Some(game))) = db::bots::Entity::find_by_id(bot_id)
+        .find_also_related(db::games::Entity)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let opponents = form
+        .iter()
+        .filter(|(k, _)| k == "opponent")
+        .map(|(_, v)| v.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppHttpError::InvalidChallenge(format!("invalid opponent: {e}")))?;
+    // The opponents are public, like the rest of the bots of the game.
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Read,
+        db::common::EntityKind::Game,
+        Some(game.id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let game_id = game.id;
+    challenge(
+        &state.db,
+        state.config.challenges.clone(),
+        game,
+        account_id,
+        bot_id,
+        opponents,
+    )
+    .await?;
+    Ok(web::Redirect::to(format!(
+        "{}/matches?game_id={}&account_id={account_id}",
+        state.config.site_base_url_path, game_id
+    ))
+    .see_other()
+    .respond_to(&req))
+}
+
+// Schedules the match if the account may start it now. The limits are checked
+// in the same transaction, so that concurrent requests can't exceed them.
+async fn challenge(
+    db: &DatabaseConnection,
+    limits: crate::config::Challenges,
+    game: db::games::Model,
+    account_id: i64,
+    bot_id: i64,
+    opponents: Vec<i64>,
+) -> Result<(), AppHttpError> {
+    db.transaction(|txn| {
+        Box::pin(async move {
+            let players =
+                challenge_players(txn, &limits, &game, account_id, bot_id, opponents).await?;
+            crate::engine::schedule_challenge(txn, game.id, &players, account_id, limits.priority)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to schedule a challenge of bot {bot_id}: {e:?}");
+                    AppHttpError::Internal
+                })
+        })
+    })
+    .await
+    .map_err(|e| match e {
+        sea_orm::TransactionError::Connection(e) => {
+            log::error!("Failed to schedule a challenge of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        }
+        sea_orm::TransactionError::Transaction(e) => e,
+    })
+}
+
+// The bots of the match, starting with the challenger, if the account may start
+// it now.
+async fn challenge_players<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    limits: &crate::config::Challenges,
+    game: &db::games::Model,
+    account_id: i64,
+    bot_id: i64,
+    opponents: Vec<i64>,
+) -> Result<Vec<i64>, AppHttpError> {
+    let num_players = 1 + opponents.len() as i32;
+    if num_players < game.min_players.max(2) || num_players > game.max_players {
+        return Err(AppHttpError::InvalidChallenge(format!(
+            "pick from {} to {} opponents",
+            (game.min_players - 1).max(1),
+            game.max_players - 1
+        )));
+    }
+    let mut players = vec![bot_id];
+    for id in opponents {
+        if players.contains(&id) {
+            return Err(AppHttpError::InvalidChallenge(format!(
+                "bot {id} is picked twice"
+            )));
+        }
+        players.push(id);
+    }
+    let active = db_active_bots_of_game(db, game.id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bots for game {} from db: {e:?}", game.id);
+            AppHttpError::Internal
+        })?
+        .into_iter()
+        .map(|b| b.id)
+        .collect::<HashSet<_>>();
+    if let Some(id) = players.iter().find(|id| !active.contains(id)) {
+        return Err(AppHttpError::InvalidChallenge(format!(
+            "bot {id} is not active in {}",
+            game.name
+        )));
+    }
+    // Challenges count for the ratings, which are final at the end of the
+    // season.
+    let frozen = crate::season::submissions_frozen(db, game.id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    if frozen {
+        return Err(AppHttpError::InvalidChallenge(
+            "the season is in its final evaluation".to_owned(),
+        ));
+    }
+    let since = time::OffsetDateTime::now_utc() - time::Duration::HOUR;
+    let (pending, recent) = db_challenges_of_account(db, account_id, since)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch challenges of account {account_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    if pending >= limits.max_pending {
+        return Err(AppHttpError::ChallengeLimitReached(format!(
+            "{pending} of your challenges are waiting to run"
+        )));
+    }
+    if recent >= limits.max_per_hour {
+        return Err(AppHttpError::ChallengeLimitReached(format!(
+            "at most {} challenges per hour",
+            limits.max_per_hour
+        )));
+    }
+    Ok(players)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test_db;
+    use sea_orm::ActiveValue::Set;
+
+    async fn setup() -> (DatabaseConnection, db::games::Model) {
+        let db = test_db::new().await;
+        test_db::game(&db, 1, 2..=3, db::games::RatingSystem::Elo).await;
+        for bot_id in 1..=4 {
+            test_db::bot(&db, bot_id, 1, bot_id != 4).await;
+        }
+        test_db::game(&db, 2, 2..=2, db::games::RatingSystem::Elo).await;
+        test_db::bot(&db, 5, 2, true).await;
+        let game = db::games::Entity::find_by_id(1)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        (db, game)
+    }
+
+    fn invalid(res: Result<Vec<i64>, AppHttpError>) -> String {
+        match res {
+            Err(AppHttpError::InvalidChallenge(reason)) => reason,
+            res => panic!("{res:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn opponents_are_validated() {
+        let (db, game) = setup().await;
+        let limits = crate::config::Challenges::default();
+        let players =
+            |opponents: &[i64]| challenge_players(&db, &limits, &game, 1, 1, opponents.to_vec());
+        assert_eq!(players(&[3]).await.unwrap(), vec![1, 3]);
+        assert_eq!(players(&[3, 2]).await.unwrap(), vec![1, 3, 2]);
+        assert_eq!(invalid(players(&[]).await), "pick from 1 to 2 opponents");
+        assert_eq!(
+            invalid(players(&[2, 3, 2]).await),
+            "pick from 1 to 2 opponents"
+        );
+        assert_eq!(invalid(players(&[2, 2]).await), "bot 2 is picked twice");
+        assert_eq!(invalid(players(&[1]).await), "bot 1 is picked twice");
+        assert_eq!(
+            invalid(players(&[4]).await),
+            "bot 4 is not active in game 1"
+        );
+        assert_eq!(
+            invalid(players(&[5]).await),
+            "bot 5 is not active in game 1"
+        );
+        assert_eq!(
+            invalid(players(&[99]).await),
+            "bot 99 is not active in game 1"
+        );
+    }
+
+    #[tokio::test]
+    async fn not_during_season_evaluation() {
+        let (db, game) = setup().await;
+        let now = time::OffsetDateTime::now_utc();
+        db::seasons::Entity::insert(db::seasons::ActiveModel {
+            game_id: Set(1),
+            name: Set("s".to_owned()),
+            status: Set(db::seasons::Status::Running),
+            start_time: Set(now - time::Duration::HOUR * 2),
+            submission_deadline: Set(now - time::Duration::HOUR),
+            end_time: Set(now + time::Duration::HOUR),
+            rating_carry_over: Set(db::seasons::RatingCarryOver::Reset),
+            evaluation_matches: Set(1),
+            final_standings: Set(None),
+            ..Default::default()
+        })
+        .exec(&db)
+        .await
+        .unwrap();
+        let limits = crate::config::Challenges::default();
+        assert_eq!(
+            invalid(challenge_players(&db, &limits, &game, 1, 1, vec![2]).await),
+            "the season is in its final evaluation"
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limits() {
+        let (db, game) = setup().await;
+        let limits = crate::config::Challenges {
+            priority: 1500,
+            max_pending: 2,
+            max_per_hour: 3,
+        };
+        let limit_reached = |res: Result<(), AppHttpError>| match res {
+            Err(AppHttpError::ChallengeLimitReached(reason)) => reason,
+            res => panic!("{res:?}"),
+        };
+        let challenge =
+            |account_id| challenge(&db, limits.clone(), game.clone(), account_id, 1, vec![2]);
+        for _ in 0..2 {
+            challenge(1).await.unwrap();
+        }
+        assert_eq!(
+            limit_reached(challenge(1).await),
+            "2 of your challenges are waiting to run"
+        );
+
+        // Finished ones still count for the hour.
+        db::work_items::Entity::update_many()
+            .col_expr(
+                db::work_items::Column::Status,
+                sea_orm::sea_query::Expr::value(db::work_items::Status::Completed),
+            )
+            .exec(&db)
+            .await
+            .unwrap();
+        challenge(1).await.unwrap();
+        assert_eq!(
+            limit_reached(challenge(1).await),
+            "at most 3 challenges per hour"
+        );
+        // Other accounts have their own limits.
+        challenge_players(&db, &limits, &game, 2, 1, vec![2])
+            .await
+            .unwrap();
+    }
+}
@@ -37,6 +37,15 @@ pub async fn post_create_bot(
     if let Err(e) = validate_bot_name(&form.name) {
         return Err(AppHttpError::InvalidBotName(e));
     }
+    let frozen = crate::season::submissions_frozen(&state.db, game_id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    if frozen {
+        return Err(AppHttpError::SubmissionsFrozen);
+    }
     // TODO: move this thing into engine.
     let txn_result = state
         .db
@@ -0,0 +1,71 @@
+use crate::engine;
+use crate::handlers::prelude::*;
+use actix_multipart::form::{tempfile::TempFile, text::Text, MultipartForm};
+
+#[derive(Debug, MultipartForm)]
+struct CreateBotVersionForm {
+    #[multipart(limit = "64KB")]
+    file: TempFile,
+    language: Text<String>,
+}
+
+#[post("/create_bot_version/{bot_id}")]
+pub async fn post_create_bot_version(
+    MultipartForm(form): MultipartForm<CreateBotVersionForm>,
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    let language = parse_language(&form.language)?;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Bot,
+        Some(bot_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let Some(bot) = db::bots::Entity::find_by_id(bot_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let frozen = crate::season::submissions_frozen(&state.db, bot.game_id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    if frozen {
+        return Err(AppHttpError::SubmissionsFrozen);
+    }
+    let txn_result = state
+        .db
+        .transaction(|txn| {
+            let file_store = state.file_store.clone();
+            Box::pin(async move {
+                engine::create_bot_version(txn, &file_store, bot_id, form.file.file, language)
+                    .await
+                    .map_err(|e| DbErr::Custom(format!("{e:#}")))
+            })
+        })
+        .await;
+    if let Err(e) = txn_result {
+        log::error!("Failed to create a version of bot {bot_id}: {e:?}");
+        return Err(AppHttpError::Internal);
+    }
+    Ok::<_, AppHttpError>(
+        web::Redirect::to(format!("{}/bot/{bot_id}", state.config.site_base_url_path))
+            .see_other()
+            .respond_to(&req),
+    )
+}
@@ -0,0 +1,86 @@
+use crate::handlers::prelude::*;
+use sea_orm::Set;
+
+#[derive(Deserialize)]
+struct CreateSeasonForm {
+    name: String,
+    // As sent by datetime-local inputs, in UTC.
+    start_time: String,
+    submission_deadline: String,
+    end_time: String,
+    rating_carry_over: String,
+    evaluation_matches: i32,
+}
+
+fn parse_time(name: &str, value: &str) -> Result<time::OffsetDateTime, AppHttpError> {
+    let format = time::macros::format_description!("[year]-[month]-[day]T[hour]:[minute]");
+    time::PrimitiveDateTime::parse(value, &format)
+        .map(|t| t.assume_utc())
+        .map_err(|e| AppHttpError::InvalidSeason(format!("{name}: {e}")))
+}
+
+#[post("/create_season/{game_id}")]
+pub async fn post_create_season(
+    req: HttpRequest,
+    session: Session,
+    form: web::Form<CreateSeasonForm>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let requester = requester(&req, &session).await?;
+    let state = server_state(&req)?;
+    let game_id = *path;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Game,
+        Some(game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    if form.name.trim().is_empty() {
+        return Err(AppHttpError::InvalidSeason("empty name".to_owned()));
+    }
+    let start_time = parse_time("start_time", &form.start_time)?;
+    let submission_deadline = parse_time("submission_deadline", &form.submission_deadline)?;
+    let end_time = parse_time("end_time", &form.end_time)?;
+    if !(start_time <= submission_deadline && submission_deadline <= end_time) {
+        return Err(AppHttpError::InvalidSeason(
+            "the submission deadline must be between the start and the end".to_owned(),
+        ));
+    }
+    if !(1..=100).contains(&form.evaluation_matches) {
+        return Err(AppHttpError::InvalidSeason(
+            "evaluation matches must be between 1 and 100".to_owned(),
+        ));
+    }
+    let rating_carry_over = db::seasons::RatingCarryOver::try_from_value(&form.rating_carry_over)
+        .map_err(|e| AppHttpError::InvalidSeason(e.to_string()))?;
+    let season = db::seasons::ActiveModel {
+        game_id: Set(game_id),
+        name: Set(form.name.trim().to_owned()),
+        status: Set(db::seasons::Status::Upcoming),
+        start_time: Set(start_time),
+        submission_deadline: Set(submission_deadline),
+        end_time: Set(end_time),
+        rating_carry_over: Set(rating_carry_over),
+        evaluation_matches: Set(form.evaluation_matches),
+        ..Default::default()
+    };
+    let season_id = db::seasons::Entity::insert(season)
+        .exec(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to insert season for game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+        .last_insert_id;
+    Ok::<_, AppHttpError>(
+        web::Redirect::to(format!(
+            "{}/season/{season_id}",
+            state.config.site_base_url_path
+        ))
+        .see_other()
+        .respond_to(&req),
+    )
+}
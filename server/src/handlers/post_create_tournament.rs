@@ -0,0 +1,69 @@
+use crate::handlers::prelude::*;
+
+#[derive(Deserialize)]
+struct CreateTournamentForm {
+    name: String,
+    format: String,
+    // Swiss only, the other formats take as many as they need.
+    rounds: Option<String>,
+}
+
+#[post("/create_tournament/{game_id}")]
+pub async fn post_create_tournament(
+    req: HttpRequest,
+    session: Session,
+    form: web::Form<CreateTournamentForm>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let requester = requester(&req, &session).await?;
+    let state = server_state(&req)?;
+    let game_id = *path;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Game,
+        Some(game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let format = db::tournaments::Format::try_from_value(&form.format)
+        .map_err(|e| AppHttpError::InvalidTournament(e.to_string()))?;
+    if form.name.trim().is_empty() {
+        return Err(AppHttpError::InvalidTournament("empty name".to_owned()));
+    }
+    // Empty when not given.
+    let rounds = match form.rounds.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(r) => match r.parse::<i32>() {
+            Ok(r) if (1..=100).contains(&r) => Some(r),
+            _ => {
+                return Err(AppHttpError::InvalidTournament(
+                    "rounds must be between 1 and 100".to_owned(),
+                ))
+            }
+        },
+    };
+    let Some(game) = db::games::Entity::find_by_id(game_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let tournament_id =
+        crate::tournament::create_tournament(&state.db, &game, form.name.trim(), format, rounds)
+            .await
+            .map_err(|e| AppHttpError::InvalidTournament(format!("{e:#}")))?;
+    Ok::<_, AppHttpError>(
+        web::Redirect::to(format!(
+            "{}/tournament/{tournament_id}",
+            state.config.site_base_url_path
+        ))
+        .see_other()
+        .respond_to(&req),
+    )
+}
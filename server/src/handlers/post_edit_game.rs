@@ -12,6 +12,9 @@ struct EditGameForm {
     markdown_file: TempFile,
     #[multipart(limit = "128KB")]
     icon_file: TempFile,
+    // An image the visualizer can reference by its file name.
+    #[multipart(limit = "256KB")]
+    asset_file: Option<TempFile>,
     #[multipart(limit = "1KB")]
     game_name: actix_multipart::form::text::Text<String>,
     #[multipart(limit = "1KB")]
@@ -24,6 +27,17 @@ struct EditGameForm {
     min_players: actix_multipart::form::text::Text<i32>,
     #[multipart(limit = "1KB")]
     param_string: actix_multipart::form::text::Text<String>,
+    #[multipart(limit = "1KB")]
+    protocol_version: Option<actix_multipart::form::text::Text<String>>,
+    // Checkbox, only sent when checked.
+    #[multipart(limit = "1KB")]
+    reveal_hidden_events: Option<actix_multipart::form::text::Text<String>>,
+    #[multipart(limit = "1KB")]
+    rating_system: Option<actix_multipart::form::text::Text<String>>,
+    #[multipart(limit = "1KB")]
+    matchmaking: Option<actix_multipart::form::text::Text<String>>,
+    #[multipart(limit = "1KB")]
+    distinct_owners: Option<actix_multipart::form::text::Text<String>>,
 }
 
 #[derive(Deserialize)]
@@ -74,6 +88,42 @@ async fn post_edit_game(
     if max_players.is_some() && min_players.is_some() && max_players < min_players {
         max_players = min_players;
     }
+    let protocol_version = match form
+        .protocol_version
+        .as_ref()
+        .map(|v| validate_protocol_version(v))
+        .transpose()
+    {
+        Ok(v) => v.flatten(),
+        Err(e) => {
+            validation_errors.push(format!("protocol_version: {e}"));
+            None
+        }
+    };
+    let rating_system = match form
+        .rating_system
+        .as_ref()
+        .map(|v| db::games::RatingSystem::try_from_value(v))
+        .transpose()
+    {
+        Ok(v) => v,
+        Err(e) => {
+            validation_errors.push(format!("rating_system: {e}"));
+            None
+        }
+    };
+    let matchmaking = match form
+        .matchmaking
+        .as_ref()
+        .map(|v| db::games::Matchmaking::try_from_value(v))
+        .transpose()
+    {
+        Ok(v) => v,
+        Err(e) => {
+            validation_errors.push(format!("matchmaking: {e}"));
+            None
+        }
+    };
     let gameserver_source = if form.gameserver_file.size != 0 {
         Some(
             tokio::fs::read(form.gameserver_file.file.path())
@@ -101,6 +151,15 @@ async fn post_edit_game(
     }
     update.description = Set(form.description.to_string());
     update.param = Set(Some(form.param_string.as_str().to_owned()));
+    update.protocol_version = Set(protocol_version);
+    update.reveal_hidden_events = Set(form.reveal_hidden_events.is_some());
+    if let Some(rs) = rating_system {
+        update.rating_system = Set(rs);
+    }
+    if let Some(m) = matchmaking {
+        update.matchmaking = Set(m);
+    }
+    update.distinct_owners = Set(form.distinct_owners.is_some());
     let file_store = state.file_store.clone();
     let game_id = state
         .db
@@ -316,6 +375,35 @@ async fn post_edit_game(
             .map_err(file_error_to_http_error)?;
         }
     }
+    if let Some(asset_file) = form.asset_file.as_ref().filter(|f| f.size > 0) {
+        let name = asset_file.file_name.clone().unwrap_or_default();
+        validate_asset_name(&name).map_err(AppHttpError::InvalidAssetName)?;
+        let content_type = match name.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("png") => db::files::ContentType::Png,
+            Some("svg") => db::files::ContentType::Svg,
+            Some(s) => return Err(AppHttpError::UnsupportedImageType(s.to_owned())),
+            None => return Err(AppHttpError::UnsupportedImageType("".to_owned())),
+        };
+        if let Ok(asset_content) = tokio::fs::read(asset_file.file.path())
+            .await
+            .inspect_err(|e| {
+                log::error!("Failed to read temp file in the form: {e}");
+                validation_errors.push(format!("{e:?}"));
+            })
+        {
+            write_content(
+                &state.file_store,
+                &state.db,
+                requester,
+                game_id,
+                name,
+                content_type,
+                asset_content,
+            )
+            .await
+            .map_err(file_error_to_http_error)?;
+        }
+    }
     Ok::<_, AppHttpError>(
         web::Redirect::to(format!(
             "{}/edit_game?game_id={game_id}",
@@ -0,0 +1,89 @@
+use crate::engine;
+use crate::handlers::prelude::*;
+
+#[derive(Deserialize)]
+struct PromoteBotVersionForm {
+    version: i32,
+}
+
+// Makes a compiled version of the bot the active one, also to roll back.
+#[post("/promote_bot_version/{bot_id}")]
+pub async fn post_promote_bot_version(
+    req: HttpRequest,
+    session: Session,
+    form: web::Form<PromoteBotVersionForm>,
+    path: web::Path<i64>,
+) -> impl Responder {
+    let bot_id = *path;
+    let state = server_state(&req)?;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Bot,
+        Some(bot_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let Some(bot) = db::bots::Entity::find_by_id(bot_id)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    let frozen = crate::season::submissions_frozen(&state.db, bot.game_id)
+        .await
+        .map_err(|e| {
+            log::error!("{e:?}");
+            AppHttpError::Internal
+        })?;
+    if frozen {
+        return Err(AppHttpError::SubmissionsFrozen);
+    }
+    let Some((version, program)) = db::bot_versions::Entity::find()
+        .filter(db::bot_versions::Column::BotId.eq(bot_id))
+        .filter(db::bot_versions::Column::Version.eq(form.version))
+        .find_also_related(db::programs::Entity)
+        .one(&state.db)
+        .await
+        .map_err(|e| {
+            log::error!(
+                "Failed to fetch version {} of bot {bot_id}: {e:?}",
+                form.version
+            );
+            AppHttpError::Internal
+        })?
+    else {
+        return Err(AppHttpError::NotFound);
+    };
+    if program.map(|p| p.status) != Some(db::programs::Status::CompilationSucceeded) {
+        return Err(AppHttpError::InvalidBotVersion(format!(
+            "version {} has not compiled",
+            version.version
+        )));
+    }
+    state
+        .db
+        .transaction(|txn| {
+            Box::pin(async move {
+                engine::activate_bot_version(txn, &version)
+                    .await
+                    .map_err(|e| DbErr::Custom(format!("{e:#}")))
+            })
+        })
+        .await
+        .map_err(|e| {
+            log::error!("Failed to promote a version of bot {bot_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    Ok::<_, AppHttpError>(
+        web::Redirect::to(format!("{}/bot/{bot_id}", state.config.site_base_url_path))
+            .see_other()
+            .respond_to(&req),
+    )
+}
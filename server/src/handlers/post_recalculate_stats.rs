@@ -0,0 +1,48 @@
+use crate::handlers::prelude::*;
+
+// Rewrites the stats of all of the bots of the game, so it takes the same
+// access as editing the game: its authors may do it, not only the admins.
+#[post("/recalculate_stats/{game_id}")]
+pub async fn post_recalculate_stats(
+    req: HttpRequest,
+    session: Session,
+    path: web::Path<i64>,
+) -> Result<HttpResponse<()>, AppHttpError> {
+    let state = server_state(&req)?;
+    let game_id = *path;
+    let requester = requester(&req, &session).await?;
+    acl::check(
+        &state.db,
+        requester,
+        db::acls::AccessType::Write,
+        db::common::EntityKind::Game,
+        Some(game_id),
+    )
+    .await
+    .map_err(acl_check_to_http_error)?;
+    let pending = db_stats_recalculation_pending(&state.db, game_id)
+        .await
+        .map_err(|e| {
+            log::error!("Failed to check scheduled stats recalculation of game {game_id}: {e:?}");
+            AppHttpError::Internal
+        })?;
+    if pending {
+        return Err(AppHttpError::StatsRecalculationAlreadyScheduled);
+    }
+    crate::engine::schedule_stats_recalculation(
+        &state.db,
+        game_id,
+        state.config.stats_recalculation_priority,
+    )
+    .await
+    .map_err(|e| {
+        log::error!("Failed to schedule stats recalculation for game {game_id}: {e:?}");
+        AppHttpError::Internal
+    })?;
+    Ok(web::Redirect::to(format!(
+        "{}/edit_game?game_id={game_id}",
+        state.config.site_base_url_path
+    ))
+    .see_other()
+    .respond_to(&req))
+}
@@ -70,6 +70,48 @@ pub fn language_choices(selected: Option<db::programs::Language>) -> Vec<Languag
         .collect()
 }
 
+pub fn rating_system_choices(selected: db::games::RatingSystem) -> Vec<RatingSystemChoice> {
+    use sea_orm::strum::IntoEnumIterator;
+    db::games::RatingSystem::iter()
+        .map(|rs| RatingSystemChoice {
+            value: rs.to_value(),
+            name: rs.as_str().to_owned(),
+            selected: selected == rs,
+        })
+        .collect()
+}
+
+pub fn matchmaking_choices(selected: db::games::Matchmaking) -> Vec<MatchmakingChoice> {
+    use sea_orm::strum::IntoEnumIterator;
+    db::games::Matchmaking::iter()
+        .map(|m| MatchmakingChoice {
+            value: m.to_value(),
+            name: m.as_str().to_owned(),
+            selected: selected == m,
+        })
+        .collect()
+}
+
+pub fn tournament_format_choices() -> Vec<TournamentFormatChoice> {
+    use sea_orm::strum::IntoEnumIterator;
+    db::tournaments::Format::iter()
+        .map(|f| TournamentFormatChoice {
+            value: f.to_value(),
+            name: f.as_str().to_owned(),
+        })
+        .collect()
+}
+
+pub fn rating_carry_over_choices() -> Vec<RatingCarryOverChoice> {
+    use sea_orm::strum::IntoEnumIterator;
+    db::seasons::RatingCarryOver::iter()
+        .map(|c| RatingCarryOverChoice {
+            value: c.to_value(),
+            name: c.as_str().to_owned(),
+        })
+        .collect()
+}
+
 pub fn acl_check_to_http_error(err: acl::Error) -> AppHttpError {
     log::error!("Error while checking ACL: {err:?}");
     match err {
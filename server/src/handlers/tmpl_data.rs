@@ -1,3 +1,4 @@
+use crate::confidence;
 use crate::handlers::prelude::*;
 
 #[derive(Serialize, Clone, Debug)]
@@ -19,6 +20,7 @@ pub struct MatchTmplData {
     pub participations: Vec<ParticipationTmplData>,
     pub duration: String,
     pub system_message: String,
+    pub has_thumbnail: bool,
 }
 
 #[derive(Serialize, Clone)]
@@ -28,6 +30,32 @@ pub struct LanguageChoice {
     pub selected: bool,
 }
 
+#[derive(Serialize, Clone)]
+pub struct RatingSystemChoice {
+    pub name: String,
+    pub value: String,
+    pub selected: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct MatchmakingChoice {
+    pub name: String,
+    pub value: String,
+    pub selected: bool,
+}
+
+#[derive(Serialize, Clone)]
+pub struct TournamentFormatChoice {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Serialize, Clone)]
+pub struct RatingCarryOverChoice {
+    pub name: String,
+    pub value: String,
+}
+
 pub async fn match_tmpl_data(
     db: &DatabaseConnection,
     matches: &[db::matches::Model],
@@ -64,6 +92,12 @@ pub async fn match_tmpl_data(
         log::error!("Failed to fetch all participating bots: {e:?}");
         AppHttpError::Internal
     })?;
+    let thumbnails = db_matches_with_thumbnails(db, matches.iter().map(|m| m.id))
+        .await
+        .map_err(|e| {
+            log::error!("Failed to fetch match thumbnails: {e:?}");
+            AppHttpError::Internal
+        })?;
     let bot_names = HashMap::<i64, String>::from_iter(
         bot_owners_and_names
             .into_iter()
@@ -86,6 +120,7 @@ pub async fn match_tmpl_data(
                 participations: vec![],
                 duration,
                 system_message: m.system_message.clone(),
+                has_thumbnail: thumbnails.contains(&m.id),
             },
         );
     }
@@ -129,6 +164,22 @@ async fn db_participations_in_matches(
         .await
 }
 
+async fn db_matches_with_thumbnails(
+    db: &DatabaseConnection,
+    match_ids: impl IntoIterator<Item = i64>,
+) -> Result<HashSet<i64>, DbErr> {
+    let ids: Vec<Option<i64>> = db::files::Entity::find()
+        .filter(db::files::Column::OwningEntity.eq(db::common::EntityKind::Match))
+        .filter(db::files::Column::OwningId.is_in(match_ids))
+        .filter(db::files::Column::Kind.eq(db::files::Kind::MatchThumbnail))
+        .select_only()
+        .column(db::files::Column::OwningId)
+        .into_tuple()
+        .all(db)
+        .await?;
+    Ok(ids.into_iter().flatten().collect())
+}
+
 async fn db_bot_owners_and_names(
     db: &DatabaseConnection,
     ids: impl IntoIterator<Item = i64>,
@@ -179,3 +230,132 @@ pub fn format_time(time: time::OffsetDateTime) -> String {
     let format = time::macros::format_description!("[year]-[month]-[day] [hour]:[minute]:[second]");
     time.format(&format).unwrap()
 }
+
+pub fn current_rating(
+    st: &db::stats_history::Model,
+    system: db::games::RatingSystem,
+) -> Option<f64> {
+    st.conservative_rating
+        .filter(|_| st.rating_system == Some(system))
+}
+
+pub fn rating_text(st: &db::stats_history::Model, system: db::games::RatingSystem) -> String {
+    let Some(mu) = current_rating(st, system).and(st.rating) else {
+        return "unrated".to_owned();
+    };
+    match st.rating_deviation.filter(|d| *d > 0.) {
+        Some(dev) => format!("{mu:.1} ± {dev:.1}"),
+        None => format!("{mu:.1}"),
+    }
+}
+
+pub fn average_score(st: &db::stats_history::Model) -> f64 {
+    st.total_score / (st.total_matches.max(1) as f64)
+}
+
+pub fn win_rate_text(c: &confidence::Confidence) -> String {
+    c.win_rate.map_or("-".to_owned(), |i| {
+        format!("{:.0}–{:.0}%", i.low * 100., i.high * 100.)
+    })
+}
+
+pub fn mean_score_text(c: &confidence::Confidence) -> String {
+    c.mean_score
+        .map_or("-".to_owned(), |i| format!("{:.2}–{:.2}", i.low, i.high))
+}
+
+pub async fn db_bot_stats(
+    db: &DatabaseConnection,
+    ids: impl ExactSizeIterator<Item = i64>,
+) -> Result<HashMap<i64, db::stats_history::Model>, DbErr> {
+    let stats = db::stats_history::Entity::find()
+        .filter(
+            sea_orm::Condition::all()
+                .add(db::stats_history::Column::Latest.eq(true))
+                .add(db::stats_history::Column::BotId.is_in(ids)),
+        )
+        .all(db)
+        .await?;
+    Ok(stats.into_iter().map(|st| (st.bot_id, st)).collect())
+}
+
+pub async fn db_active_bots_of_game<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+) -> Result<Vec<db::bots::Model>, DbErr> {
+    let condition = sea_orm::Condition::all()
+        .add(db::bots::Column::GameId.eq(game_id))
+        .add(db::bots::Column::SystemStatus.eq(db::bots::SystemStatus::Ok))
+        .add(db::bots::Column::OwnerSetStatus.eq(db::bots::OwnerSetStatus::Active));
+    db::bots::Entity::find().filter(condition).all(db).await
+}
+
+pub async fn db_languages_of_programs(
+    db: &DatabaseConnection,
+    ids: impl IntoIterator<Item = i64>,
+) -> Result<Vec<(i64, db::programs::Language)>, DbErr> {
+    db::programs::Entity::find()
+        .select_only()
+        .column(db::programs::Column::Id)
+        .column(db::programs::Column::Language)
+        .filter(db::programs::Column::Id.is_in(ids))
+        .into_tuple()
+        .all(db)
+        .await
+}
+
+// Whether a recalculation of the stats of the game is scheduled or running.
+pub async fn db_stats_recalculation_pending(
+    db: &DatabaseConnection,
+    game_id: i64,
+) -> Result<bool, DbErr> {
+    let pending = db::work_items::Entity::find()
+        .filter(
+            sea_orm::Condition::all()
+                .add(db::work_items::Column::Status.is_in([
+                    db::work_items::Status::Scheduled,
+                    db::work_items::Status::Started,
+                ]))
+                .add(
+                    db::work_items::Column::WorkType.eq(db::work_items::WorkType::RecalculateStats),
+                )
+                .add(db::work_items::Column::GameId.eq(Some(game_id))),
+        )
+        .one(db)
+        .await?;
+    Ok(pending.is_some())
+}
+
+// Challenges of the account still waiting to run, and all since the time.
+pub async fn db_challenges_of_account<C: sea_orm::ConnectionTrait>(
+    db: &C,
+    account_id: i64,
+    since: time::OffsetDateTime,
+) -> Result<(u64, u64), DbErr> {
+    let challenges = db::work_items::Entity::find()
+        .filter(db::work_items::Column::RequesterId.eq(account_id))
+        .filter(
+            sea_orm::Condition::any()
+                .add(db::work_items::Column::CreationTime.gte(since))
+                .add(db::work_items::Column::Status.is_in([
+                    db::work_items::Status::Scheduled,
+                    db::work_items::Status::Started,
+                ])),
+        )
+        .all(db)
+        .await?;
+    let pending = challenges
+        .iter()
+        .filter(|w| {
+            matches!(
+                w.status,
+                db::work_items::Status::Scheduled | db::work_items::Status::Started
+            )
+        })
+        .count();
+    let recent = challenges
+        .iter()
+        .filter(|w| w.creation_time >= since)
+        .count();
+    Ok((pending as u64, recent as u64))
+}
@@ -0,0 +1,98 @@
+// Pairwise results between the bots of a game, added up match by match.
+
+use sea_orm::sea_query::{Expr, OnConflict};
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait, Set};
+
+use proglad_db as db;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub bot_id: i64,
+    pub opponent_id: i64,
+    pub win: bool,
+    pub draw: bool,
+    pub score_diff: f64,
+}
+
+// Results of every participant against every other one, in both directions.
+pub fn outcomes(scores: &[(i64, f64)]) -> Vec<Outcome> {
+    let mut results = vec![];
+    for (bot_id, score) in scores {
+        for (opponent_id, opponent_score) in scores {
+            if bot_id == opponent_id {
+                continue;
+            }
+            results.push(Outcome {
+                bot_id: *bot_id,
+                opponent_id: *opponent_id,
+                win: score > opponent_score,
+                draw: score == opponent_score,
+                score_diff: score - opponent_score,
+            });
+        }
+    }
+    results
+}
+
+pub async fn db_add_match<C: ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+    scores: &[(i64, f64)],
+) -> Result<(), DbErr> {
+    use db::head_to_head::Column;
+    for r in outcomes(scores) {
+        let (wins, draws, losses) = match (r.win, r.draw) {
+            (true, _) => (1, 0, 0),
+            (_, true) => (0, 1, 0),
+            _ => (0, 0, 1),
+        };
+        let add =
+            |c: Column, v: sea_orm::Value| (c, Expr::col((db::head_to_head::Entity, c)).add(v));
+        db::head_to_head::Entity::insert(db::head_to_head::ActiveModel {
+            bot_id: Set(r.bot_id),
+            opponent_id: Set(r.opponent_id),
+            game_id: Set(game_id),
+            matches: Set(1),
+            wins: Set(wins),
+            draws: Set(draws),
+            losses: Set(losses),
+            score_diff: Set(r.score_diff),
+        })
+        .on_conflict(
+            OnConflict::columns([Column::BotId, Column::OpponentId])
+                .values([
+                    add(Column::Matches, 1i64.into()),
+                    add(Column::Wins, wins.into()),
+                    add(Column::Draws, draws.into()),
+                    add(Column::Losses, losses.into()),
+                    add(Column::ScoreDiff, r.score_diff.into()),
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outcomes_of_every_pair() {
+        let results = outcomes(&[(1, 3.), (2, 1.), (3, 1.)]);
+        assert_eq!(results.len(), 6);
+        let find = |b, o| {
+            results
+                .iter()
+                .find(|r| r.bot_id == b && r.opponent_id == o)
+                .unwrap()
+        };
+        assert!(find(1, 2).win && !find(1, 2).draw);
+        assert!(!find(2, 1).win && !find(2, 1).draw);
+        assert_eq!(find(2, 1).score_diff, -2.);
+        assert!(find(2, 3).draw && find(3, 2).draw);
+        assert!(outcomes(&[(1, 2.), (1, 3.)]).is_empty());
+    }
+}
@@ -42,11 +42,35 @@ pub enum AppHttpError {
     #[display(fmt = "Unrecognized or unsupported image type: {_0}")]
     UnsupportedImageType(String),
 
+    #[display(fmt = "Invalid asset name: {_0}")]
+    InvalidAssetName(String),
+
     #[display(fmt = "Match already scheduled")]
     MatchAlreadyScheduled,
 
+    #[display(fmt = "Stats recalculation already scheduled")]
+    StatsRecalculationAlreadyScheduled,
+
     #[display(fmt = "No edit bot action is specified")]
     NoEditBotActionSpecified,
+
+    #[display(fmt = "Invalid challenge: {_0}")]
+    InvalidChallenge(String),
+
+    #[display(fmt = "Too many challenges: {_0}")]
+    ChallengeLimitReached(String),
+
+    #[display(fmt = "Invalid bot version: {_0}")]
+    InvalidBotVersion(String),
+
+    #[display(fmt = "Cannot create the tournament: {_0}")]
+    InvalidTournament(String),
+
+    #[display(fmt = "Cannot create the season: {_0}")]
+    InvalidSeason(String),
+
+    #[display(fmt = "The season is in its final evaluation, new bots are not accepted.")]
+    SubmissionsFrozen,
 }
 
 impl std::error::Error for AppHttpError {}
@@ -72,8 +96,16 @@ impl actix_web::error::ResponseError for AppHttpError {
             AppHttpError::GameNameAlreadyTaken(_) => StatusCode::CONFLICT,
             AppHttpError::GameNameValidationFailed(_) => StatusCode::BAD_REQUEST,
             AppHttpError::UnsupportedImageType(_) => StatusCode::BAD_REQUEST,
+            AppHttpError::InvalidAssetName(_) => StatusCode::BAD_REQUEST,
             AppHttpError::MatchAlreadyScheduled => StatusCode::CONFLICT,
+            AppHttpError::StatsRecalculationAlreadyScheduled => StatusCode::CONFLICT,
             AppHttpError::NoEditBotActionSpecified => StatusCode::BAD_REQUEST,
+            AppHttpError::InvalidChallenge(_) => StatusCode::BAD_REQUEST,
+            AppHttpError::ChallengeLimitReached(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppHttpError::InvalidBotVersion(_) => StatusCode::BAD_REQUEST,
+            AppHttpError::InvalidTournament(_) => StatusCode::BAD_REQUEST,
+            AppHttpError::InvalidSeason(_) => StatusCode::BAD_REQUEST,
+            AppHttpError::SubmissionsFrozen => StatusCode::FORBIDDEN,
         }
     }
 }
@@ -108,7 +108,7 @@ pub async fn kratos_authenticate(
     Ok(Some(account_id))
 }
 
-pub async fn kratos_logout<'a>(req: &HttpRequest) -> Result<HttpResponse<()>, AppHttpError> {
+pub async fn kratos_logout(req: &HttpRequest) -> Result<HttpResponse<()>, AppHttpError> {
     let state = server_state(req)?;
     let flow = ory_kratos_client::apis::frontend_api::create_browser_logout_flow(
         &kratos_config(req)?,
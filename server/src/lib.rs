@@ -1,12 +1,23 @@
 pub mod acl;
+pub mod chart;
+pub mod confidence;
 pub mod config;
 pub mod engine;
 pub mod file_store;
+pub mod head_to_head;
+pub mod matchmaking;
+pub mod rating;
+pub mod recalculate;
 pub mod scheduler;
+pub mod season;
 pub mod server;
+pub mod source_diff;
+pub mod tournament;
 
 mod handlers;
 mod http_types;
 mod kratos;
 mod server_state;
+#[cfg(test)]
+mod test_db;
 mod validation;
@@ -0,0 +1,316 @@
+// Choosing the bots of the next match of a game.
+
+use anyhow::anyhow;
+use rand::{seq::SliceRandom, RngCore};
+
+use proglad_db as db;
+
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub bot_id: i64,
+    pub owner_id: i64,
+    pub matches_played: i64,
+    // Too few matches to trust the stats, see confidence::is_provisional.
+    pub provisional: bool,
+    // Conservative rating with the current rating system of the game.
+    pub rating: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    // No two bots of the same owner in one match.
+    pub distinct_owners: bool,
+}
+
+// What the strategies know about the game.
+pub struct Pool<'a> {
+    pub candidates: &'a [Candidate],
+    // Bots of each of the recent matches of the game, active bots only.
+    pub recent_matches: &'a [Vec<i64>],
+    pub constraints: Constraints,
+}
+
+impl<'a> Pool<'a> {
+    // The most players a match can have under the constraints.
+    pub fn max_players(&self) -> usize {
+        if self.constraints.distinct_owners {
+            let mut owners = self
+                .candidates
+                .iter()
+                .map(|c| c.owner_id)
+                .collect::<Vec<_>>();
+            owners.sort();
+            owners.dedup();
+            owners.len()
+        } else {
+            self.candidates.len()
+        }
+    }
+    fn eligible(&self, selected: &[&'a Candidate]) -> Vec<&'a Candidate> {
+        self.candidates
+            .iter()
+            .filter(|c| {
+                selected.iter().all(|s| {
+                    s.bot_id != c.bot_id
+                        && !(self.constraints.distinct_owners && s.owner_id == c.owner_id)
+                })
+            })
+            .collect()
+    }
+    // Recent matches that had all of `bots`.
+    fn recent_matches_with(&self, bots: &[&Candidate]) -> usize {
+        self.recent_matches
+            .iter()
+            .filter(|m| bots.iter().all(|b| m.contains(&b.bot_id)))
+            .count()
+    }
+}
+
+pub trait MatchmakingStrategy: Send + Sync {
+    // The next candidate to join the selected ones, out of the eligible ones
+    // (never empty).
+    fn pick<'a>(
+        &self,
+        pool: &Pool<'a>,
+        selected: &[&'a Candidate],
+        eligible: &[&'a Candidate],
+        rng: &mut dyn RngCore,
+    ) -> &'a Candidate;
+
+    // Picks `num_players` bots one by one. While there are provisional bots,
+    // every match has one of them, so that their stats settle sooner.
+    fn choose(
+        &self,
+        pool: &Pool,
+        num_players: usize,
+        rng: &mut dyn RngCore,
+    ) -> anyhow::Result<Vec<i64>> {
+        let mut selected = Vec::with_capacity(num_players);
+        for _ in 0..num_players {
+            let mut eligible = pool.eligible(&selected);
+            if eligible.is_empty() {
+                return Err(anyhow!(
+                    "Only {} of {num_players} players can play together",
+                    selected.len()
+                ));
+            }
+            if selected.is_empty() && eligible.iter().any(|c| c.provisional) {
+                eligible.retain(|c| c.provisional);
+            }
+            selected.push(self.pick(pool, &selected, &eligible, rng));
+        }
+        let mut ids = selected.iter().map(|c| c.bot_id).collect::<Vec<_>>();
+        ids.shuffle(rng);
+        Ok(ids)
+    }
+}
+
+pub fn strategy(matchmaking: db::games::Matchmaking) -> &'static dyn MatchmakingStrategy {
+    match matchmaking {
+        db::games::Matchmaking::Balanced => &Balanced,
+        db::games::Matchmaking::RatingProximity => &RatingProximity { window: 3 },
+        db::games::Matchmaking::ChallengeLeader => &ChallengeLeader,
+        db::games::Matchmaking::NewBotsPlayMore => &NewBotsPlayMore,
+    }
+}
+
+// Plays the bot sets that played together the least, at random otherwise.
+pub struct Balanced;
+
+impl MatchmakingStrategy for Balanced {
+    fn pick<'a>(
+        &self,
+        pool: &Pool<'a>,
+        selected: &[&'a Candidate],
+        eligible: &[&'a Candidate],
+        rng: &mut dyn RngCore,
+    ) -> &'a Candidate {
+        let mut bots = selected.to_vec();
+        let counts = eligible
+            .iter()
+            .map(|&c| {
+                bots.push(c);
+                let count = pool.recent_matches_with(&bots);
+                bots.pop();
+                count
+            })
+            .collect::<Vec<_>>();
+        let min_count = counts.iter().copied().min().unwrap_or_default();
+        let candidates = eligible
+            .iter()
+            .zip(counts)
+            .filter_map(|(c, count)| (count == min_count).then_some(*c))
+            .collect::<Vec<_>>();
+        candidates.choose(rng).copied().unwrap()
+    }
+}
+
+// Starts with a random bot and adds ones rated close to it. Unrated bots
+// count as rated at the median.
+pub struct RatingProximity {
+    // Each next bot is one of this many closest ones.
+    pub window: usize,
+}
+
+impl MatchmakingStrategy for RatingProximity {
+    fn pick<'a>(
+        &self,
+        pool: &Pool<'a>,
+        selected: &[&'a Candidate],
+        eligible: &[&'a Candidate],
+        rng: &mut dyn RngCore,
+    ) -> &'a Candidate {
+        let Some(first) = selected.first() else {
+            return eligible.choose(rng).copied().unwrap();
+        };
+        let mut rated = pool
+            .candidates
+            .iter()
+            .filter_map(|c| c.rating)
+            .collect::<Vec<_>>();
+        rated.sort_by(f64::total_cmp);
+        let median = rated.get(rated.len() / 2).copied().unwrap_or_default();
+        let rating = |c: &Candidate| c.rating.unwrap_or(median);
+        let mut closest = eligible.to_vec();
+        closest.sort_by(|x, y| {
+            (rating(x) - rating(first))
+                .abs()
+                .total_cmp(&(rating(y) - rating(first)).abs())
+        });
+        closest.truncate(self.window.max(1));
+        closest.choose(rng).copied().unwrap()
+    }
+}
+
+// The top rated bot plays every match, against balanced opponents.
+pub struct ChallengeLeader;
+
+impl MatchmakingStrategy for ChallengeLeader {
+    fn pick<'a>(
+        &self,
+        pool: &Pool<'a>,
+        selected: &[&'a Candidate],
+        eligible: &[&'a Candidate],
+        rng: &mut dyn RngCore,
+    ) -> &'a Candidate {
+        let leader = eligible
+            .iter()
+            .filter(|c| c.rating.is_some())
+            .max_by(|x, y| x.rating.unwrap().total_cmp(&y.rating.unwrap()));
+        match leader {
+            Some(leader) if selected.is_empty() => leader,
+            _ => Balanced.pick(pool, selected, eligible, rng),
+        }
+    }
+}
+
+// Bots are picked with odds inverse to the number of matches they played.
+pub struct NewBotsPlayMore;
+
+impl MatchmakingStrategy for NewBotsPlayMore {
+    fn pick<'a>(
+        &self,
+        _pool: &Pool<'a>,
+        _selected: &[&'a Candidate],
+        eligible: &[&'a Candidate],
+        rng: &mut dyn RngCore,
+    ) -> &'a Candidate {
+        eligible
+            .choose_weighted(rng, |c| 1. / (1. + c.matches_played.max(0) as f64))
+            .copied()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+
+    fn candidates() -> Vec<Candidate> {
+        (0..6)
+            .map(|i| Candidate {
+                bot_id: i,
+                owner_id: i / 2,
+                matches_played: i * 10,
+                provisional: false,
+                rating: Some(i as f64),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strategies_respect_constraints() {
+        let candidates = candidates();
+        let pool = Pool {
+            candidates: &candidates,
+            recent_matches: &[vec![0, 2], vec![0, 4]],
+            constraints: Constraints {
+                distinct_owners: true,
+            },
+        };
+        assert_eq!(pool.max_players(), 3);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for matchmaking in [
+            db::games::Matchmaking::Balanced,
+            db::games::Matchmaking::RatingProximity,
+            db::games::Matchmaking::ChallengeLeader,
+            db::games::Matchmaking::NewBotsPlayMore,
+        ] {
+            for _ in 0..20 {
+                let mut bots = strategy(matchmaking).choose(&pool, 3, &mut rng).unwrap();
+                bots.sort();
+                let mut owners = bots.iter().map(|b| b / 2).collect::<Vec<_>>();
+                owners.dedup();
+                assert_eq!(owners.len(), 3, "{matchmaking:?}: {bots:?}");
+                if matchmaking == db::games::Matchmaking::ChallengeLeader {
+                    assert!(bots.contains(&5), "{bots:?}");
+                }
+            }
+            assert!(strategy(matchmaking).choose(&pool, 4, &mut rng).is_err());
+        }
+    }
+
+    #[test]
+    fn provisional_bots_play_every_match() {
+        let mut candidates = candidates();
+        candidates[1].provisional = true;
+        let pool = Pool {
+            candidates: &candidates,
+            recent_matches: &[vec![0, 1], vec![1, 2]],
+            constraints: Constraints::default(),
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        for _ in 0..20 {
+            let bots = strategy(db::games::Matchmaking::ChallengeLeader)
+                .choose(&pool, 2, &mut rng)
+                .unwrap();
+            assert!(bots.contains(&1), "{bots:?}");
+        }
+    }
+
+    #[test]
+    fn balanced_avoids_recent_sets() {
+        let candidates = candidates();
+        let pool = Pool {
+            candidates: &candidates[..3],
+            recent_matches: &[vec![0, 1], vec![0, 2], vec![1, 2]],
+            constraints: Constraints::default(),
+        };
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let selected = [&candidates[0]];
+        let eligible = pool.eligible(&selected);
+        for _ in 0..10 {
+            let picked = Balanced.pick(&pool, &selected, &eligible, &mut rng);
+            assert_ne!(picked.bot_id, 0);
+        }
+        let pool = Pool {
+            recent_matches: &[vec![0, 1], vec![0, 1]],
+            ..pool
+        };
+        for _ in 0..10 {
+            let picked = Balanced.pick(&pool, &selected, &eligible, &mut rng);
+            assert_eq!(picked.bot_id, 2);
+        }
+    }
+}
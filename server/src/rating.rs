@@ -0,0 +1,279 @@
+// Skill ratings, updated from the scores of each match. Higher scores rank
+// better and equal scores are a draw, so matches of any number of players
+// count as all the pairwise results between them.
+
+use proglad_db as db;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rating {
+    pub mu: f64,
+    // Standard deviation of the estimate, 0 for Elo.
+    pub sigma: f64,
+    // How erratic the performance is, used by Glicko-2 only.
+    pub volatility: f64,
+}
+
+pub trait RatingSystem: Send + Sync {
+    fn initial(&self) -> Rating;
+    // New ratings of the match participants, in the same order.
+    fn update(&self, players: &[(Rating, f64)]) -> Vec<Rating>;
+    // A rating the player is very likely above, bots are ranked by it.
+    fn conservative(&self, r: &Rating) -> f64;
+    // Halfway back to the initial rating, at the start of a season.
+    fn decay(&self, r: &Rating) -> Rating {
+        let initial = self.initial();
+        Rating {
+            mu: (r.mu + initial.mu) / 2.,
+            sigma: r.sigma.max((r.sigma + initial.sigma) / 2.),
+            volatility: r.volatility,
+        }
+    }
+}
+
+pub fn rating_system(system: db::games::RatingSystem) -> &'static dyn RatingSystem {
+    match system {
+        db::games::RatingSystem::Elo => &Elo { k: 32. },
+        db::games::RatingSystem::Glicko2 => &Glicko2 { tau: 0.5 },
+        db::games::RatingSystem::TrueSkill => &TrueSkill {
+            beta: 25. / 6.,
+            tau: 25. / 300.,
+        },
+    }
+}
+
+// 1 for a win of i over j, 0.5 for a draw.
+fn outcome(players: &[(Rating, f64)], i: usize, j: usize) -> f64 {
+    match players[i].1.partial_cmp(&players[j].1) {
+        Some(std::cmp::Ordering::Greater) => 1.,
+        Some(std::cmp::Ordering::Less) => 0.,
+        _ => 0.5,
+    }
+}
+
+fn opponents(n: usize, i: usize) -> impl Iterator<Item = usize> {
+    (0..n).filter(move |j| *j != i)
+}
+
+pub struct Elo {
+    // Points at stake in a match, split between the opponents.
+    pub k: f64,
+}
+
+impl RatingSystem for Elo {
+    fn initial(&self) -> Rating {
+        Rating {
+            mu: 1500.,
+            sigma: 0.,
+            volatility: 0.,
+        }
+    }
+    fn update(&self, players: &[(Rating, f64)]) -> Vec<Rating> {
+        let n = players.len();
+        let k = self.k / (n.max(2) - 1) as f64;
+        (0..n)
+            .map(|i| {
+                let (r, _) = players[i];
+                let delta = opponents(n, i)
+                    .map(|j| {
+                        let expected = 1. / (1. + 10f64.powf((players[j].0.mu - r.mu) / 400.));
+                        k * (outcome(players, i, j) - expected)
+                    })
+                    .sum::<f64>();
+                Rating {
+                    mu: r.mu + delta,
+                    ..r
+                }
+            })
+            .collect()
+    }
+    fn conservative(&self, r: &Rating) -> f64 {
+        r.mu
+    }
+}
+
+// Glickman, "Example of the Glicko-2 system", with each match as a rating
+// period.
+pub struct Glicko2 {
+    // Constrains the change in volatility.
+    pub tau: f64,
+}
+
+const GLICKO2_SCALE: f64 = 173.7178;
+
+impl Glicko2 {
+    fn volatility(&self, phi: f64, sigma: f64, v: f64, delta: f64) -> f64 {
+        let a = (sigma * sigma).ln();
+        let f = |x: f64| {
+            let ex = x.exp();
+            let d = phi * phi + v + ex;
+            ex * (delta * delta - d) / (2. * d * d) - (x - a) / (self.tau * self.tau)
+        };
+        let mut lo = a;
+        let mut hi = if delta * delta > phi * phi + v {
+            (delta * delta - phi * phi - v).ln()
+        } else {
+            let mut k = 1.;
+            while f(a - k * self.tau) < 0. {
+                k += 1.;
+            }
+            a - k * self.tau
+        };
+        let (mut f_lo, mut f_hi) = (f(lo), f(hi));
+        while (hi - lo).abs() > 1e-6 {
+            let c = lo + (lo - hi) * f_lo / (f_hi - f_lo);
+            let f_c = f(c);
+            if f_c * f_hi <= 0. {
+                lo = hi;
+                f_lo = f_hi;
+            } else {
+                f_lo /= 2.;
+            }
+            hi = c;
+            f_hi = f_c;
+        }
+        (lo / 2.).exp()
+    }
+}
+
+impl RatingSystem for Glicko2 {
+    fn initial(&self) -> Rating {
+        Rating {
+            mu: 1500.,
+            sigma: 350.,
+            volatility: 0.06,
+        }
+    }
+    fn update(&self, players: &[(Rating, f64)]) -> Vec<Rating> {
+        let n = players.len();
+        let scaled = |r: &Rating| ((r.mu - 1500.) / GLICKO2_SCALE, r.sigma / GLICKO2_SCALE);
+        let g = |phi: f64| 1. / (1. + 3. * phi * phi / (std::f64::consts::PI.powi(2))).sqrt();
+        (0..n)
+            .map(|i| {
+                let (r, _) = players[i];
+                let (mu, phi) = scaled(&r);
+                if n < 2 {
+                    let phi = (phi * phi + r.volatility * r.volatility).sqrt();
+                    return Rating {
+                        sigma: phi * GLICKO2_SCALE,
+                        ..r
+                    };
+                }
+                let mut inv_v = 0.;
+                let mut sum = 0.;
+                for j in opponents(n, i) {
+                    let (mu_j, phi_j) = scaled(&players[j].0);
+                    let g_j = g(phi_j);
+                    let e = 1. / (1. + (-g_j * (mu - mu_j)).exp());
+                    inv_v += g_j * g_j * e * (1. - e);
+                    sum += g_j * (outcome(players, i, j) - e);
+                }
+                let v = 1. / inv_v;
+                let volatility = self.volatility(phi, r.volatility, v, v * sum);
+                let phi_star = (phi * phi + volatility * volatility).sqrt();
+                let phi = 1. / (1. / (phi_star * phi_star) + inv_v).sqrt();
+                Rating {
+                    mu: 1500. + (mu + phi * phi * sum) * GLICKO2_SCALE,
+                    sigma: phi * GLICKO2_SCALE,
+                    volatility,
+                }
+            })
+            .collect()
+    }
+    fn conservative(&self, r: &Rating) -> f64 {
+        r.mu - 2. * r.sigma
+    }
+}
+
+// TrueSkill-like Bayesian ratings with the closed-form Bradley-Terry updates
+// of Weng and Lin, "A Bayesian Approximation Method for Online Ranking"
+// (2011), which handle any number of players without factor graphs.
+pub struct TrueSkill {
+    // Performance variation within a match.
+    pub beta: f64,
+    // Added uncertainty before every match, so that ratings keep moving.
+    pub tau: f64,
+}
+
+impl RatingSystem for TrueSkill {
+    fn initial(&self) -> Rating {
+        Rating {
+            mu: 25.,
+            sigma: 25. / 3.,
+            volatility: 0.,
+        }
+    }
+    fn update(&self, players: &[(Rating, f64)]) -> Vec<Rating> {
+        let n = players.len();
+        let sigma2 = |r: &Rating| r.sigma * r.sigma + self.tau * self.tau;
+        (0..n)
+            .map(|i| {
+                let (r, _) = players[i];
+                let s2 = sigma2(&r);
+                let mut omega = 0.;
+                let mut delta = 0.;
+                for j in opponents(n, i) {
+                    let r_j = &players[j].0;
+                    let c = (s2 + sigma2(r_j) + 2. * self.beta * self.beta).sqrt();
+                    let p = 1. / (1. + ((r_j.mu - r.mu) / c).exp());
+                    omega += s2 / c * (outcome(players, i, j) - p);
+                    let gamma = s2.sqrt() / c;
+                    delta += gamma * s2 / (c * c) * p * (1. - p);
+                }
+                Rating {
+                    mu: r.mu + omega,
+                    sigma: (s2 * (1. - delta).max(1e-4)).sqrt(),
+                    ..r
+                }
+            })
+            .collect()
+    }
+    fn conservative(&self, r: &Rating) -> f64 {
+        r.mu - 3. * r.sigma
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glicko2_paper_example() {
+        let rating = |mu, sigma| Rating {
+            mu,
+            sigma,
+            volatility: 0.06,
+        };
+        // Wins against the first opponent, loses to the other two.
+        let players = [
+            (rating(1500., 200.), 2.),
+            (rating(1400., 30.), 1.),
+            (rating(1550., 100.), 3.),
+            (rating(1700., 300.), 3.),
+        ];
+        let after = Glicko2 { tau: 0.5 }.update(&players)[0];
+        assert!((after.mu - 1464.06).abs() < 0.01, "{after:?}");
+        assert!((after.sigma - 151.52).abs() < 0.01, "{after:?}");
+        assert!((after.volatility - 0.05999).abs() < 1e-5, "{after:?}");
+    }
+
+    #[test]
+    fn winners_go_up() {
+        for system in [
+            db::games::RatingSystem::Elo,
+            db::games::RatingSystem::Glicko2,
+            db::games::RatingSystem::TrueSkill,
+        ] {
+            let rs = rating_system(system);
+            let r = rs.initial();
+            let after = rs.update(&[(r, 3.), (r, 1.), (r, 1.)]);
+            assert!(after[0].mu > r.mu, "{system:?}: {after:?}");
+            assert!(after[1].mu < r.mu, "{system:?}: {after:?}");
+            assert_eq!(after[1], after[2], "{system:?}");
+            assert!(rs.conservative(&after[0]) > rs.conservative(&after[1]));
+            if system != db::games::RatingSystem::Elo {
+                assert!(after[0].sigma < r.sigma, "{system:?}: {after:?}");
+            }
+            assert_eq!(rs.update(&[(r, 1.)])[0].mu, r.mu, "{system:?}");
+        }
+    }
+}
@@ -0,0 +1,623 @@
+// Rebuilding the stats of a game from the scores of its matches, e.g. after a
+// fix of the scoring. The matches still in the database are replayed in the
+// order they ended, with the seasons starting in between, so the result only
+// depends on them and rebuilding twice changes nothing. Matches deleted by the
+// cleanup of old matches are lost from the rebuilt stats, which the preview
+// reports.
+
+use anyhow::Context;
+use sea_orm::prelude::TimeDateTimeWithTimeZone;
+use sea_orm::{
+    ActiveValue::NotSet, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, RelationTrait, TransactionTrait,
+};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{confidence, head_to_head, rating};
+use proglad_db as db;
+
+// Rows per insert statement, within the limit of bound variables.
+const INSERT_CHUNK: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct MatchScores {
+    pub match_id: i64,
+    pub end_time: TimeDateTimeWithTimeZone,
+    pub scores: Vec<(i64, f64)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SeasonStart {
+    pub time: TimeDateTimeWithTimeZone,
+    pub carry_over: db::seasons::RatingCarryOver,
+}
+
+// What the replay rebuilds. Ids of the history rows are not set.
+#[derive(Debug, Default)]
+pub struct Replayed {
+    pub history: Vec<db::stats_history::Model>,
+    pub head_to_head: Vec<db::head_to_head::Model>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub matches: i64,
+    pub average_score: f64,
+    pub rating: Option<f64>,
+}
+
+// Latest stats of a bot before and after the rebuild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diff {
+    pub bot_id: i64,
+    pub before: Option<Summary>,
+    pub after: Option<Summary>,
+}
+
+impl Diff {
+    pub fn changed(&self) -> bool {
+        match (&self.before, &self.after) {
+            (Some(b), Some(a)) => {
+                let close = |x: f64, y: f64| (x - y).abs() < 1e-6;
+                b.matches != a.matches
+                    || !close(b.average_score, a.average_score)
+                    || match (b.rating, a.rating) {
+                        (Some(x), Some(y)) => !close(x, y),
+                        (x, y) => x.is_some() != y.is_some(),
+                    }
+            }
+            (b, a) => b.is_some() != a.is_some(),
+        }
+    }
+}
+
+// What a rebuild changes, or would change.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rebuild {
+    pub diffs: Vec<Diff>,
+    // Matches counted in the current stats that are no longer in the database.
+    // The ones that ended at the same time count once.
+    pub deleted_matches: usize,
+}
+
+fn summary(st: &db::stats_history::Model, system: db::games::RatingSystem) -> Summary {
+    Summary {
+        matches: st.total_matches,
+        average_score: st.total_score / st.total_matches.max(1) as f64,
+        rating: st
+            .conservative_rating
+            .filter(|_| st.rating_system == Some(system)),
+    }
+}
+
+fn rating_of(
+    st: &db::stats_history::Model,
+    system: db::games::RatingSystem,
+) -> Option<rating::Rating> {
+    st.rating_system.filter(|s| *s == system)?;
+    Some(rating::Rating {
+        mu: st.rating?,
+        sigma: st.rating_deviation?,
+        volatility: st.rating_volatility?,
+    })
+}
+
+fn set_rating(
+    st: &mut db::stats_history::Model,
+    system: db::games::RatingSystem,
+    r: Option<rating::Rating>,
+) {
+    let rs = rating::rating_system(system);
+    st.rating_system = r.map(|_| system);
+    st.rating = r.map(|r| r.mu);
+    st.rating_deviation = r.map(|r| r.sigma);
+    st.rating_volatility = r.map(|r| r.volatility);
+    st.conservative_rating = r.map(|r| rs.conservative(&r));
+}
+
+fn start_season(
+    system: db::games::RatingSystem,
+    season: &SeasonStart,
+    latest: &mut BTreeMap<i64, db::stats_history::Model>,
+    history: &mut Vec<db::stats_history::Model>,
+) {
+    let rs = rating::rating_system(system);
+    for st in latest.values_mut() {
+        let r = match season.carry_over {
+            db::seasons::RatingCarryOver::Reset => None,
+            db::seasons::RatingCarryOver::Decay => rating_of(st, system).map(|r| rs.decay(&r)),
+        };
+        set_rating(st, system, r);
+        st.update_time = season.time;
+        st.match_id = None;
+        st.total_score = 0.;
+        st.total_matches = 0;
+        st.total_score_sq = 0.;
+        st.total_wins = 0.;
+        st.sampled_matches = 0;
+        history.push(st.clone());
+    }
+}
+
+// The same updates as the engine makes after each match and at the start of
+// each season.
+pub fn replay(
+    game_id: i64,
+    system: db::games::RatingSystem,
+    matches: &[MatchScores],
+    seasons: &[SeasonStart],
+) -> Replayed {
+    let rs = rating::rating_system(system);
+    let mut latest = BTreeMap::<i64, db::stats_history::Model>::new();
+    let mut history = vec![];
+    let mut h2h = BTreeMap::<(i64, i64), db::head_to_head::Model>::new();
+    let mut seasons = seasons.iter().peekable();
+    for m in matches {
+        while let Some(s) = seasons.next_if(|s| s.time <= m.end_time) {
+            start_season(system, s, &mut latest, &mut history);
+        }
+        let ratings = m
+            .scores
+            .iter()
+            .map(|(id, score)| {
+                let r = latest.get(id).and_then(|st| rating_of(st, system));
+                (r.unwrap_or_else(|| rs.initial()), *score)
+            })
+            .collect::<Vec<_>>();
+        let ratings = rs.update(&ratings);
+        let scores = m.scores.iter().map(|(_, s)| *s).collect::<Vec<_>>();
+        let wins = confidence::win_shares(&scores);
+        for ((id, score), (r, win)) in m.scores.iter().zip(ratings.into_iter().zip(wins)) {
+            let st = latest
+                .entry(*id)
+                .or_insert_with(|| db::stats_history::Model {
+                    id: 0,
+                    bot_id: *id,
+                    latest: false,
+                    update_time: m.end_time,
+                    match_id: None,
+                    total_score: 0.,
+                    total_matches: 0,
+                    total_score_sq: 0.,
+                    total_wins: 0.,
+                    sampled_matches: 0,
+                    rating_system: None,
+                    rating: None,
+                    rating_deviation: None,
+                    rating_volatility: None,
+                    conservative_rating: None,
+                });
+            set_rating(st, system, Some(r));
+            st.update_time = m.end_time;
+            st.match_id = Some(m.match_id);
+            st.total_score += score;
+            st.total_matches += 1;
+            st.total_score_sq += score * score;
+            st.total_wins += win;
+            st.sampled_matches += 1;
+            history.push(st.clone());
+        }
+        for o in head_to_head::outcomes(&m.scores) {
+            let h =
+                h2h.entry((o.bot_id, o.opponent_id))
+                    .or_insert_with(|| db::head_to_head::Model {
+                        bot_id: o.bot_id,
+                        opponent_id: o.opponent_id,
+                        game_id,
+                        matches: 0,
+                        wins: 0,
+                        draws: 0,
+                        losses: 0,
+                        score_diff: 0.,
+                    });
+            h.matches += 1;
+            match (o.win, o.draw) {
+                (true, _) => h.wins += 1,
+                (_, true) => h.draws += 1,
+                _ => h.losses += 1,
+            }
+            h.score_diff += o.score_diff;
+        }
+    }
+    for s in seasons {
+        start_season(system, s, &mut latest, &mut history);
+    }
+    let mut seen = std::collections::HashSet::new();
+    for st in history.iter_mut().rev() {
+        st.latest = seen.insert(st.bot_id);
+    }
+    Replayed {
+        history,
+        head_to_head: h2h.into_values().collect(),
+    }
+}
+
+pub fn diff(
+    system: db::games::RatingSystem,
+    before: &[db::stats_history::Model],
+    after: &Replayed,
+) -> Vec<Diff> {
+    let mut diffs = BTreeMap::<i64, Diff>::new();
+    let new = |bot_id| Diff {
+        bot_id,
+        before: None,
+        after: None,
+    };
+    for st in before {
+        diffs
+            .entry(st.bot_id)
+            .or_insert_with(|| new(st.bot_id))
+            .before = Some(summary(st, system));
+    }
+    for st in after.history.iter().filter(|st| st.latest) {
+        diffs
+            .entry(st.bot_id)
+            .or_insert_with(|| new(st.bot_id))
+            .after = Some(summary(st, system));
+    }
+    diffs.into_values().collect()
+}
+
+struct Loaded {
+    game: db::games::Model,
+    bot_ids: Vec<i64>,
+    matches: Vec<MatchScores>,
+    seasons: Vec<SeasonStart>,
+    latest: Vec<db::stats_history::Model>,
+    deleted_matches: usize,
+}
+
+async fn load<C: ConnectionTrait>(db: &C, game_id: i64) -> anyhow::Result<Loaded> {
+    let game = db::games::Entity::find_by_id(game_id)
+        .one(db)
+        .await?
+        .context(format!("Game {game_id} not found"))?;
+    let bot_ids = db::bots::Entity::find()
+        .filter(db::bots::Column::GameId.eq(game_id))
+        .select_only()
+        .column(db::bots::Column::Id)
+        .into_tuple::<i64>()
+        .all(db)
+        .await
+        .context("Failed to fetch bots of the game")?;
+    let ended = db::matches::Entity::find()
+        .filter(db::matches::Column::GameId.eq(game_id))
+        .filter(db::matches::Column::EndTime.is_not_null())
+        .order_by_asc(db::matches::Column::EndTime)
+        .order_by_asc(db::matches::Column::Id)
+        .all(db)
+        .await
+        .context("Failed to fetch matches of the game")?;
+    // Deleting a match clears the match_id of the stats it updated, the rows
+    // at the start of a season have none either but count no matches.
+    let deleted_matches = db::stats_history::Entity::find()
+        .filter(db::stats_history::Column::BotId.is_in(bot_ids.iter().copied()))
+        .filter(db::stats_history::Column::MatchId.is_null())
+        .filter(db::stats_history::Column::TotalMatches.gt(0))
+        .select_only()
+        .column(db::stats_history::Column::UpdateTime)
+        .distinct()
+        .into_tuple::<TimeDateTimeWithTimeZone>()
+        .all(db)
+        .await
+        .context("Failed to fetch stats of deleted matches")?
+        .len();
+    let mut participations = HashMap::<i64, Vec<db::match_participations::Model>>::new();
+    for p in db::match_participations::Entity::find()
+        .join(
+            sea_orm::JoinType::InnerJoin,
+            db::match_participations::Relation::Matches.def(),
+        )
+        .filter(db::matches::Column::GameId.eq(game_id))
+        .all(db)
+        .await
+        .context("Failed to fetch match participations of the game")?
+    {
+        participations.entry(p.match_id).or_default().push(p);
+    }
+    // Only matches that finished with a score for everyone count, as in
+    // run_match.
+    let matches = ended
+        .into_iter()
+        .filter_map(|m| {
+            let mut ps = participations.remove(&m.id)?;
+            ps.sort_by_key(|p| p.ingame_player);
+            let scores = ps
+                .into_iter()
+                .map(|p| Some((p.bot_id, p.score?)))
+                .collect::<Option<Vec<_>>>()?;
+            Some(MatchScores {
+                match_id: m.id,
+                end_time: m.end_time?,
+                scores,
+            })
+        })
+        .collect();
+    let seasons = db::seasons::Entity::find()
+        .filter(db::seasons::Column::GameId.eq(game_id))
+        .filter(db::seasons::Column::Status.ne(db::seasons::Status::Upcoming))
+        .order_by_asc(db::seasons::Column::StartTime)
+        .all(db)
+        .await
+        .context("Failed to fetch seasons of the game")?
+        .into_iter()
+        .map(|s| SeasonStart {
+            time: s.start_time,
+            carry_over: s.rating_carry_over,
+        })
+        .collect();
+    let latest = db::stats_history::Entity::find()
+        .filter(db::stats_history::Column::Latest.eq(true))
+        .filter(db::stats_history::Column::BotId.is_in(bot_ids.iter().copied()))
+        .all(db)
+        .await
+        .context("Failed to fetch latest stats")?;
+    Ok(Loaded {
+        game,
+        bot_ids,
+        matches,
+        seasons,
+        latest,
+        deleted_matches,
+    })
+}
+
+// What a rebuild would change, without changing anything.
+pub async fn preview<C: ConnectionTrait>(db: &C, game_id: i64) -> anyhow::Result<Rebuild> {
+    let l = load(db, game_id).await?;
+    let replayed = replay(game_id, l.game.rating_system, &l.matches, &l.seasons);
+    Ok(Rebuild {
+        diffs: diff(l.game.rating_system, &l.latest, &replayed),
+        deleted_matches: l.deleted_matches,
+    })
+}
+
+// Replaces the stats history and the head-to-head results of the game with
+// rebuilt ones, in one transaction.
+pub async fn recalculate<C: ConnectionTrait + TransactionTrait>(
+    db: &C,
+    game_id: i64,
+) -> anyhow::Result<Rebuild> {
+    db.transaction(|txn| {
+        Box::pin(async move {
+            recalculate_in(txn, game_id)
+                .await
+                .map_err(|e| DbErr::Custom(format!("{e:#}")))
+        })
+    })
+    .await
+    .context(format!("Failed to recalculate stats of game {game_id}"))
+}
+
+async fn recalculate_in<C: ConnectionTrait>(db: &C, game_id: i64) -> anyhow::Result<Rebuild> {
+    let l = load(db, game_id).await?;
+    let replayed = replay(game_id, l.game.rating_system, &l.matches, &l.seasons);
+    let diffs = diff(l.game.rating_system, &l.latest, &replayed);
+    db::stats_history::Entity::delete_many()
+        .filter(db::stats_history::Column::BotId.is_in(l.bot_ids))
+        .exec(db)
+        .await
+        .context("Failed to delete stats history")?;
+    for chunk in replayed.history.chunks(INSERT_CHUNK) {
+        db::stats_history::Entity::insert_many(chunk.iter().map(|st| {
+            let mut st: db::stats_history::ActiveModel = st.clone().into();
+            st.id = NotSet;
+            st
+        }))
+        .exec(db)
+        .await
+        .context("Failed to insert stats history")?;
+    }
+    db::head_to_head::Entity::delete_many()
+        .filter(db::head_to_head::Column::GameId.eq(game_id))
+        .exec(db)
+        .await
+        .context("Failed to delete head-to-head results")?;
+    for chunk in replayed.head_to_head.chunks(INSERT_CHUNK) {
+        db::head_to_head::Entity::insert_many(
+            chunk
+                .iter()
+                .map(|h| db::head_to_head::ActiveModel::from(h.clone())),
+        )
+        .exec(db)
+        .await
+        .context("Failed to insert head-to-head results")?;
+    }
+    Ok(Rebuild {
+        diffs,
+        deleted_matches: l.deleted_matches,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replay_with_a_season() {
+        let t = |h| time::macros::datetime!(2024-12-15 00:00 UTC) + time::Duration::hours(h);
+        let m = |match_id, h, scores: &[(i64, f64)]| MatchScores {
+            match_id,
+            end_time: t(h),
+            scores: scores.to_vec(),
+        };
+        let matches = [
+            m(1, 0, &[(1, 2.), (2, 1.)]),
+            m(2, 1, &[(1, 1.), (2, 1.)]),
+            m(3, 3, &[(2, 3.), (3, 0.)]),
+        ];
+        let seasons = [SeasonStart {
+            time: t(2),
+            carry_over: db::seasons::RatingCarryOver::Reset,
+        }];
+        let system = db::games::RatingSystem::Elo;
+        let replayed = replay(7, system, &matches, &seasons);
+        // 2 rows per match and one per rated bot at the start of the season.
+        assert_eq!(replayed.history.len(), 8);
+        let latest = replayed
+            .history
+            .iter()
+            .filter(|st| st.latest)
+            .map(|st| (st.bot_id, st))
+            .collect::<BTreeMap<_, _>>();
+        assert_eq!(latest.len(), 3);
+        assert_eq!(latest[&1].total_matches, 0);
+        assert_eq!(latest[&1].rating, None);
+        assert_eq!(latest[&2].total_matches, 1);
+        assert_eq!(latest[&2].total_wins, 1.);
+        assert_eq!(latest[&2].match_id, Some(3));
+        let h = &replayed.head_to_head;
+        assert_eq!(h.len(), 4);
+        assert_eq!((h[0].bot_id, h[0].opponent_id), (1, 2));
+        assert_eq!((h[0].matches, h[0].wins, h[0].draws), (2, 1, 1));
+        assert_eq!(h[0].score_diff, 1.);
+        // Replaying what was replayed changes nothing.
+        let before = replayed
+            .history
+            .iter()
+            .filter(|st| st.latest)
+            .cloned()
+            .collect::<Vec<_>>();
+        let diffs = diff(system, &before, &replay(7, system, &matches, &seasons));
+        assert_eq!(diffs.len(), 3);
+        assert!(diffs.iter().all(|d| !d.changed()), "{diffs:?}");
+        let diffs = diff(system, &before, &replay(7, system, &matches[..2], &seasons));
+        assert!(diffs.iter().any(|d| d.changed()), "{diffs:?}");
+    }
+
+    async fn game_db() -> sea_orm::DatabaseConnection {
+        use crate::test_db;
+        use sea_orm::ActiveValue::Set;
+        let db = test_db::new().await;
+        test_db::game(&db, 1, 2..=2, db::games::RatingSystem::Elo).await;
+        for bot_id in [1, 2] {
+            test_db::bot(&db, bot_id, 1, true).await;
+        }
+        let t = test_db::time;
+        for (match_id, scores) in [(1, [1., 0.]), (2, [0.5, 0.5]), (3, [0., 1.])] {
+            db::matches::Entity::insert(db::matches::ActiveModel {
+                id: Set(match_id),
+                game_id: Set(1),
+                creation_time: Set(t(match_id)),
+                start_time: Set(Some(t(match_id))),
+                end_time: Set(Some(t(match_id))),
+                system_message: Set(String::new()),
+                tournament_id: Set(None),
+                tournament_round: Set(None),
+            })
+            .exec(&db)
+            .await
+            .unwrap();
+            for (i, score) in scores.into_iter().enumerate() {
+                db::match_participations::Entity::insert(db::match_participations::ActiveModel {
+                    match_id: Set(match_id),
+                    bot_id: Set(i as i64 + 1),
+                    ingame_player: Set(i as u32 + 1),
+                    score: Set(Some(score)),
+                    system_message: Set(None),
+                    program_id: Set(None),
+                })
+                .exec(&db)
+                .await
+                .unwrap();
+            }
+        }
+        db
+    }
+
+    async fn rows(
+        db: &sea_orm::DatabaseConnection,
+    ) -> (Vec<db::stats_history::Model>, Vec<db::head_to_head::Model>) {
+        let mut history = db::stats_history::Entity::find()
+            .order_by_asc(db::stats_history::Column::Id)
+            .all(db)
+            .await
+            .unwrap();
+        for st in &mut history {
+            st.id = 0;
+        }
+        let h2h = db::head_to_head::Entity::find()
+            .order_by_asc(db::head_to_head::Column::BotId)
+            .order_by_asc(db::head_to_head::Column::OpponentId)
+            .all(db)
+            .await
+            .unwrap();
+        (history, h2h)
+    }
+
+    #[tokio::test]
+    async fn recalculate_twice_keeps_the_rows() {
+        let db = game_db().await;
+        let diffs = recalculate(&db, 1).await.unwrap().diffs;
+        assert!(diffs.iter().all(|d| d.before.is_none()), "{diffs:?}");
+        let (history, h2h) = rows(&db).await;
+        assert_eq!(history.len(), 6);
+        assert_eq!(h2h.len(), 2);
+        assert_eq!((h2h[0].wins, h2h[0].draws, h2h[0].losses), (1, 1, 1));
+
+        let diffs = recalculate(&db, 1).await.unwrap().diffs;
+        assert_eq!(diffs.len(), 2);
+        assert!(diffs.iter().all(|d| !d.changed()), "{diffs:?}");
+        assert_eq!(rows(&db).await, (history, h2h));
+    }
+
+    #[tokio::test]
+    async fn preview_reports_changed_bots() {
+        let db = game_db().await;
+        recalculate(&db, 1).await.unwrap();
+        let rebuild = preview(&db, 1).await.unwrap();
+        assert!(rebuild.diffs.iter().all(|d| !d.changed()));
+        assert_eq!(rebuild.deleted_matches, 0);
+
+        // E.g. a score that was fixed after the stats were updated.
+        db::match_participations::Entity::update_many()
+            .col_expr(
+                db::match_participations::Column::Score,
+                sea_orm::sea_query::Expr::value(1.),
+            )
+            .filter(db::match_participations::Column::MatchId.eq(3))
+            .filter(db::match_participations::Column::BotId.eq(1))
+            .exec(&db)
+            .await
+            .unwrap();
+        let before = rows(&db).await;
+        let diffs = preview(&db, 1).await.unwrap().diffs;
+        let changed = diffs
+            .iter()
+            .filter(|d| d.changed())
+            .map(|d| d.bot_id)
+            .collect::<Vec<_>>();
+        assert_eq!(changed, vec![1, 2]);
+        let bot1 = &diffs[0];
+        assert_eq!(bot1.before.as_ref().unwrap().average_score, 0.5);
+        assert!((bot1.after.as_ref().unwrap().average_score - 2.5 / 3.).abs() < 1e-9);
+        // Nothing is written.
+        assert_eq!(rows(&db).await, before);
+    }
+
+    #[tokio::test]
+    async fn preview_reports_deleted_matches() {
+        let db = game_db().await;
+        recalculate(&db, 1).await.unwrap();
+        // As the cleanup of old matches does.
+        db::match_participations::Entity::delete_many()
+            .filter(db::match_participations::Column::MatchId.eq(1))
+            .exec(&db)
+            .await
+            .unwrap();
+        db::matches::Entity::delete_by_id(1)
+            .exec(&db)
+            .await
+            .unwrap();
+        let rebuild = preview(&db, 1).await.unwrap();
+        assert_eq!(rebuild.deleted_matches, 1);
+        let matches = |s: &Option<Summary>| s.as_ref().unwrap().matches;
+        assert!(rebuild
+            .diffs
+            .iter()
+            .all(|d| (matches(&d.before), matches(&d.after)) == (3, 2)));
+        // Once applied, the stats no longer count it.
+        assert_eq!(recalculate(&db, 1).await.unwrap().deleted_matches, 1);
+        assert_eq!(preview(&db, 1).await.unwrap().deleted_matches, 0);
+    }
+}
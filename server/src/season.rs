@@ -0,0 +1,349 @@
+// Seasons of a game: ratings start over (or decay) when a season starts, new
+// bots are accepted until the submission deadline, the frozen bots then play
+// more matches until the end, when the standings are archived.
+
+use anyhow::Context;
+use sea_orm::prelude::TimeDateTimeWithTimeZone;
+use sea_orm::{
+    ColumnTrait, Condition, ConnectionTrait, DbErr, EntityTrait, QueryFilter, QuerySelect, Set,
+    TransactionTrait,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::rating;
+use proglad_db as db;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Phase {
+    Upcoming,
+    Submissions,
+    Evaluation,
+    Ended,
+}
+
+impl Phase {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Phase::Upcoming => "Upcoming",
+            Phase::Submissions => "Open for submissions",
+            Phase::Evaluation => "Final evaluation",
+            Phase::Ended => "Ended",
+        }
+    }
+}
+
+pub fn phase(season: &db::seasons::Model, now: TimeDateTimeWithTimeZone) -> Phase {
+    if now < season.start_time {
+        Phase::Upcoming
+    } else if now < season.submission_deadline {
+        Phase::Submissions
+    } else if now < season.end_time {
+        Phase::Evaluation
+    } else {
+        Phase::Ended
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Standing {
+    pub bot_id: i64,
+    pub name: String,
+    pub owner: String,
+    pub rating: Option<f64>,
+    pub matches_played: i64,
+    pub average_score: f64,
+}
+
+// The season of the game that is running now, if any.
+pub async fn running_season<C: ConnectionTrait>(
+    db: &C,
+    game_id: i64,
+) -> anyhow::Result<Option<db::seasons::Model>> {
+    db::seasons::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::seasons::Column::GameId.eq(game_id))
+                .add(db::seasons::Column::Status.eq(db::seasons::Status::Running)),
+        )
+        .one(db)
+        .await
+        .context(format!(
+            "Failed to fetch the running season of game {game_id}"
+        ))
+}
+
+// Whether the game takes no new bots because its season is being evaluated.
+pub async fn submissions_frozen<C: ConnectionTrait>(db: &C, game_id: i64) -> anyhow::Result<bool> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    Ok(running_season(db, game_id)
+        .await?
+        .is_some_and(|s| phase(&s, now) == Phase::Evaluation))
+}
+
+// Ranked by the conservative rating of the current rating system of the game,
+// then by the average score. Only bots that played in the season are ranked.
+pub async fn standings<C: ConnectionTrait>(
+    db: &C,
+    game: &db::games::Model,
+) -> anyhow::Result<Vec<Standing>> {
+    let stats = db::stats_history::Entity::find()
+        .filter(db::stats_history::Column::Latest.eq(true))
+        .filter(db::stats_history::Column::TotalMatches.gt(0))
+        .find_also_related(db::bots::Entity)
+        .filter(db::bots::Column::GameId.eq(game.id))
+        .all(db)
+        .await
+        .context(format!("Failed to fetch stats of game {}", game.id))?;
+    let owners = db::accounts::Entity::find()
+        .filter(
+            db::accounts::Column::Id.is_in(
+                stats
+                    .iter()
+                    .filter_map(|(_, b)| b.as_ref().map(|b| b.owner_id)),
+            ),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch bot owners")?
+        .into_iter()
+        .map(|a| (a.id, a.name))
+        .collect::<HashMap<_, _>>();
+    let mut standings = stats
+        .into_iter()
+        .filter_map(|(st, b)| {
+            let b = b?;
+            Some(Standing {
+                bot_id: b.id,
+                name: b.name,
+                owner: owners.get(&b.owner_id).cloned().unwrap_or_default(),
+                rating: st
+                    .conservative_rating
+                    .filter(|_| st.rating_system == Some(game.rating_system)),
+                matches_played: st.total_matches,
+                average_score: st.total_score / st.total_matches as f64,
+            })
+        })
+        .collect::<Vec<_>>();
+    standings.sort_by(rank);
+    Ok(standings)
+}
+
+// Better standings first, the unrated ones last. Ratings computed from broken
+// scores may be NaN, which total_cmp orders instead of panicking.
+fn rank(x: &Standing, y: &Standing) -> std::cmp::Ordering {
+    let rating = |s: &Standing| s.rating.unwrap_or_default();
+    y.rating
+        .is_some()
+        .cmp(&x.rating.is_some())
+        .then(rating(y).total_cmp(&rating(x)))
+        .then(y.average_score.total_cmp(&x.average_score))
+}
+
+// Starts and finishes the seasons that are due.
+pub async fn scheduling_round<C: ConnectionTrait + TransactionTrait>(db: &C) -> anyhow::Result<()> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    let due = db::seasons::Entity::find()
+        .filter(
+            Condition::any()
+                .add(
+                    Condition::all()
+                        .add(db::seasons::Column::Status.eq(db::seasons::Status::Upcoming))
+                        .add(db::seasons::Column::StartTime.lte(now)),
+                )
+                .add(
+                    Condition::all()
+                        .add(db::seasons::Column::Status.eq(db::seasons::Status::Running))
+                        .add(db::seasons::Column::EndTime.lte(now)),
+                ),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch due seasons")?;
+    for season in due {
+        let season_id = season.id;
+        let res = db
+            .transaction(|txn| {
+                Box::pin(async move {
+                    match season.status {
+                        db::seasons::Status::Upcoming => start(txn, &season).await,
+                        _ => finish(txn, &season).await,
+                    }
+                    .map_err(|e| DbErr::Custom(format!("{e:#}")))
+                })
+            })
+            .await;
+        if let Err(e) = res {
+            log::error!("Failed to update season {season_id}: {e:?}");
+        }
+    }
+    Ok(())
+}
+
+async fn start<C: ConnectionTrait>(db: &C, season: &db::seasons::Model) -> anyhow::Result<()> {
+    // A game runs one season at a time.
+    if let Some(running) = running_season(db, season.game_id).await? {
+        finish(db, &running).await?;
+    }
+    let game = db::games::Entity::find_by_id(season.game_id)
+        .one(db)
+        .await?
+        .context(format!("Game {} not found", season.game_id))?;
+    let bot_ids = db::bots::Entity::find()
+        .filter(db::bots::Column::GameId.eq(game.id))
+        .select_only()
+        .column(db::bots::Column::Id)
+        .into_tuple::<i64>()
+        .all(db)
+        .await
+        .context("Failed to fetch bots of the game")?;
+    let stats = db::stats_history::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::stats_history::Column::Latest.eq(true))
+                .add(db::stats_history::Column::BotId.is_in(bot_ids)),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch latest stats")?;
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    if !stats.is_empty() {
+        db::stats_history::Entity::update_many()
+            .set(db::stats_history::ActiveModel {
+                latest: Set(false),
+                ..Default::default()
+            })
+            .filter(db::stats_history::Column::Id.is_in(stats.iter().map(|st| st.id)))
+            .exec(db)
+            .await
+            .context("Failed to update non-latest stats")?;
+        let rating_system = rating::rating_system(game.rating_system);
+        let new_stats = stats.iter().map(|st| {
+            let mut new_st = db::stats_history::ActiveModel {
+                bot_id: Set(st.bot_id),
+                latest: Set(true),
+                update_time: Set(now),
+                match_id: Set(None),
+                total_score: Set(0.),
+                total_matches: Set(0),
+                total_score_sq: Set(0.),
+                total_wins: Set(0.),
+                sampled_matches: Set(0),
+                ..Default::default()
+            };
+            let previous = st
+                .rating_system
+                .filter(|s| *s == game.rating_system)
+                .and_then(|_| {
+                    Some(rating::Rating {
+                        mu: st.rating?,
+                        sigma: st.rating_deviation?,
+                        volatility: st.rating_volatility?,
+                    })
+                });
+            // Reset ratings are left unset, the first match starts them over.
+            if let (db::seasons::RatingCarryOver::Decay, Some(previous)) =
+                (season.rating_carry_over, previous)
+            {
+                let r = rating_system.decay(&previous);
+                new_st.rating_system = Set(Some(game.rating_system));
+                new_st.rating = Set(Some(r.mu));
+                new_st.rating_deviation = Set(Some(r.sigma));
+                new_st.rating_volatility = Set(Some(r.volatility));
+                new_st.conservative_rating = Set(Some(rating_system.conservative(&r)));
+            }
+            new_st
+        });
+        db::stats_history::Entity::insert_many(new_stats)
+            .exec(db)
+            .await
+            .context("Failed to insert new stats")?;
+    }
+    db::seasons::Entity::update(db::seasons::ActiveModel {
+        id: Set(season.id),
+        status: Set(db::seasons::Status::Running),
+        ..Default::default()
+    })
+    .exec(db)
+    .await
+    .context("Failed to start the season")?;
+    log::info!("Season {} of game {} started", season.id, game.id);
+    Ok(())
+}
+
+async fn finish<C: ConnectionTrait>(db: &C, season: &db::seasons::Model) -> anyhow::Result<()> {
+    let game = db::games::Entity::find_by_id(season.game_id)
+        .one(db)
+        .await?
+        .context(format!("Game {} not found", season.game_id))?;
+    let standings = standings(db, &game).await?;
+    db::seasons::Entity::update(db::seasons::ActiveModel {
+        id: Set(season.id),
+        status: Set(db::seasons::Status::Finished),
+        final_standings: Set(Some(serde_json::to_string(&standings)?)),
+        ..Default::default()
+    })
+    .exec(db)
+    .await
+    .context("Failed to finish the season")?;
+    log::info!("Season {} of game {} finished", season.id, game.id);
+    Ok(())
+}
+
+// Matches to schedule for the game in one scheduling round, more during the
+// evaluation of a season.
+pub async fn matches_per_round<C: ConnectionTrait>(db: &C, game_id: i64) -> anyhow::Result<i32> {
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    Ok(match running_season(db, game_id).await? {
+        Some(s) if phase(&s, now) == Phase::Evaluation => s.evaluation_matches.max(1),
+        _ => 1,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phases() {
+        let t = |h| time::macros::datetime!(2024-11-24 00:00 UTC) + time::Duration::hours(h);
+        let season = db::seasons::Model {
+            id: 1,
+            game_id: 1,
+            name: "s".to_owned(),
+            status: db::seasons::Status::Running,
+            start_time: t(0),
+            submission_deadline: t(10),
+            end_time: t(12),
+            rating_carry_over: db::seasons::RatingCarryOver::Reset,
+            evaluation_matches: 3,
+            final_standings: None,
+        };
+        assert_eq!(phase(&season, t(-1)), Phase::Upcoming);
+        assert_eq!(phase(&season, t(0)), Phase::Submissions);
+        assert_eq!(phase(&season, t(11)), Phase::Evaluation);
+        assert_eq!(phase(&season, t(12)), Phase::Ended);
+    }
+
+    #[test]
+    fn ranks_nan_ratings() {
+        let standing = |bot_id, rating, average_score| Standing {
+            bot_id,
+            name: String::new(),
+            owner: String::new(),
+            rating,
+            matches_played: 1,
+            average_score,
+        };
+        let mut standings = [
+            standing(1, None, 3.),
+            standing(2, Some(f64::NAN), 1.),
+            standing(3, Some(10.), f64::NAN),
+            standing(4, Some(20.), 0.),
+            standing(5, Some(10.), 2.),
+        ];
+        standings.sort_by(rank);
+        let ids = standings.iter().map(|s| s.bot_id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![2, 4, 3, 5, 1]);
+    }
+}
@@ -67,21 +67,36 @@ pub async fn create(config: Config) -> anyhow::Result<Handle> {
                 secret_key.clone(),
             ))
             .app_data(app_state.clone())
+            .service(handlers::get_bot::get_bot)
+            .service(handlers::get_bot::get_bot_history_chart)
             .service(handlers::get_bots::get_bots)
+            .service(handlers::get_challenge::get_challenge)
             .service(handlers::get_edit_game::get_edit_game)
             .service(handlers::get_files::get_files)
             .service(handlers::get_files::get_files_nameless)
             .service(handlers::get_game::get_game)
             .service(handlers::get_games::get_games)
             .service(handlers::get_index::get_index)
+            .service(handlers::get_leaderboard::get_leaderboard)
             .service(handlers::get_logout::get_logout)
             .service(handlers::get_matches::get_matches)
+            .service(handlers::get_recalculate_stats::get_recalculate_stats)
+            .service(handlers::get_season::get_season)
+            .service(handlers::get_tournament::get_tournament)
             .service(handlers::get_visualizer::get_visualizer)
+            .service(handlers::get_visualizer::get_visualizer_export)
+            .service(handlers::get_visualizer::get_compare)
             .service(handlers::kratos_hooks::post_kratos_after_registration_hook)
             .service(handlers::kratos_hooks::post_kratos_after_settings_hook)
+            .service(handlers::post_challenge::post_challenge)
             .service(handlers::post_create_bot::post_create_bot)
+            .service(handlers::post_create_bot_version::post_create_bot_version)
+            .service(handlers::post_create_season::post_create_season)
+            .service(handlers::post_create_tournament::post_create_tournament)
             .service(handlers::post_edit_bot::post_edit_bot)
             .service(handlers::post_edit_game::post_edit_game)
+            .service(handlers::post_promote_bot_version::post_promote_bot_version)
+            .service(handlers::post_recalculate_stats::post_recalculate_stats)
             .service(handlers::post_schedule_match::post_schedule_match)
             .service(actix_files::Files::new(
                 "/static",
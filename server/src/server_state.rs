@@ -11,7 +11,7 @@ pub struct ServerState<'a> {
     pub file_store: crate::file_store::FileStore,
 }
 
-pub fn server_state(req: &HttpRequest) -> Result<&ServerState, AppHttpError> {
+pub fn server_state(req: &HttpRequest) -> Result<&ServerState<'_>, AppHttpError> {
     req.app_data::<ServerState>().ok_or_else(move || {
         log::error!("Server state is not there");
         AppHttpError::Internal
@@ -0,0 +1,129 @@
+// Line diffs of source code between bot versions, in the unified format.
+
+use std::fmt::Write;
+
+// Beyond this many compared line pairs the changed middle is shown as
+// removed and added as a whole.
+const MAX_CELLS: usize = 4_000_000;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Op<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+fn ops<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Op<'a>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    let mut ops = old[..prefix]
+        .iter()
+        .map(|l| Op::Same(l))
+        .collect::<Vec<_>>();
+    if a.len() * b.len() > MAX_CELLS {
+        ops.extend(a.iter().map(|l| Op::Removed(l)));
+        ops.extend(b.iter().map(|l| Op::Added(l)));
+    } else {
+        // lcs[i][j] is the longest common subsequence of a[i..] and b[j..].
+        let w = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * w];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * w + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                ops.push(Op::Same(a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+                ops.push(Op::Removed(a[i]));
+                i += 1;
+            } else {
+                ops.push(Op::Added(b[j]));
+                j += 1;
+            }
+        }
+    }
+    ops.extend(old[old.len() - suffix..].iter().map(|l| Op::Same(l)));
+    ops
+}
+
+// Changed lines with `context` unchanged lines around them, empty if the
+// sources are the same.
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let ops = ops(&old, &new);
+    let changes = ops
+        .iter()
+        .enumerate()
+        .filter(|(_, op)| !matches!(op, Op::Same(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+    // Ranges of ops, merged when their context overlaps.
+    let mut hunks = Vec::<(usize, usize)>::new();
+    for i in changes {
+        let (start, end) = (i.saturating_sub(context), (i + context + 1).min(ops.len()));
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+    let mut out = String::new();
+    for (start, end) in hunks {
+        let in_old = |op: &&Op| !matches!(op, Op::Added(_));
+        let in_new = |op: &&Op| !matches!(op, Op::Removed(_));
+        let _ = writeln!(
+            out,
+            "@@ -{},{} +{},{} @@",
+            ops[..start].iter().filter(in_old).count() + 1,
+            ops[start..end].iter().filter(in_old).count(),
+            ops[..start].iter().filter(in_new).count() + 1,
+            ops[start..end].iter().filter(in_new).count(),
+        );
+        for op in &ops[start..end] {
+            let _ = match op {
+                Op::Same(l) => writeln!(out, " {l}"),
+                Op::Removed(l) => writeln!(out, "-{l}"),
+                Op::Added(l) => writeln!(out, "+{l}"),
+            };
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn hunks_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+        assert_eq!(
+            unified(old, new, 1),
+            "@@ -1,3 +1,3 @@\n a\n-b\n+B\n c\n@@ -8,1 +8,2 @@\n h\n+i\n"
+        );
+        assert_eq!(
+            unified(old, new, 3),
+            "@@ -1,8 +1,9 @@\n a\n-b\n+B\n c\n d\n e\n f\n g\n h\n+i\n"
+        );
+        assert_eq!(unified(old, old, 3), "");
+    }
+}
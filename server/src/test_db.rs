@@ -0,0 +1,98 @@
+// Fixtures for the tests that need a database: an in-memory one with the
+// migrations applied, and the rows that games and bots depend on.
+use sea_orm::prelude::TimeDateTimeWithTimeZone;
+use sea_orm::{ActiveValue::Set, DatabaseConnection, EntityTrait};
+use sea_orm_migration::MigratorTrait;
+
+use proglad_db as db;
+
+pub const ACCOUNT_ID: i64 = 1;
+
+pub fn time(hours: i64) -> TimeDateTimeWithTimeZone {
+    time::macros::datetime!(2024-12-15 00:00 UTC) + time::Duration::hours(hours)
+}
+
+// With the account ACCOUNT_ID that owns everything.
+pub async fn new() -> DatabaseConnection {
+    let db = sea_orm::Database::connect("sqlite::memory:")
+        .await
+        .expect("Failed to open in-memory sqlite DB.");
+    migration::Migrator::up(&db, None)
+        .await
+        .expect("Applying initial DB migrations failed");
+    db::accounts::Entity::insert(db::accounts::ActiveModel {
+        id: Set(ACCOUNT_ID),
+        name: Set("owner".to_owned()),
+        email: Set(None),
+    })
+    .exec(&db)
+    .await
+    .unwrap();
+    db
+}
+
+async fn program(db: &DatabaseConnection) -> i64 {
+    db::programs::Entity::insert(db::programs::ActiveModel {
+        language: Set(db::programs::Language::Rust),
+        status: Set(db::programs::Status::New),
+        status_reason: Set(None),
+        status_update_time: Set(time(0)),
+        is_public: Set(None),
+        ..Default::default()
+    })
+    .exec(db)
+    .await
+    .unwrap()
+    .last_insert_id
+}
+
+pub async fn game(
+    db: &DatabaseConnection,
+    id: i64,
+    players: std::ops::RangeInclusive<i32>,
+    rating_system: db::games::RatingSystem,
+) {
+    let program_id = program(db).await;
+    db::games::Entity::insert(db::games::ActiveModel {
+        id: Set(id),
+        name: Set(format!("game {id}")),
+        description: Set(String::new()),
+        min_players: Set(*players.start()),
+        max_players: Set(*players.end()),
+        program_id: Set(program_id),
+        status: Set(db::games::Status::Active),
+        param: Set(None),
+        protocol_version: Set(None),
+        reveal_hidden_events: Set(false),
+        rating_system: Set(rating_system),
+        matchmaking: Set(db::games::Matchmaking::Balanced),
+        distinct_owners: Set(false),
+    })
+    .exec(db)
+    .await
+    .unwrap();
+}
+
+pub async fn bot(db: &DatabaseConnection, id: i64, game_id: i64, active: bool) {
+    let program_id = program(db).await;
+    db::bots::Entity::insert(db::bots::ActiveModel {
+        id: Set(id),
+        name: Set(format!("bot {id}")),
+        owner_id: Set(ACCOUNT_ID),
+        game_id: Set(game_id),
+        program_id: Set(program_id),
+        owner_set_status: Set(if active {
+            db::bots::OwnerSetStatus::Active
+        } else {
+            db::bots::OwnerSetStatus::Inactive
+        }),
+        system_status: Set(db::bots::SystemStatus::Ok),
+        system_status_reason: Set(None),
+        creation_time: Set(time(0)),
+        status_update_time: Set(time(0)),
+        is_reference_bot: Set(None),
+    })
+    .exec(db)
+    .await
+    .unwrap();
+}
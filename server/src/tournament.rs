@@ -0,0 +1,564 @@
+// Fixed events of two-player matches on top of the continuous ladder. Each
+// round is scheduled as work items with explicit players once the previous
+// one is over, standings are recomputed from the match results.
+
+use anyhow::{anyhow, Context};
+use sea_orm::prelude::TimeDateTimeWithTimeZone;
+use sea_orm::{ColumnTrait, Condition, ConnectionTrait, EntityTrait, QueryFilter, Set};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use proglad_db as db;
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub bot_id: i64,
+    pub seed: i32,
+    pub name: String,
+}
+
+// A tournament match, without scores if it failed.
+#[derive(Debug, Clone)]
+pub struct MatchResult {
+    pub round: i32,
+    pub scores: Vec<(i64, Option<f64>)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Standing {
+    pub bot_id: i64,
+    pub name: String,
+    pub seed: i32,
+    pub played: i32,
+    pub wins: i32,
+    pub draws: i32,
+    pub losses: i32,
+    // 1 per win or bye, 0.5 per draw.
+    pub points: f64,
+    // Knockout only.
+    pub eliminated_in_round: Option<i32>,
+}
+
+// Rounds needed to finish the tournament, Swiss tournaments take any number.
+pub fn default_rounds(format: db::tournaments::Format, num_entries: usize) -> i32 {
+    let log2 = (num_entries.max(1) as f64).log2().ceil() as i32;
+    match format {
+        db::tournaments::Format::RoundRobin if num_entries.is_multiple_of(2) => {
+            num_entries as i32 - 1
+        }
+        db::tournaments::Format::RoundRobin => num_entries as i32,
+        db::tournaments::Format::Swiss | db::tournaments::Format::Knockout => log2,
+    }
+}
+
+// Everyone plays everyone once over the rounds, by the circle method. `round`
+// starts at 1.
+pub fn round_robin_pairings(seeded: &[i64], round: i32) -> Vec<[i64; 2]> {
+    let mut bots = seeded.iter().copied().map(Some).collect::<Vec<_>>();
+    if bots.len() % 2 == 1 {
+        bots.push(None);
+    }
+    let n = bots.len();
+    if n < 2 {
+        return vec![];
+    }
+    bots[1..].rotate_right((round as usize - 1) % (n - 1));
+    (0..n / 2)
+        .filter_map(|i| Some([bots[i]?, bots[n - 1 - i]?]))
+        .collect()
+}
+
+// Pairs bots with close standings, avoiding rematches when possible.
+// `standings` are sorted best first.
+pub fn swiss_pairings(standings: &[Standing], played: &HashSet<(i64, i64)>) -> Vec<[i64; 2]> {
+    let mut unpaired = standings.iter().map(|s| s.bot_id).collect::<Vec<_>>();
+    // The lowest ranked bot gets a bye.
+    if unpaired.len() % 2 == 1 {
+        unpaired.pop();
+    }
+    let mut pairs = vec![];
+    while unpaired.len() >= 2 {
+        let a = unpaired.remove(0);
+        let i = unpaired
+            .iter()
+            .position(|b| !played.contains(&(a, *b)))
+            .unwrap_or(0);
+        pairs.push([a, unpaired.remove(i)]);
+    }
+    pairs
+}
+
+// The strongest remaining bot plays the weakest one. Top seeds get byes in
+// the first round when the number of bots is not a power of two.
+// `alive` is sorted by seed.
+pub fn knockout_pairings(alive: &[i64]) -> Vec<[i64; 2]> {
+    let byes = alive.len().next_power_of_two() - alive.len();
+    let playing = &alive[byes.min(alive.len())..];
+    (0..playing.len() / 2)
+        .map(|i| [playing[i], playing[playing.len() - 1 - i]])
+        .collect()
+}
+
+// The winner of a two-player match. Draws and failed matches go to the better
+// seed.
+fn winner(m: &MatchResult, seeds: &HashMap<i64, i32>) -> Option<i64> {
+    let [(a, sa), (b, sb)] = m.scores[..] else {
+        return None;
+    };
+    let better_seed = if seeds.get(&a) <= seeds.get(&b) { a } else { b };
+    match (sa, sb) {
+        (Some(sa), Some(sb)) if sa > sb => Some(a),
+        (Some(sa), Some(sb)) if sb > sa => Some(b),
+        _ => Some(better_seed),
+    }
+}
+
+// Standings after the given number of complete rounds, best first.
+pub fn standings(
+    format: db::tournaments::Format,
+    entries: &[Entry],
+    matches: &[MatchResult],
+    complete_rounds: i32,
+) -> Vec<Standing> {
+    let seeds = entries
+        .iter()
+        .map(|e| (e.bot_id, e.seed))
+        .collect::<HashMap<_, _>>();
+    let mut standings = entries
+        .iter()
+        .map(|e| {
+            (
+                e.bot_id,
+                Standing {
+                    bot_id: e.bot_id,
+                    name: e.name.clone(),
+                    seed: e.seed,
+                    played: 0,
+                    wins: 0,
+                    draws: 0,
+                    losses: 0,
+                    points: 0.,
+                    eliminated_in_round: None,
+                },
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    for m in matches.iter().filter(|m| m.round <= complete_rounds) {
+        for (i, (bot_id, score)) in m.scores.iter().enumerate() {
+            let Some(st) = standings.get_mut(bot_id) else {
+                continue;
+            };
+            st.played += 1;
+            for (j, (_, other)) in m.scores.iter().enumerate() {
+                if i == j {
+                    continue;
+                }
+                match (score, other) {
+                    (Some(s), Some(o)) if s > o => st.wins += 1,
+                    (Some(s), Some(o)) if s == o => st.draws += 1,
+                    _ => st.losses += 1,
+                }
+            }
+        }
+        if format == db::tournaments::Format::Knockout {
+            let winner = winner(m, &seeds);
+            for (bot_id, _) in m.scores.iter().filter(|(b, _)| Some(*b) != winner) {
+                if let Some(st) = standings.get_mut(bot_id) {
+                    st.eliminated_in_round = Some(m.round);
+                }
+            }
+        }
+    }
+    if format == db::tournaments::Format::Swiss {
+        for round in 1..=complete_rounds {
+            let playing = matches
+                .iter()
+                .filter(|m| m.round == round)
+                .flat_map(|m| m.scores.iter().map(|(b, _)| *b))
+                .collect::<HashSet<_>>();
+            for st in standings.values_mut() {
+                if !playing.contains(&st.bot_id) {
+                    st.wins += 1;
+                }
+            }
+        }
+    }
+    let mut standings = standings
+        .into_values()
+        .map(|mut st| {
+            st.points = st.wins as f64 + st.draws as f64 / 2.;
+            st
+        })
+        .collect::<Vec<_>>();
+    standings.sort_by(|x, y| {
+        let eliminated = |s: &Standing| s.eliminated_in_round.unwrap_or(i32::MAX);
+        eliminated(y)
+            .cmp(&eliminated(x))
+            .then(y.points.total_cmp(&x.points))
+            .then(x.seed.cmp(&y.seed))
+    });
+    standings
+}
+
+// Pairings of the given round, empty if the tournament is over.
+pub fn pairings(
+    format: db::tournaments::Format,
+    entries: &[Entry],
+    matches: &[MatchResult],
+    round: i32,
+) -> Vec<[i64; 2]> {
+    let mut seeded = entries.to_vec();
+    seeded.sort_by_key(|e| e.seed);
+    let seeded = seeded.iter().map(|e| e.bot_id).collect::<Vec<_>>();
+    match format {
+        db::tournaments::Format::RoundRobin => round_robin_pairings(&seeded, round),
+        db::tournaments::Format::Swiss => {
+            let played = matches
+                .iter()
+                .filter_map(|m| match m.scores[..] {
+                    [(a, _), (b, _)] => Some([(a, b), (b, a)]),
+                    _ => None,
+                })
+                .flatten()
+                .collect::<HashSet<_>>();
+            swiss_pairings(&standings(format, entries, matches, round - 1), &played)
+        }
+        db::tournaments::Format::Knockout => {
+            let standings = standings(format, entries, matches, round - 1);
+            let alive = seeded
+                .into_iter()
+                .filter(|id| {
+                    standings
+                        .iter()
+                        .any(|s| s.bot_id == *id && s.eliminated_in_round.is_none())
+                })
+                .collect::<Vec<_>>();
+            knockout_pairings(&alive)
+        }
+    }
+}
+
+// Creates a tournament of the active bots of the game, seeded by their rating.
+pub async fn create_tournament<C: ConnectionTrait>(
+    db: &C,
+    game: &db::games::Model,
+    name: &str,
+    format: db::tournaments::Format,
+    rounds: Option<i32>,
+) -> anyhow::Result<i64> {
+    if game.min_players > 2 || game.max_players < 2 {
+        return Err(anyhow!(
+            "Tournaments need two-player matches, game {} has {}-{} players",
+            game.id,
+            game.min_players,
+            game.max_players
+        ));
+    }
+    let bots = db::bots::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::bots::Column::GameId.eq(game.id))
+                .add(db::bots::Column::SystemStatus.eq(db::bots::SystemStatus::Ok))
+                .add(db::bots::Column::OwnerSetStatus.eq(db::bots::OwnerSetStatus::Active)),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch active bots")?;
+    if bots.len() < 2 {
+        return Err(anyhow!("A tournament needs at least 2 active bots"));
+    }
+    let ratings = db::stats_history::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::stats_history::Column::Latest.eq(true))
+                .add(db::stats_history::Column::BotId.is_in(bots.iter().map(|b| b.id))),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch bot stats")?
+        .into_iter()
+        .filter(|st| st.rating_system == Some(game.rating_system))
+        .filter_map(|st| Some((st.bot_id, st.conservative_rating?)))
+        .collect::<HashMap<_, _>>();
+    let mut bot_ids = bots.iter().map(|b| b.id).collect::<Vec<_>>();
+    // Unrated bots go last, older bots first among equals.
+    bot_ids.sort_by(|x, y| {
+        let rating = |id| ratings.get(id).copied().unwrap_or(f64::NEG_INFINITY);
+        rating(y).total_cmp(&rating(x)).then(x.cmp(y))
+    });
+    let rounds = rounds
+        .filter(|_| format == db::tournaments::Format::Swiss)
+        .unwrap_or_else(|| default_rounds(format, bot_ids.len()));
+    let tournament = db::tournaments::ActiveModel {
+        game_id: Set(game.id),
+        name: Set(name.to_owned()),
+        format: Set(format),
+        status: Set(db::tournaments::Status::Running),
+        rounds: Set(rounds),
+        current_round: Set(0),
+        creation_time: Set(TimeDateTimeWithTimeZone::now_utc()),
+        ..Default::default()
+    };
+    let tournament_id = db::tournaments::Entity::insert(tournament)
+        .exec(db)
+        .await
+        .context("Failed to insert tournament")?
+        .last_insert_id;
+    let entries =
+        bot_ids
+            .iter()
+            .enumerate()
+            .map(|(i, bot_id)| db::tournament_entries::ActiveModel {
+                tournament_id: Set(tournament_id),
+                bot_id: Set(*bot_id),
+                seed: Set(1 + i as i32),
+            });
+    db::tournament_entries::Entity::insert_many(entries)
+        .exec(db)
+        .await
+        .context(format!(
+            "Failed to insert entries of tournament {tournament_id}"
+        ))?;
+    Ok(tournament_id)
+}
+
+pub async fn db_entries<C: ConnectionTrait>(
+    db: &C,
+    tournament_id: i64,
+) -> anyhow::Result<Vec<Entry>> {
+    let entries = db::tournament_entries::Entity::find()
+        .filter(db::tournament_entries::Column::TournamentId.eq(tournament_id))
+        .find_also_related(db::bots::Entity)
+        .all(db)
+        .await
+        .context(format!(
+            "Failed to fetch entries of tournament {tournament_id}"
+        ))?;
+    Ok(entries
+        .into_iter()
+        .map(|(e, b)| Entry {
+            bot_id: e.bot_id,
+            seed: e.seed,
+            name: b.map(|b| b.name).unwrap_or_default(),
+        })
+        .collect())
+}
+
+pub async fn db_match_results<C: ConnectionTrait>(
+    db: &C,
+    tournament_id: i64,
+) -> anyhow::Result<Vec<(db::matches::Model, MatchResult)>> {
+    let matches = db::matches::Entity::find()
+        .filter(db::matches::Column::TournamentId.eq(tournament_id))
+        .find_with_related(db::match_participations::Entity)
+        .all(db)
+        .await
+        .context(format!(
+            "Failed to fetch matches of tournament {tournament_id}"
+        ))?;
+    Ok(matches
+        .into_iter()
+        .map(|(m, mut ps)| {
+            ps.sort_by_key(|p| p.ingame_player);
+            let result = MatchResult {
+                round: m.tournament_round.unwrap_or_default(),
+                scores: ps.iter().map(|p| (p.bot_id, p.score)).collect(),
+            };
+            (m, result)
+        })
+        .collect())
+}
+
+// Schedules the next round of each running tournament whose current round is
+// over, or finishes the tournament.
+pub async fn scheduling_round<C: ConnectionTrait>(db: &C, priority: i64) -> anyhow::Result<()> {
+    let running = db::tournaments::Entity::find()
+        .filter(db::tournaments::Column::Status.eq(db::tournaments::Status::Running))
+        .all(db)
+        .await
+        .context("Failed to fetch running tournaments")?;
+    for t in running {
+        let tournament_id = t.id;
+        let _ = advance(db, t, priority).await.inspect_err(|e| {
+            log::error!("Failed to advance tournament {tournament_id}: {e:?}");
+        });
+    }
+    Ok(())
+}
+
+async fn advance<C: ConnectionTrait>(
+    db: &C,
+    t: db::tournaments::Model,
+    priority: i64,
+) -> anyhow::Result<()> {
+    let pending = db::work_items::Entity::find()
+        .filter(
+            Condition::all()
+                .add(db::work_items::Column::TournamentId.eq(t.id))
+                .add(db::work_items::Column::Status.is_in([
+                    db::work_items::Status::Scheduled,
+                    db::work_items::Status::Started,
+                ])),
+        )
+        .all(db)
+        .await
+        .context("Failed to fetch pending work items")?;
+    if !pending.is_empty() {
+        return Ok(());
+    }
+    let entries = db_entries(db, t.id).await?;
+    let matches = db_match_results(db, t.id)
+        .await?
+        .into_iter()
+        .map(|(_, r)| r)
+        .collect::<Vec<_>>();
+    let round = t.current_round + 1;
+    let pairs = if round <= t.rounds {
+        pairings(t.format, &entries, &matches, round)
+    } else {
+        vec![]
+    };
+    let now = TimeDateTimeWithTimeZone::now_utc();
+    if pairs.is_empty() {
+        let standings = standings(t.format, &entries, &matches, t.current_round);
+        let update = db::tournaments::ActiveModel {
+            id: Set(t.id),
+            status: Set(db::tournaments::Status::Finished),
+            end_time: Set(Some(now)),
+            final_standings: Set(Some(serde_json::to_string(&standings)?)),
+            ..Default::default()
+        };
+        db::tournaments::Entity::update(update)
+            .exec(db)
+            .await
+            .context("Failed to finish tournament")?;
+        log::info!("Tournament {} finished", t.id);
+        return Ok(());
+    }
+    let work_items = pairs.iter().map(|[a, b]| db::work_items::ActiveModel {
+        game_id: Set(Some(t.game_id)),
+        creation_time: Set(now),
+        work_type: Set(db::work_items::WorkType::RunMatch),
+        status: Set(db::work_items::Status::Scheduled),
+        priority: Set(priority),
+        bot_ids: Set(Some(format!("{a},{b}"))),
+        tournament_id: Set(Some(t.id)),
+        tournament_round: Set(Some(round)),
+        ..Default::default()
+    });
+    db::work_items::Entity::insert_many(work_items)
+        .exec(db)
+        .await
+        .context("Failed to schedule tournament matches")?;
+    let update = db::tournaments::ActiveModel {
+        id: Set(t.id),
+        current_round: Set(round),
+        ..Default::default()
+    };
+    db::tournaments::Entity::update(update)
+        .exec(db)
+        .await
+        .context("Failed to update the current round")?;
+    log::info!("Tournament {} round {round}: {pairs:?}", t.id);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use db::tournaments::Format;
+
+    fn entries(n: i64) -> Vec<Entry> {
+        (1..=n)
+            .map(|i| Entry {
+                bot_id: i * 10,
+                seed: i as i32,
+                name: format!("bot{i}"),
+            })
+            .collect()
+    }
+
+    // The better seed wins every match.
+    fn play(format: Format, entries: &[Entry]) -> (Vec<MatchResult>, Vec<Standing>) {
+        let mut matches = vec![];
+        let rounds = default_rounds(format, entries.len());
+        for round in 1..=rounds {
+            for [a, b] in pairings(format, entries, &matches, round) {
+                matches.push(MatchResult {
+                    round,
+                    scores: vec![(a, Some(-a as f64)), (b, Some(-b as f64))],
+                });
+            }
+        }
+        let standings = standings(format, entries, &matches, rounds);
+        (matches, standings)
+    }
+
+    #[test]
+    fn round_robin_plays_everyone_once() {
+        for n in [2, 5, 6] {
+            let entries = entries(n);
+            let (matches, standings) = play(Format::RoundRobin, &entries);
+            let mut pairs = matches
+                .iter()
+                .map(|m| {
+                    (
+                        m.scores[0].0.min(m.scores[1].0),
+                        m.scores[0].0.max(m.scores[1].0),
+                    )
+                })
+                .collect::<Vec<_>>();
+            pairs.sort();
+            pairs.dedup();
+            assert_eq!(pairs.len() as i64, n * (n - 1) / 2, "{n}");
+            assert_eq!(matches.len(), pairs.len());
+            assert_eq!(standings[0].wins as i64, n - 1);
+            assert_eq!(standings.last().unwrap().wins, 0);
+        }
+    }
+
+    #[test]
+    fn knockout_leaves_one_winner() {
+        let entries = entries(6);
+        let (matches, standings) = play(Format::Knockout, &entries);
+        assert_eq!(matches.len(), 5);
+        assert_eq!(standings[0].bot_id, 10);
+        assert_eq!(standings[0].eliminated_in_round, None);
+        assert!(standings[1..]
+            .iter()
+            .all(|s| s.eliminated_in_round.is_some()));
+        // Top seeds had byes.
+        assert!(matches
+            .iter()
+            .filter(|m| m.round == 1)
+            .all(|m| m.scores.iter().all(|(b, _)| *b > 20)));
+    }
+
+    #[test]
+    fn swiss_avoids_rematches() {
+        let entries = entries(8);
+        let (matches, table) = play(Format::Swiss, &entries);
+        let mut pairs = matches
+            .iter()
+            .map(|m| {
+                (
+                    m.scores[0].0.min(m.scores[1].0),
+                    m.scores[0].0.max(m.scores[1].0),
+                )
+            })
+            .collect::<Vec<_>>();
+        pairs.sort();
+        let len = pairs.len();
+        pairs.dedup();
+        assert_eq!(pairs.len(), len);
+        assert_eq!(table[0].bot_id, 10);
+        assert_eq!(table[0].points, 3.);
+
+        // The odd bot out gets a point for the bye.
+        let entries = entries[..3].to_vec();
+        let pairs = pairings(Format::Swiss, &entries, &[], 1);
+        assert_eq!(pairs, vec![[10, 20]]);
+        let st = standings(Format::Swiss, &entries, &[], 1);
+        assert_eq!(st.iter().find(|s| s.bot_id == 30).unwrap().points, 1.);
+    }
+}
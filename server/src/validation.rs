@@ -1,3 +1,6 @@
+// Shared with the visualizer, which only shows images with valid names.
+pub use proglad_api::visualize::validate_asset_name;
+
 pub fn validate_bot_name(name: &str) -> Result<(), String> {
     const MAX: usize = 30;
     if !(1..=MAX).contains(&name.len()) {
@@ -35,6 +38,21 @@ pub fn validate_account_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
+// Empty means the game server does not declare a protocol version.
+pub fn validate_protocol_version(v: &str) -> Result<Option<i32>, String> {
+    use proglad_api::textapi::{LEGACY_PROTOCOL_VERSION, PROTOCOL_VERSION};
+    let v = v.trim();
+    if v.is_empty() {
+        return Ok(None);
+    }
+    match v.parse::<u32>() {
+        Ok(n) if (LEGACY_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&n) => Ok(Some(n as i32)),
+        _ => Err(format!(
+            "{v} expected to be in range [{LEGACY_PROTOCOL_VERSION}..{PROTOCOL_VERSION}]"
+        )),
+    }
+}
+
 pub fn validate_game_name(name: &str) -> Result<(), String> {
     // TODO: more thorough validation.
     const MAX: usize = 30;
@@ -43,12 +43,18 @@ mod tests {
             kratos_api_url: "".to_owned(),
             fs_root_dir: "".into(),
             access_control,
+            challenges: Default::default(),
+            stats_recalculation_priority: 2000,
         };
         let manager_config = proglad_controller::manager::Config {
             container_name_prefix: format!("{test_name}-"),
             cache_dir: dir.as_ref().join("cache"),
             match_run_dir: dir.as_ref().join("matches"),
-            template_dir: Default::default(),
+            template_dir: [(
+                proglad_controller::manager::Language::RustCargo,
+                "../agent-templates/rust".into(),
+            )]
+            .into(),
             compilation_timeout: std::time::Duration::from_secs(30),
             agent_container_timeout: std::time::Duration::from_secs(3600),
             container_stdio_limit_bytes: 32000,
@@ -447,7 +453,7 @@ mod tests {
             .text("description", "Simple Game For Testing")
             .text("min_players", "3")
             .text("max_players", "6")
-            .text("language", "rust")
+            .text("language", "rustcargo")
             .text("param_string", "{num_players} 10 500 inlinevisualize")
             .part("markdown_file", markdown_file)
             .part("icon_file", icon_file)
@@ -1,8 +1,6 @@
 use clap::Parser;
-use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, TransactionTrait};
 
-use proglad_db as db;
-use proglad_server::engine;
+use proglad_server::recalculate;
 
 #[derive(Parser, Debug)]
 struct Config {
@@ -10,37 +8,51 @@ struct Config {
     db: String,
     #[arg(long)]
     game_id: i64,
+    // Only print what would change.
+    #[arg(long)]
+    dry_run: bool,
+}
+
+fn summary_text(s: &Option<recalculate::Summary>) -> String {
+    match s {
+        Some(s) => format!(
+            "{} matches, average score {:.2}, rating {}",
+            s.matches,
+            s.average_score,
+            s.rating.map_or("unrated".to_owned(), |r| format!("{r:.1}"))
+        ),
+        None => "no stats".to_owned(),
+    }
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cfg = Config::parse();
     let db = sea_orm::Database::connect(cfg.db).await?;
-    let matches = db::matches::Entity::find()
-        .filter(db::matches::Column::GameId.eq(cfg.game_id))
-        .order_by_asc(db::matches::Column::EndTime)
-        .all(&db)
-        .await?;
-    for m in matches {
-        println!("Processing match {}", m.id);
-        let participations = db::match_participations::Entity::find()
-            .filter(db::match_participations::Column::MatchId.eq(m.id))
-            .all(&db)
-            .await?;
-        let scores = participations
-            .into_iter()
-            .filter_map(|p| p.score.map(|s| (p.bot_id, s)))
-            .collect::<Vec<_>>();
-        if scores.is_empty() {
-            continue;
-        }
-        db.transaction(|txn| {
-            Box::pin(async move {
-                engine::db_update_stats_for_match(txn, m.id, scores).await?;
-                Ok::<(), engine::MyDbError>(())
-            })
-        })
-        .await?;
+    let rebuild = if cfg.dry_run {
+        recalculate::preview(&db, cfg.game_id).await?
+    } else {
+        recalculate::recalculate(&db, cfg.game_id).await?
+    };
+    let changed = rebuild
+        .diffs
+        .iter()
+        .filter(|d| d.changed())
+        .collect::<Vec<_>>();
+    for d in &changed {
+        println!(
+            "Bot {}: {} -> {}",
+            d.bot_id,
+            summary_text(&d.before),
+            summary_text(&d.after)
+        );
+    }
+    println!("{} of {} bots changed", changed.len(), rebuild.diffs.len());
+    if rebuild.deleted_matches > 0 {
+        println!(
+            "Warning: {} matches counted in the stats were deleted, they are left out",
+            rebuild.deleted_matches
+        );
     }
     Ok(())
 }
@@ -34,7 +34,9 @@ fn main() {
                 ],
                 position: (0.15, 0.3),
                 z_index: 10,
+                parent: None,
             },
+            visibility: Visibility::All,
         },
         TimedEvent {
             start_time: 0.1,
@@ -44,6 +46,7 @@ fn main() {
                 animate_function: AnimateFunction::Linear,
                 transform: Transform::Rotate(std::f32::consts::PI * 2. / 3.),
             },
+            visibility: Visibility::All,
         },
         TimedEvent {
             start_time: 0.5,
@@ -53,6 +56,7 @@ fn main() {
                 animate_function: AnimateFunction::EaseInOut,
                 transform: Transform::Move((0.3, 0.1)),
             },
+            visibility: Visibility::All,
         },
     ]);
     for ev in replay.events.iter() {
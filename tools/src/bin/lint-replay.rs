@@ -0,0 +1,101 @@
+// Checks match logs for invalid visualization events, see
+// proglad_api::visualize::validate. Takes log files, plain or gzip-compressed
+// as written by proglad-local, and/or ids of matches stored in the database.
+//
+// Example:
+//   lint-replay replay.gz
+//   lint-replay --db sqlite://proglad.db -m 123 -m 124
+use std::io::Read;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use clap::Parser;
+
+use proglad_api::visualize;
+use proglad_db as db;
+use proglad_server::file_store;
+
+#[derive(Parser, Debug)]
+struct Config {
+    #[arg(long)]
+    db: Option<String>,
+    #[arg(long, short = 'm')]
+    match_id: Vec<i64>,
+    files: Vec<PathBuf>,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let cfg = Config::parse();
+    let mut num_issues = 0;
+    for path in cfg.files.iter() {
+        let content = std::fs::read(path).context(format!("Failed to read {path:?}"))?;
+        num_issues += lint(&path.to_string_lossy(), &decompress(content)?);
+    }
+    if !cfg.match_id.is_empty() {
+        let Some(db_url) = &cfg.db else {
+            return Err(anyhow!("--db is required to read matches"));
+        };
+        let db = sea_orm::Database::connect(db_url).await?;
+        let file_store = file_store::FileStore::new();
+        for match_id in cfg.match_id.iter() {
+            let file = file_store
+                .read(
+                    &db,
+                    file_store::Requester::System,
+                    db::common::EntityKind::Match,
+                    Some(*match_id),
+                    "",
+                )
+                .await
+                .context(format!("Failed to read the replay of match {match_id}"))?;
+            let file = file_store::FileStore::decompress(file)?;
+            let content = String::from_utf8_lossy(file.content.as_deref().unwrap_or_default());
+            num_issues += lint(&format!("match {match_id}"), &content);
+        }
+    }
+    if num_issues > 0 {
+        return Err(anyhow!("Found {num_issues} issues"));
+    }
+    Ok(())
+}
+
+fn decompress(content: Vec<u8>) -> anyhow::Result<String> {
+    if !content.starts_with(&[0x1f, 0x8b]) {
+        return Ok(String::from_utf8_lossy(&content).into_owned());
+    }
+    let mut res = String::new();
+    flate2::read::GzDecoder::new(content.as_slice())
+        .read_to_string(&mut res)
+        .context("Failed to decompress")?;
+    Ok(res)
+}
+
+// Prints the issues prefixed with the log line number, returns their count.
+fn lint(name: &str, log: &str) -> usize {
+    let mut events = vec![];
+    let mut line_numbers = vec![];
+    let mut num_issues = 0;
+    for (i, line) in log.lines().enumerate() {
+        let Some(vis) = visualize::vis_payload(line) else {
+            continue;
+        };
+        // The same decoder as the visualizer's, so the lines reported here
+        // are exactly the ones it drops.
+        match visualize::decode_event(vis) {
+            Ok(event) => {
+                events.push(event);
+                line_numbers.push(i + 1);
+            }
+            Err(e) => {
+                println!("{name}:{}: {e}", i + 1);
+                num_issues += 1;
+            }
+        }
+    }
+    let issues = visualize::validate(&visualize::Replay::new(events));
+    for issue in issues.iter() {
+        println!("{name}:{}: {issue}", line_numbers[issue.event_index]);
+    }
+    num_issues + issues.len()
+}
@@ -1,249 +1,17 @@
 use std::cell::RefCell;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys::{
-    window, Document, HtmlElement, HtmlInputElement, Response, ScrollBehavior,
+    window, Document, HtmlElement, HtmlInputElement, HtmlSelectElement, Response, ScrollBehavior,
     ScrollIntoViewOptions, ScrollLogicalPosition, SvgElement,
 };
 
-use proglad_api::textapi;
+use proglad_api::render::{self, Object, ReplayState, SvgNode};
 use proglad_api::visualize::*;
 
 mod svg;
 
-// Engine
-
-#[derive(Debug)]
-struct VisibleObject {
-    z_index: u8,
-    geometry: Vec<geom::Geom>,
-    base_position: nalgebra::Vector2<f32>,
-    current_position: nalgebra::Vector2<f32>,
-    base_transform: nalgebra::Affine2<f32>,
-    current_transform: nalgebra::Affine2<f32>,
-}
-
-#[derive(Debug)]
-struct OngoingAnimation {
-    id: u64,
-    start_time: f32,
-    duration: f32,
-    transform: Transform,
-    animate_function: AnimateFunction,
-}
-
-fn map_animate_progress(f: AnimateFunction, progress: f32) -> f32 {
-    match f {
-        AnimateFunction::Step => {
-            if progress <= 0.5 {
-                0.0
-            } else {
-                1.0
-            }
-        }
-        AnimateFunction::Linear => progress,
-        AnimateFunction::EaseIn => 1.0 - (progress * std::f32::consts::PI * 0.5).cos(),
-        AnimateFunction::EaseOut => (progress * std::f32::consts::PI * 0.5).sin(),
-        AnimateFunction::EaseInOut => (1.0 + ((progress - 0.5) * std::f32::consts::PI).sin()) * 0.5,
-    }
-}
-
-impl OngoingAnimation {
-    pub fn apply_transform(&self, time: f32, obj: &mut VisibleObject) -> bool {
-        let elapsed = time - self.start_time;
-        if elapsed < 0. {
-            return false;
-        }
-        let (progress, done) = if elapsed >= self.duration {
-            (1., true)
-        } else {
-            (elapsed / self.duration, false)
-        };
-        let p = map_animate_progress(self.animate_function, progress);
-
-        match self.transform {
-            Transform::Move((dx, dy)) => {
-                let d = nalgebra::Vector2::new(dx, dy);
-                if done {
-                    obj.base_position += d;
-                }
-                obj.current_position += d * p;
-            }
-            Transform::Scale(s) => {
-                if done {
-                    obj.base_transform *= nalgebra::Affine2::from_matrix_unchecked(
-                        nalgebra::Scale2::new(s, s).to_homogeneous(),
-                    );
-                }
-                obj.current_transform *= nalgebra::Affine2::from_matrix_unchecked(
-                    nalgebra::Scale2::new(s * p + 1.0 - p, s * p + 1.0 - p).to_homogeneous(),
-                );
-            }
-            Transform::Rotate(phi) => {
-                if done {
-                    obj.base_transform *= nalgebra::Affine2::from_matrix_unchecked(
-                        nalgebra::Rotation2::new(phi).to_homogeneous(),
-                    );
-                }
-                obj.current_transform *= nalgebra::Affine2::from_matrix_unchecked(
-                    nalgebra::Rotation2::new(phi * p).to_homogeneous(),
-                );
-            }
-        };
-        done
-    }
-}
-
-struct ReplayState {
-    replay: Replay,
-    objects: HashMap<u64, VisibleObject>,
-    object_id_by_z_index: Vec<HashSet<u64>>,
-    next_event_idx: usize,
-    time: f32,
-    highlighted_log: std::ops::Range<usize>,
-    animations: Vec<OngoingAnimation>,
-}
-
-#[derive(Default)]
-struct UpdateResult {
-    reset: bool,
-    changed: Vec<u64>,
-    created: Vec<u64>,
-    deleted: Vec<u64>,
-    highlighted_log: std::ops::Range<usize>,
-    unhighlighted_log: std::ops::Range<usize>,
-}
-
-impl ReplayState {
-    fn new(replay: Replay) -> Self {
-        Self {
-            replay,
-            objects: HashMap::new(),
-            object_id_by_z_index: vec![HashSet::new(); 256],
-            next_event_idx: 0,
-            time: 0.,
-            animations: vec![],
-            highlighted_log: 0..0,
-        }
-    }
-    // Returns the ids of the objects that need updating.
-    fn update(&mut self, new_time: f32) -> UpdateResult {
-        if self.time > new_time {
-            let deleted: Vec<u64> = self.objects.keys().cloned().collect();
-            let unhighlight = self.highlighted_log.clone();
-            self.reset();
-            let mut upd = self.update(new_time);
-            upd.reset = true;
-            upd.deleted = deleted;
-            upd.unhighlighted_log = unhighlight;
-            if !upd.highlighted_log.is_empty() {
-                upd.highlighted_log.start = upd.highlighted_log.end - 1;
-            }
-            return upd;
-        }
-        self.time = new_time;
-        let mut res = UpdateResult::default();
-        let old_hilog = self.highlighted_log.clone();
-        while self.next_event_idx < self.replay.events.len()
-            && self.replay.events[self.next_event_idx].start_time <= new_time
-        {
-            self.process_event(self.next_event_idx, &mut res);
-            self.next_event_idx += 1;
-        }
-        if self.highlighted_log != old_hilog {
-            self.highlighted_log.start = old_hilog.end;
-            res.unhighlighted_log = old_hilog;
-            res.highlighted_log = self.highlighted_log.clone();
-        }
-        res.changed = self.animations.iter().map(|a| a.id).collect();
-        self.animate();
-        res
-    }
-    fn animate(&mut self) {
-        for (_, obj) in self.objects.iter_mut() {
-            obj.current_position = obj.base_position;
-            obj.current_transform = obj.base_transform;
-        }
-        self.animations.retain(|an| {
-            let t = self
-                .objects
-                .get_mut(&an.id)
-                .map(|obj| an.apply_transform(self.time, obj));
-            t.map_or(false, |x| !x)
-        });
-    }
-    fn reset(&mut self) {
-        self.highlighted_log = 0..0;
-        self.objects.clear();
-        for o in self.object_id_by_z_index.iter_mut() {
-            o.clear()
-        }
-        self.next_event_idx = 0;
-        self.time = 0.;
-        self.animations.clear();
-    }
-    fn process_event(&mut self, idx: usize, res: &mut UpdateResult) {
-        let event = &self.replay.events[idx];
-        match &event.event {
-            Event::Create {
-                id,
-                geometry,
-                position,
-                z_index,
-            } => {
-                self.objects.insert(
-                    *id,
-                    VisibleObject {
-                        base_position: nalgebra::Vector2::new(position.0, position.1),
-                        base_transform: nalgebra::Affine2::identity(),
-                        current_transform: nalgebra::Affine2::identity(),
-                        current_position: nalgebra::Vector2::new(position.0, position.1),
-                        geometry: geometry.clone(),
-                        z_index: *z_index,
-                    },
-                );
-                self.object_id_by_z_index[*z_index as usize].insert(*id);
-                res.created.push(*id);
-            }
-            Event::Destroy { id } => {
-                if let Some(o) = self.objects.remove(id) {
-                    self.object_id_by_z_index
-                        .get_mut(o.z_index as usize)
-                        .map(|m| m.remove(id));
-                    res.deleted.push(*id);
-                }
-            }
-            &Event::Transform {
-                id,
-                transform,
-                duration,
-                animate_function,
-            } => {
-                self.animations.push(OngoingAnimation {
-                    id,
-                    start_time: event.start_time,
-                    duration,
-                    animate_function,
-                    transform,
-                });
-            }
-            Event::Log { .. } => {
-                self.highlighted_log.end += 1;
-            }
-            Event::TickMarker => {
-                log::warn!("Not implemented: TickMarker");
-            }
-        }
-    }
-    fn get_log(&self) -> impl Iterator<Item = &str> {
-        self.replay.events.iter().filter_map(|e| match &e.event {
-            Event::Log { line } => Some(line.as_str()),
-            _ => None,
-        })
-    }
-}
-
 async fn fetch_text(url: &str) -> String {
     let fut = wasm_bindgen_futures::JsFuture::from;
     let resp = fut(web_sys::window().unwrap().fetch_with_str(url))
@@ -255,30 +23,74 @@ async fn fetch_text(url: &str) -> String {
     String::try_from(value).unwrap()
 }
 
-fn parse_vis_line(l: &str) -> Option<&str> {
-    let [_, _, vis, rest] = textapi::split(l);
-    if vis != "vis" {
-        return None;
-    }
-    Some(rest)
-}
-
 #[cfg(target_arch = "wasm32")]
 #[wasm_bindgen::prelude::wasm_bindgen]
-pub async fn run(replay_url: &str) {
+// Images are loaded from `assets_url` followed by the file name.
+pub async fn run(replay_url: &str, assets_url: &str) {
     wasm_logger::init(wasm_logger::Config::default());
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
 
     log::info!("Fetching {replay_url}");
     let j = fetch_text(replay_url).await;
     log::info!("Received replay : {}", j.len());
-    let document = window().unwrap().document().unwrap();
+    start(
+        &[("", &j)],
+        Assets {
+            url: assets_url.to_owned(),
+            embedded: HashMap::new(),
+        },
+    );
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+// Plays a log embedded into the page, for exported replays. `assets` is a JSON
+// object mapping image file names to data URLs.
+pub fn run_embedded(log: &str, assets: &str) {
+    wasm_logger::init(wasm_logger::Config::default());
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    let embedded = serde_json::from_str(assets).unwrap_or_else(|e| {
+        log::error!("Failed to decode embedded assets: {e}");
+        HashMap::new()
+    });
+    start(
+        &[("", log)],
+        Assets {
+            url: String::new(),
+            embedded,
+        },
+    );
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
+// Plays two replays side by side on one timeline, e.g. of two versions of a
+// bot on the same seed. The page has two of each canvas, inner-svg and log
+// elements, with "-a" and "-b" appended to their ids.
+pub async fn run_compare(replay_url_a: &str, replay_url_b: &str, assets_url: &str) {
+    wasm_logger::init(wasm_logger::Config::default());
+    std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+
+    log::info!("Fetching {replay_url_a} and {replay_url_b}");
+    let a = fetch_text(replay_url_a).await;
+    let b = fetch_text(replay_url_b).await;
+    start(
+        &[("-a", &a), ("-b", &b)],
+        Assets {
+            url: assets_url.to_owned(),
+            embedded: HashMap::new(),
+        },
+    );
+}
+
+fn parse_replay(j: &str) -> Replay {
     let mut events = Vec::<TimedEvent>::new();
     let mut time = 0f32;
     for log_line in j.lines() {
-        match parse_vis_line(log_line) {
+        match vis_payload(log_line) {
             Some(vis) => {
-                let ev = match serde_hjson::from_str::<TimedEvent>(vis) {
+                let ev = match decode_event(vis) {
                     Ok(ev) => ev,
                     Err(e) => {
                         log::error!("Failed to decode vis line: {e}");
@@ -294,27 +106,85 @@ pub async fn run(replay_url: &str) {
                     event: Event::Log {
                         line: log_line.to_owned(),
                     },
+                    visibility: Visibility::All,
                 });
             }
         }
     }
-    let replay = Replay::new(events);
+    Replay::new(events)
+}
+
+// Each log is shown in the elements with ids suffixed by its first member.
+fn start(logs: &[(&str, &str)], assets: Assets) {
+    let document = window().unwrap().document().unwrap();
+    let replays = logs
+        .iter()
+        .map(|(suffix, j)| (*suffix, parse_replay(j)))
+        .collect::<Vec<_>>();
+    let duration = replays.iter().map(|(_, r)| r.duration).fold(0f32, f32::max);
     let slider = get_element_by_id_unchecked::<HtmlInputElement>("progress-slider");
     let playpause = elem("playpause");
+    let chapters = elem("chapters");
+    for (_, replay) in replays.iter().filter(|_| duration > 0.) {
+        for (t, label) in replay.ticks() {
+            let option = document.create_element("option").unwrap();
+            let _ = option.set_attribute("value", &format!("{}", t / duration));
+            if let Some(label) = label {
+                let _ = option.set_attribute("label", label);
+            }
+            chapters.append_child(&option).unwrap();
+        }
+    }
+    // Only offered when some events are hidden from some viewers.
+    let perspective = get_element_by_id_unchecked::<HtmlSelectElement>("perspective");
+    let mut players = replays
+        .iter()
+        .flat_map(|(_, r)| r.players_with_hidden_events())
+        .collect::<Vec<_>>();
+    players.sort();
+    players.dedup();
+    if replays
+        .iter()
+        .any(|(_, r)| r.events.iter().any(|e| !e.visibility.is_all()))
+    {
+        let options = [
+            ("all".to_owned(), "Everything".to_owned()),
+            ("spectator".to_owned(), "Spectator".to_owned()),
+        ]
+        .into_iter()
+        .chain(
+            players
+                .iter()
+                .map(|p| (p.to_string(), format!("Player {p}"))),
+        );
+        for (value, text) in options {
+            let option = document.create_element("option").unwrap();
+            let _ = option.set_attribute("value", &value);
+            option.set_text_content(Some(&text));
+            perspective.append_child(&option).unwrap();
+        }
+        perspective.set_hidden(false);
+    }
     let progress_cb = {
         let slider = slider.clone();
         Box::new(move |progress: f32| {
             slider.set_value(format!("{progress}").as_str());
         })
     };
-    let handler = Rc::new(RefCell::new(MyHandler::new(
-        document,
-        get_element_by_id_unchecked("canvas"),
-        get_element_by_id_unchecked("log"),
-        get_element_by_id_unchecked("inner-svg"),
-        ReplayState::new(replay),
-        progress_cb,
-    )));
+    let views = replays
+        .into_iter()
+        .map(|(suffix, replay)| {
+            View::new(
+                document.clone(),
+                get_element_by_id_unchecked(&format!("canvas{suffix}")),
+                get_element_by_id_unchecked(&format!("log{suffix}")),
+                get_element_by_id_unchecked(&format!("inner-svg{suffix}")),
+                assets.clone(),
+                ReplayState::new(replay),
+            )
+        })
+        .collect();
+    let handler = Rc::new(RefCell::new(MyHandler::new(views, progress_cb)));
     {
         let slider1 = slider.clone();
         let handler = handler.clone();
@@ -344,6 +214,143 @@ pub async fn run(replay_url: &str) {
         playpause.set_onclick(Some(cb.as_ref().unchecked_ref()));
         cb.forget();
     }
+    let tick_buttons: [(&str, fn() -> UserEvent); 2] = [
+        ("prev-tick", || UserEvent::PrevTick),
+        ("next-tick", || UserEvent::NextTick),
+    ];
+    for (id, user_event) in tick_buttons {
+        let handler = handler.clone();
+        let cb = wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+            handler.borrow_mut().on_user_event(user_event());
+        });
+        elem(id).set_onclick(Some(cb.as_ref().unchecked_ref()));
+        cb.forget();
+    }
+    let speed = get_element_by_id_unchecked::<HtmlSelectElement>("speed");
+    {
+        let perspective1 = perspective.clone();
+        let handler = handler.clone();
+        let cb = wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+            let viewer = match perspective1.value().as_str() {
+                "spectator" => Viewer::Spectator,
+                p => match p.parse::<usize>() {
+                    Ok(p) => Viewer::Players(vec![p]),
+                    Err(_) => Viewer::Omniscient,
+                },
+            };
+            handler
+                .borrow_mut()
+                .on_user_event(UserEvent::Perspective(viewer));
+        });
+        perspective.set_onchange(Some(cb.as_ref().unchecked_ref()));
+        cb.forget();
+    }
+    {
+        let speed1 = speed.clone();
+        let handler = handler.clone();
+        let cb = wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+            let val = speed1.value().parse::<f32>().unwrap_or(1.);
+            handler.borrow_mut().on_user_event(UserEvent::Speed(val));
+        });
+        speed.set_onchange(Some(cb.as_ref().unchecked_ref()));
+        cb.forget();
+    }
+    {
+        // Arrows jump between the ticks, space plays/pauses, [ and ] change the speed.
+        let handler = handler.clone();
+        let playpause = playpause.clone();
+        let cb = wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::KeyboardEvent)>::new(
+            move |e: web_sys::KeyboardEvent| {
+                let user_event = match e.key().as_str() {
+                    "ArrowLeft" => UserEvent::PrevTick,
+                    "ArrowRight" => UserEvent::NextTick,
+                    " " => {
+                        e.prevent_default();
+                        playpause.click();
+                        return;
+                    }
+                    key @ ("[" | "]") => {
+                        let idx = speed.selected_index();
+                        let idx = if key == "[" { idx - 1 } else { idx + 1 };
+                        if idx < 0 || idx >= speed.length() as i32 {
+                            return;
+                        }
+                        speed.set_selected_index(idx);
+                        UserEvent::Speed(speed.value().parse::<f32>().unwrap_or(1.))
+                    }
+                    _ => return,
+                };
+                e.prevent_default();
+                handler.borrow_mut().on_user_event(user_event);
+            },
+        );
+        document
+            .add_event_listener_with_callback("keydown", cb.as_ref().unchecked_ref())
+            .unwrap();
+        cb.forget();
+    }
+    // Panning and zooming any of the canvases moves all of them.
+    for (suffix, _) in logs {
+        let canvas = get_element_by_id_unchecked::<SvgElement>(&format!("canvas{suffix}"));
+        let canvas_point = {
+            let canvas = canvas.clone();
+            move |e: &web_sys::MouseEvent| {
+                let rect = canvas.get_bounding_client_rect();
+                (
+                    (e.client_x() as f64 - rect.left()) as f32,
+                    (e.client_y() as f64 - rect.top()) as f32,
+                )
+            }
+        };
+        {
+            let handler = handler.clone();
+            let canvas_point = canvas_point.clone();
+            let cb = wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::WheelEvent)>::new(
+                move |e: web_sys::WheelEvent| {
+                    e.prevent_default();
+                    let factor = (-e.delta_y() as f32 * 0.0015).exp();
+                    let at = canvas_point(&e);
+                    handler
+                        .borrow_mut()
+                        .on_user_event(UserEvent::Zoom { factor, at });
+                },
+            );
+            canvas
+                .add_event_listener_with_callback("wheel", cb.as_ref().unchecked_ref())
+                .unwrap();
+            cb.forget();
+        }
+        let mouse_events: [(&str, fn((f32, f32)) -> UserEvent); 5] = [
+            ("mousedown", UserEvent::DragStart),
+            ("mousemove", UserEvent::DragMove),
+            ("mouseup", |_| UserEvent::DragEnd),
+            ("mouseleave", |_| UserEvent::DragEnd),
+            ("dblclick", |_| UserEvent::ResetView),
+        ];
+        for (event, make_user_event) in mouse_events {
+            let handler = handler.clone();
+            let canvas_point = canvas_point.clone();
+            let cb = wasm_bindgen::closure::Closure::<dyn FnMut(web_sys::MouseEvent)>::new(
+                move |e: web_sys::MouseEvent| {
+                    handler
+                        .borrow_mut()
+                        .on_user_event(make_user_event(canvas_point(&e)));
+                },
+            );
+            canvas
+                .add_event_listener_with_callback(event, cb.as_ref().unchecked_ref())
+                .unwrap();
+            cb.forget();
+        }
+    }
+    {
+        let handler = handler.clone();
+        let cb = wasm_bindgen::closure::Closure::<dyn FnMut()>::new(move || {
+            handler.borrow_mut().on_user_event(UserEvent::ResetView);
+        });
+        elem("reset-view").set_onclick(Some(cb.as_ref().unchecked_ref()));
+        cb.forget();
+    }
     //handler.borrow_mut().on_draw();
     // TODO: make this actually not use CPU.
     anim(move || handler.borrow_mut().on_draw());
@@ -367,6 +374,28 @@ impl Timer {
     }
 }
 
+// Images are loaded from `url` followed by the file name, unless embedded.
+#[derive(Clone)]
+struct Assets {
+    url: String,
+    embedded: HashMap<String, String>,
+}
+
+impl Assets {
+    fn embed(&self, node: &mut SvgNode) {
+        for (name, value) in node.attributes.iter_mut() {
+            if *name == "href" {
+                if let Some(data_url) = self.embedded.get(value.as_str()) {
+                    value.clone_from(data_url);
+                }
+            }
+        }
+        for child in node.children.iter_mut() {
+            self.embed(child);
+        }
+    }
+}
+
 struct Surface {
     document: Document,
     size_x: f32,
@@ -374,14 +403,23 @@ struct Surface {
     groups_by_z: Vec<SvgElement>,
     canvas: SvgElement,
     svg_group: SvgElement,
+    assets: Assets,
+    // Pixels per board unit.
+    scale: f32,
+    camera: Camera,
 }
 
 impl Surface {
-    pub fn new(document: Document, canvas: SvgElement, svg_group: SvgElement) -> Self {
+    pub fn new(
+        document: Document,
+        canvas: SvgElement,
+        svg_group: SvgElement,
+        assets: Assets,
+    ) -> Self {
         let mut groups_by_z = Vec::with_capacity(256);
         for _ in 0..256 {
             let group: SvgElement = document
-                .create_element_ns(Some("http://www.w3.org/2000/svg"), "g")
+                .create_element_ns(Some(render::SVG_NS), "g")
                 .unwrap()
                 .dyn_into()
                 .unwrap();
@@ -396,74 +434,77 @@ impl Surface {
             groups_by_z,
             canvas,
             svg_group,
+            assets,
             size_x: w as f32,
             size_y: w as f32,
+            scale: w as f32,
+            camera: Camera::new(1.),
         }
     }
-    pub fn update_size(&mut self) {
-        let w = self.canvas.client_width();
-        let h = self.canvas.client_height();
-        let w = w.min(h);
-        self.size_x = w as f32;
-        self.size_y = w as f32;
+    // Fits the part of the board seen by the camera into the canvas.
+    pub fn update_size(&mut self, aspect: f32, camera: Camera) {
+        let w = self.canvas.client_width() as f32;
+        let h = self.canvas.client_height() as f32;
+        let (view_w, view_h) = camera.view_size(aspect);
+        self.size_x = w;
+        self.size_y = h;
+        self.scale = (w / view_w).min(h / view_h);
+        self.camera = camera;
         self.svg_group
-            .set_attribute("transform", &format!("scale({} {})", w, w))
+            .set_attribute(
+                "transform",
+                &format!(
+                    "translate({} {}) scale({}) translate({} {})",
+                    w * 0.5,
+                    h * 0.5,
+                    self.scale,
+                    -camera.center.0,
+                    -camera.center.1
+                ),
+            )
             .unwrap();
     }
+    // Converts a point on the canvas, in pixels, to board coordinates.
+    pub fn to_board(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        (
+            self.camera.center.0 + (x - self.size_x * 0.5) / self.scale,
+            self.camera.center.1 + (y - self.size_y * 0.5) / self.scale,
+        )
+    }
 }
-struct MyHandler {
+// One replay on one canvas, with its log.
+struct View {
     replay_state: ReplayState,
-    timer: Timer,
-    prev_time: f32,
-    progress_time: f32,
-    progress_callback: Box<dyn FnMut(f32)>,
-    paused: bool,
     surface: Surface,
     object_elements: HashMap<u64, SvgElement>,
     log_list: HtmlElement,
+    // As served, replay_state shows the part of it the selected viewer sees.
+    full_replay: Replay,
 }
 
-impl MyHandler {
+impl View {
     pub fn new(
         document: Document,
         canvas: SvgElement,
         log_list: HtmlElement,
         svg_group: SvgElement,
+        assets: Assets,
         replay_state: ReplayState,
-        progress_callback: Box<dyn FnMut(f32)>,
     ) -> Self {
-        for line in replay_state.get_log() {
-            log_list
-                .append_child(&make_log_element(&document, line))
-                .unwrap();
-        }
+        fill_log_list(&document, &log_list, &replay_state);
+        let full_replay = replay_state.replay.clone();
         Self {
             replay_state,
-            timer: Timer::new(),
-            prev_time: 0.0,
-            progress_time: 0.0,
-            paused: true,
-            progress_callback,
-            surface: Surface::new(document.clone(), canvas, svg_group),
+            surface: Surface::new(document, canvas, svg_group, assets),
             object_elements: HashMap::default(),
             log_list,
+            full_replay,
         }
     }
-    fn time_delta(&mut self) -> f32 {
-        let t = self.timer.secs_elapsed() as f32;
-        let d = t - self.prev_time;
-        self.prev_time = t;
-        d
-    }
-    fn on_draw(&mut self) {
-        self.surface.update_size();
-        if !self.paused {
-            self.progress_time += self.time_delta();
-            (self.progress_callback)(self.progress_time / self.replay_state.replay.duration);
-        } else {
-            self.time_delta();
-        }
-        let update_result = self.replay_state.update(self.progress_time);
+    fn on_draw(&mut self, time: f32, user_camera: Option<Camera>) {
+        let update_result = self.replay_state.update(time);
+        self.surface
+            .update_size(self.replay_state.replay.aspect, self.camera(user_camera));
         for id in update_result.deleted {
             let Some(elt) = self.object_elements.get(&id) else {
                 continue;
@@ -472,19 +513,31 @@ impl MyHandler {
             self.object_elements.remove(&id).inspect(|e| e.remove());
         }
         for id in update_result.created {
-            self.object_elements.insert(
-                id,
-                create_element(&self.surface, self.replay_state.objects.get(&id).unwrap()),
-            );
+            // Might have been destroyed right away, e.g. together with its parent.
+            let Some(obj) = self.replay_state.object(id) else {
+                continue;
+            };
+            self.object_elements
+                .insert(id, create_element(&self.surface, obj));
+        }
+        for id in update_result.restyled {
+            let (Some(elt), Some(obj)) =
+                (self.object_elements.get(&id), self.replay_state.object(id))
+            else {
+                continue;
+            };
+            elt.remove();
+            self.object_elements
+                .insert(id, create_element(&self.surface, obj));
         }
         for id in update_result.changed {
             let (Some(elt), Some(obj)) = (
                 self.object_elements.get_mut(&id),
-                self.replay_state.objects.get(&id),
+                self.replay_state.object(id),
             ) else {
                 continue;
             };
-            elt.set_attribute("transform", &make_transform_attribute(obj))
+            elt.set_attribute("transform", &obj.world_transform.to_string())
                 .unwrap();
         }
         for i in update_result.unhighlighted_log {
@@ -511,25 +564,172 @@ impl MyHandler {
         child.scroll_into_view_with_scroll_into_view_options(&opts);
         child.class_list().add_1("highlight").unwrap();
     }
+    fn mark_diverged_log_element(&self, index: usize) {
+        let Some(child) = self.log_list.children().item(index as u32) else {
+            return;
+        };
+        child.class_list().add_1("diverged").unwrap();
+    }
+    // Starts over with the events the viewer sees.
+    fn set_viewer(&mut self, viewer: &Viewer) {
+        for (_, elt) in self.object_elements.drain() {
+            elt.remove();
+        }
+        self.replay_state = ReplayState::new(self.full_replay.for_viewer(viewer));
+        fill_log_list(&self.surface.document, &self.log_list, &self.replay_state);
+    }
+    fn camera(&self, user_camera: Option<Camera>) -> Camera {
+        user_camera.unwrap_or(self.replay_state.current_camera())
+    }
+}
+
+struct MyHandler {
+    views: Vec<View>,
+    timer: Timer,
+    prev_time: f32,
+    progress_time: f32,
+    progress_callback: Box<dyn FnMut(f32)>,
+    paused: bool,
+    // Set when the user pans or zooms, overrides the camera from the replay.
+    user_camera: Option<Camera>,
+    drag_from: Option<(f32, f32)>,
+    // Playback speed multiplier.
+    speed: f32,
+    ticks: Vec<f32>,
+    duration: f32,
+}
+
+// Going to the previous tick right after passing one goes to the one before.
+const PREV_TICK_GRACE_SECS: f32 = 0.3;
+
+impl MyHandler {
+    pub fn new(views: Vec<View>, progress_callback: Box<dyn FnMut(f32)>) -> Self {
+        let mut handler = Self {
+            views,
+            timer: Timer::new(),
+            prev_time: 0.0,
+            progress_time: 0.0,
+            paused: true,
+            progress_callback,
+            user_camera: None,
+            drag_from: None,
+            speed: 1.0,
+            ticks: vec![],
+            duration: 0.0,
+        };
+        handler.on_replays_changed();
+        handler
+    }
+    // Recomputes what is derived from all of the shown replays.
+    fn on_replays_changed(&mut self) {
+        let replays = self.views.iter().map(|v| &v.replay_state.replay);
+        self.duration = replays.clone().map(|r| r.duration).fold(0., f32::max);
+        self.ticks = replays.flat_map(|r| r.ticks().map(|(t, _)| t)).collect();
+        self.ticks.sort_by(f32::total_cmp);
+        self.ticks.dedup();
+        if let [a, b] = &self.views[..] {
+            let diverged = first_divergence(a.replay_state.get_log(), b.replay_state.get_log());
+            if let Some(i) = diverged {
+                a.mark_diverged_log_element(i);
+                b.mark_diverged_log_element(i);
+            }
+        }
+    }
+    fn time_delta(&mut self) -> f32 {
+        let t = self.timer.secs_elapsed() as f32;
+        let d = t - self.prev_time;
+        self.prev_time = t;
+        d
+    }
+    fn on_draw(&mut self) {
+        if !self.paused {
+            self.progress_time += self.time_delta() * self.speed;
+            (self.progress_callback)(self.progress_time / self.duration);
+        } else {
+            self.time_delta();
+        }
+        for view in self.views.iter_mut() {
+            view.on_draw(self.progress_time, self.user_camera);
+        }
+    }
     fn on_user_event(&mut self, user_event: UserEvent) {
         match user_event {
             UserEvent::ProgressChange(progress) => {
-                self.progress_time = self.replay_state.replay.duration * progress;
+                self.progress_time = self.duration * progress;
             }
             UserEvent::PlayPause => {
-                if self.progress_time >= self.replay_state.replay.duration {
+                if self.progress_time >= self.duration {
                     self.progress_time = 0.0;
                 }
                 self.paused = !self.paused;
             }
+            UserEvent::Zoom { factor, at } => {
+                // The canvases are the same size, the first one stands for all.
+                let view = &self.views[0];
+                let mut camera = view.camera(self.user_camera);
+                // Keep the point under the cursor in place.
+                let (x, y) = view.surface.to_board(at);
+                camera.zoom *= factor;
+                camera.center = (
+                    x - (x - camera.center.0) / factor,
+                    y - (y - camera.center.1) / factor,
+                );
+                self.user_camera = Some(camera);
+            }
+            UserEvent::DragStart(at) => self.drag_from = Some(at),
+            UserEvent::DragMove(at) => {
+                let Some(from) = self.drag_from else {
+                    return;
+                };
+                let view = &self.views[0];
+                let mut camera = view.camera(self.user_camera);
+                camera.center.0 -= (at.0 - from.0) / view.surface.scale;
+                camera.center.1 -= (at.1 - from.1) / view.surface.scale;
+                self.user_camera = Some(camera);
+                self.drag_from = Some(at);
+            }
+            UserEvent::DragEnd => self.drag_from = None,
+            UserEvent::ResetView => self.user_camera = None,
+            UserEvent::NextTick => {
+                let t = self.ticks.iter().find(|t| **t > self.progress_time);
+                if let Some(&t) = t {
+                    self.seek(t);
+                }
+            }
+            UserEvent::PrevTick => {
+                let threshold = self.progress_time - PREV_TICK_GRACE_SECS * self.speed;
+                let t = self.ticks.iter().rev().find(|t| **t < threshold);
+                self.seek(t.cloned().unwrap_or(0.));
+            }
+            UserEvent::Speed(speed) => self.speed = speed,
+            UserEvent::Perspective(viewer) => {
+                for view in self.views.iter_mut() {
+                    view.set_viewer(&viewer);
+                }
+                self.on_replays_changed();
+            }
         }
     }
+    fn seek(&mut self, time: f32) {
+        self.progress_time = time;
+        (self.progress_callback)(self.progress_time / self.duration);
+    }
 }
 
 #[derive(Debug)]
 enum UserEvent {
     ProgressChange(f32),
     PlayPause,
+    // Positions are in pixels relative to the canvas.
+    Zoom { factor: f32, at: (f32, f32) },
+    DragStart((f32, f32)),
+    DragMove((f32, f32)),
+    DragEnd,
+    ResetView,
+    NextTick,
+    PrevTick,
+    Speed(f32),
+    Perspective(Viewer),
 }
 
 fn get_element_by_id_unchecked<T: JsCast>(id: &str) -> T {
@@ -558,67 +758,22 @@ fn anim<F: Fn() + Clone + 'static>(cb: F) {
     closure.forget();
 }
 
-fn create_element(surface: &Surface, obj: &VisibleObject) -> SvgElement {
-    let document = &surface.document;
-    let group = svg::group(document, (obj.base_position.x, obj.base_position.y));
-    for g in obj.geometry.iter() {
-        let elt: SvgElement = match g {
-            geom::Geom::Circle {
-                center,
-                radius,
-                fill_color,
-                stroke_color,
-                thickness,
-            } => {
-                log::info!("Create circle");
-                svg::circle(
-                    document,
-                    *center,
-                    *radius,
-                    *fill_color,
-                    *stroke_color,
-                    *thickness,
-                )
-                .into()
-            }
-            geom::Geom::Line {
-                from,
-                to,
-                thickness,
-                color,
-            } => svg::line(document, *from, *to, *thickness, *color).into(),
-            geom::Geom::Text {
-                text,
-                position,
-                size,
-                color,
-            } => svg::text(document, position.0, position.1, *size, text, *color),
-            geom::Geom::Polygon {
-                vs,
-                fill_color,
-                stroke_color,
-                thickness,
-            } => svg::polygon(document, vs, *fill_color, *stroke_color, *thickness).into(),
-        };
-        group.append_child(&elt).unwrap();
-    }
+fn create_element(surface: &Surface, obj: &Object) -> SvgElement {
+    let mut node = render::object_node(obj, &surface.assets.url);
+    surface.assets.embed(&mut node);
+    let group = svg::element(&surface.document, &node);
     let z = (obj.z_index as usize).min(surface.groups_by_z.len());
     surface.groups_by_z[z].append_child(&group).unwrap();
     group
 }
 
-fn make_transform_attribute(obj: &VisibleObject) -> String {
-    let matrix = obj.current_transform.matrix();
-    let scale = matrix.determinant().sqrt();
-    let angle = (matrix.get((0, 0)).unwrap() / scale)
-        .clamp(-1., 1.)
-        .acos()
-        * 180.
-        / std::f32::consts::PI;
-    format!(
-        "translate({} {}) scale({}) rotate({})",
-        obj.current_position.x, obj.current_position.y, scale, angle
-    )
+fn fill_log_list(document: &Document, log_list: &HtmlElement, replay_state: &ReplayState) {
+    log_list.set_inner_html("");
+    for line in replay_state.get_log() {
+        log_list
+            .append_child(&make_log_element(document, line))
+            .unwrap();
+    }
 }
 
 fn make_log_element(document: &Document, line: &str) -> HtmlElement {